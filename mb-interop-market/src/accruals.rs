@@ -0,0 +1,136 @@
+use mb_sdk::{
+    interfaces::ext_new_market,
+    near_assert,
+    near_sdk::{
+        self,
+        env,
+        json_types::U128,
+        AccountId,
+        Balance,
+        Promise,
+    },
+    utils::ft_transfer,
+};
+
+use crate::{
+    data::*,
+    Market,
+    MarketExt,
+};
+
+#[near_sdk::near_bindgen]
+impl Market {
+    /// Transfers the FTs from `ft_contract_id` that have accrued for the
+    /// caller from payouts below `min_ft_payout`. The accrued balance must
+    /// have reached `min_ft_payout`. Requires a deposit of one yoctoNEAR to
+    /// cover the `ft_transfer`.
+    #[payable]
+    pub fn claim_ft_accruals(&mut self, ft_contract_id: AccountId) -> Promise {
+        near_sdk::assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let key = (ft_contract_id.clone(), account_id.clone());
+        let amount = self.ft_accruals.get(&key).unwrap_or(0);
+        near_assert!(amount > 0, "There are no accrued FTs to claim");
+        near_assert!(
+            amount >= self.min_ft_payout,
+            "Accrued amount of {} is below the minimum payout of {}",
+            amount,
+            self.min_ft_payout
+        );
+
+        self.ft_accruals.remove(&key);
+        self.decrease_ft_accruals_total(&ft_contract_id, amount);
+
+        ft_transfer(ft_contract_id.clone(), account_id.clone(), amount).then(
+            ext_new_market::ext(env::current_account_id())
+                .with_static_gas(FT_RESOLVE_CLAIM_ACCRUALS_GAS)
+                .ft_resolve_claim_accruals(
+                    ft_contract_id,
+                    account_id,
+                    amount.into(),
+                ),
+        )
+    }
+
+    /// Callback for `claim_ft_accruals`, crediting the amount back to the
+    /// claimant if the transfer failed.
+    #[private]
+    pub fn ft_resolve_claim_accruals(
+        &mut self,
+        ft_contract_id: AccountId,
+        account_id: AccountId,
+        amount: U128,
+    ) {
+        if let near_sdk::PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        self.accrue_ft(&ft_contract_id, &account_id, amount.0);
+    }
+
+    /// Show the FTs from `ft_contract_id` that have accrued for `account_id`
+    /// and can be claimed via `claim_ft_accruals`.
+    pub fn get_ft_accruals(
+        &self,
+        ft_contract_id: AccountId,
+        account_id: AccountId,
+    ) -> U128 {
+        self.ft_accruals
+            .get(&(ft_contract_id, account_id))
+            .unwrap_or(0)
+            .into()
+    }
+
+    /// Transfers FTs as part of a payout. Amounts below `min_ft_payout` are
+    /// credited to the receiver instead, as some FT contracts reject
+    /// transferring tiny or zero amounts. Returns whether a transfer has been
+    /// made.
+    pub(crate) fn ft_payout(
+        &mut self,
+        ft_contract_id: &AccountId,
+        receiver_id: AccountId,
+        amount: Balance,
+    ) -> bool {
+        if amount == 0 {
+            return false;
+        }
+        if amount < self.min_ft_payout {
+            self.accrue_ft(ft_contract_id, &receiver_id, amount);
+            return false;
+        }
+        ft_transfer(ft_contract_id.clone(), receiver_id, amount);
+        true
+    }
+
+    /// Sum of all FT amounts from the given contract that have accrued and
+    /// not yet been claimed.
+    pub(crate) fn ft_accruals_total(
+        &self,
+        ft_contract_id: &AccountId,
+    ) -> Balance {
+        self.ft_accruals_total.get(ft_contract_id).unwrap_or(0)
+    }
+
+    fn accrue_ft(
+        &mut self,
+        ft_contract_id: &AccountId,
+        account_id: &AccountId,
+        amount: Balance,
+    ) {
+        let key = (ft_contract_id.clone(), account_id.clone());
+        let accrued = self.ft_accruals.get(&key).unwrap_or(0);
+        self.ft_accruals.insert(&key, &(accrued + amount));
+        let total = self.ft_accruals_total(ft_contract_id);
+        self.ft_accruals_total
+            .insert(ft_contract_id, &(total + amount));
+    }
+
+    fn decrease_ft_accruals_total(
+        &mut self,
+        ft_contract_id: &AccountId,
+        amount: Balance,
+    ) {
+        let total = self.ft_accruals_total(ft_contract_id);
+        self.ft_accruals_total
+            .insert(ft_contract_id, &(total - amount));
+    }
+}
@@ -0,0 +1,143 @@
+use mb_sdk::{
+    near_assert,
+    near_panic,
+    near_sdk::{
+        self,
+        env,
+        AccountId,
+        Balance,
+        Promise,
+    },
+};
+
+use crate::{
+    data::*,
+    offers::log_refund_offer,
+    Market,
+    MarketExt,
+};
+
+#[near_sdk::near_bindgen]
+impl Market {
+    /// Settles an auction that has ended by selling the token to the highest
+    /// bidder. The payout is processed the same way as for `buy` or
+    /// `ft_transfer_call`, and should the sale fail, the bid is refunded.
+    /// Anyone can call this.
+    ///
+    /// Auctions that have ended without any bids cannot be settled, but the
+    /// token owner can unlist them.
+    pub fn settle_auction(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> Promise {
+        let token_key = token_key(&nft_contract_id, &token_id);
+        let mut listing = match self.get_listing_internal(&token_key) {
+            None => env::panic_str(ERR_LISTING_NOT_FOUND),
+            Some(l) => l,
+        };
+        let auction = match self.auctions.get(&token_key) {
+            None => near_panic!("This NFT is not auctioned"),
+            Some(auction) => auction,
+        };
+        near_assert!(
+            env::block_timestamp() >= auction.ends_at,
+            "Auction cannot be settled before timestamp {}",
+            auction.ends_at / 1_000_000_000
+        );
+        self.assert_not_resolving(&token_key);
+        let offer = match listing.current_offer.take() {
+            None => near_panic!(
+                "Auction has ended without bids, the listing can be removed via `unlist`"
+            ),
+            Some(offer) => offer,
+        };
+
+        // timeouts for resolving the payout start with the settlement
+        let (receiver_id, amount) = (offer.offerer_id.clone(), offer.amount);
+        listing.current_offer = Some(offer);
+        self.listings.insert(&token_key, &listing);
+        self.record_offer_start(&token_key);
+
+        self.execute_transfer(listing, receiver_id, amount, 0)
+    }
+
+    /// Escrows `bid` as the new `current_offer` of an auctioned listing, and
+    /// refunds the bid that it replaces. Returns the reason if the bid cannot
+    /// be accepted, in which case the caller needs to refund it.
+    pub(crate) fn place_bid(
+        &mut self,
+        mut listing: Listing,
+        auction: &Auction,
+        bid: Offer,
+    ) -> Result<(), String> {
+        if let Some(reason) =
+            self.bid_blocked_reason(&listing, auction, bid.amount)
+        {
+            return Err(reason);
+        }
+
+        self.log_make_offer(&listing, &bid);
+        self.lock_ft(&listing.currency, bid.amount);
+
+        if let Some(outbid) = listing.current_offer.replace(bid) {
+            self.unlock_ft(&listing.currency, outbid.amount);
+            log_refund_offer(
+                listing.nft_contract_id.clone(),
+                listing.nft_token_id.clone(),
+                outbid.offerer_id.clone(),
+                &listing.currency,
+                outbid.amount,
+                OFFER_REFUND_REASON_OUTBID,
+            );
+            match listing.currency.get_ft_contract_id() {
+                None => {
+                    Promise::new(outbid.offerer_id).transfer(outbid.amount);
+                }
+                Some(ft_contract_id) => {
+                    self.ft_payout(
+                        &ft_contract_id,
+                        outbid.offerer_id,
+                        outbid.amount,
+                    );
+                }
+            }
+        }
+        self.listings.insert(&listing.token_key(), &listing);
+        self.record_offer_start(&listing.token_key());
+        Ok(())
+    }
+
+    /// Reason why a bid of `amount` on an auctioned listing would currently
+    /// be refunded, `None` if it would be accepted.
+    pub(crate) fn bid_blocked_reason(
+        &self,
+        listing: &Listing,
+        auction: &Auction,
+        amount: Balance,
+    ) -> Option<String> {
+        if env::block_timestamp() >= auction.ends_at {
+            return Some("Auction has ended".into());
+        }
+        match listing.current_offer.as_ref() {
+            None if amount < auction.min_bid => {
+                Some(format!("Bid needs to be at least {}", auction.min_bid))
+            }
+            Some(offer) if amount <= offer.amount => Some(format!(
+                "Bid needs to be higher than the current bid of {}",
+                offer.amount
+            )),
+            _ => None,
+        }
+    }
+
+    /// Panics if the listing is auctioned and its bid is merely escrowed,
+    /// not being settled.
+    pub(crate) fn assert_not_running_auction(&self, token_key: &String) {
+        near_assert!(
+            !self.auctions.contains_key(token_key)
+                || self.resolving.contains(token_key),
+            "Bids on auctions can only be released while the auction is settled"
+        );
+    }
+}
@@ -1,38 +1,126 @@
-use mb_sdk::near_sdk::{
-    self,
-    borsh::{
+use std::{
+    collections::HashMap,
+    str::FromStr,
+};
+
+use mb_sdk::{
+    constants::YOCTO_PER_BYTE,
+    near_assert,
+    near_sdk::{
         self,
-        BorshDeserialize,
-        BorshSerialize,
-    },
-    json_types::{
-        U128,
-        U64,
+        borsh::{
+            self,
+            BorshDeserialize,
+            BorshSerialize,
+        },
+        json_types::{
+            U128,
+            U64,
+        },
+        serde::{
+            Deserialize,
+            Serialize,
+        },
+        AccountId,
+        Balance,
+        Gas,
+        Timestamp,
     },
-    serde::{
-        Deserialize,
-        Serialize,
-    },
-    AccountId,
-    Balance,
-    Gas,
-    Timestamp,
 };
 
 pub const ERR_LISTING_NOT_FOUND: &str = "Listing not found";
+pub const ERR_TOKEN_KEY_SEPARATOR: &str =
+    "Token IDs cannot contain the token key separator `<$>`";
 pub const ERR_OFFER_IN_PROGRESS: &str =
     "Cannot modify listing while offer is being processed";
+pub const ERR_OFFER_RESOLVING: &str =
+    "Cannot modify listing while its sale is being resolved";
 
 /// Storage deposit for 1 kB of data.
 pub const TEN_MILLINEAR: Balance = 10_000_000_000_000_000_000_000;
+/// Worst-case storage of a listing in bytes, including the one-time storage
+/// for the first listing of an account, see `Listing`.
+pub const LISTING_STORAGE_BYTES: u64 = 851;
+/// Lowest allowed value for `listing_storage_deposit`.
+pub const MIN_LISTING_STORAGE_DEPOSIT: Balance =
+    LISTING_STORAGE_BYTES as Balance * YOCTO_PER_BYTE;
+
+pub const NANOSECONDS_PER_DAY: u64 = 24 * 3600 * 1_000_000_000;
+
+/// Default for how long (in seconds) an offer may remain unresolved before
+/// anyone can release it.
+pub const DEFAULT_OFFER_TIMEOUT_SECONDS: u64 = 3600;
 
 pub const MAX_LEN_PAYOUT_NEAR: u32 = 50;
+/// Number of royalty holders that each sale of a `batch_buy` can pay out.
+pub const MAX_LEN_PAYOUT_NEAR_BATCH: u32 = 10;
+/// Maximum number of tokens that can be bought via `batch_buy`.
+pub const MAX_LEN_BATCH_BUY: usize = 5;
+/// Number of royalty holders that FT sales can always pay out.
 pub const MAX_LEN_PAYOUT_FT: u32 = 10;
+/// Upper bound for the number of royalty holders that FT sales can pay out
+/// when sufficient gas is attached, see `Market::max_len_payout_ft`.
+pub const MAX_LEN_PAYOUT_FT_CAP: u32 = 25;
 pub const LISTING_KIND_SIMPLE: &str = "simple";
+pub const LISTING_KIND_AUCTION: &str = "auction";
+pub const UNLIST_REASON_STALE: &str = "stale";
+pub const UNLIST_REASON_FORCED: &str = "forced";
+pub const OFFER_REMOVAL_REASON_WITHDRAWN: &str = "withdrawn";
+pub const OFFER_REMOVAL_REASON_EXPIRED: &str = "expired";
+pub const OFFER_REFUND_REASON_LISTING_NOT_FOUND: &str = "listing_not_found";
+pub const OFFER_REFUND_REASON_OUTBID: &str = "outbid";
+pub const OFFER_REFUND_REASON_SELF_PURCHASE: &str = "self_purchase";
+pub const LISTING_FIELD_CURRENT_OFFER: &str = "current_offer";
 pub const NFT_TRANSFER_PAYOUT_GAS: Gas = Gas(15_000_000_000_000);
+pub const NFT_TRANSFER_GAS: Gas = Gas(10_000_000_000_000);
 pub const NFT_RESOLVE_PAYOUT_NEAR_GAS: Gas = Gas(175_000_000_000_000);
+/// Gas for `nft_resolve_payout_near` when paying out
+/// `MAX_LEN_PAYOUT_NEAR_BATCH` royalty holders as part of a `batch_buy`.
+pub const NFT_RESOLVE_PAYOUT_NEAR_BATCH_GAS: Gas = Gas(40_000_000_000_000);
+/// Gas for `nft_resolve_payout_ft` when paying out `MAX_LEN_PAYOUT_FT`
+/// royalty holders.
 pub const NFT_RESOLVE_PAYOUT_FT_GAS: Gas = Gas(235_000_000_000_000);
-// const LISTING_KIND_AUCTION: &str = "auction";
+/// Additional gas for `nft_resolve_payout_ft` for each royalty holder beyond
+/// `MAX_LEN_PAYOUT_FT`.
+pub const FT_PAYOUT_GAS_PER_RECIPIENT: Gas = Gas(15_000_000_000_000);
+/// Gas that `ft_on_transfer` keeps for itself after scheduling the transfer.
+pub const FT_ON_TRANSFER_RESERVED_GAS: Gas = Gas(10_000_000_000_000);
+pub const FT_BALANCE_OF_GAS: Gas = Gas(5_000_000_000_000);
+pub const FT_RESOLVE_RESCUE_GAS: Gas = Gas(25_000_000_000_000);
+pub const NFT_TOKEN_GAS: Gas = Gas(10_000_000_000_000);
+pub const RESOLVE_STALE_LISTING_GAS: Gas = Gas(15_000_000_000_000);
+/// Gas for `resolve_release_stuck_offer`, including a refund via
+/// `ft_transfer`.
+pub const RESOLVE_RELEASE_STUCK_OFFER_GAS: Gas = Gas(30_000_000_000_000);
+pub const FT_RESOLVE_CLAIM_ACCRUALS_GAS: Gas = Gas(10_000_000_000_000);
+pub const FT_METADATA_GAS: Gas = Gas(5_000_000_000_000);
+pub const FT_RESOLVE_REFRESH_GAS: Gas = Gas(10_000_000_000_000);
+/// Maximum number of listings returned by `get_listings` and
+/// `get_listings_by_owner`.
+pub const MAX_LEN_LISTINGS_PAGE: u64 = 100;
+/// Maximum number of accounts returned by `banned_accounts_paginated`.
+pub const MAX_LEN_BANNED_ACCOUNTS_PAGE: u64 = 100;
+/// Maximum number of accounts for `batch_ban` and `batch_unban`.
+pub const MAX_LEN_BATCH_BAN: usize = 100;
+/// Maximum length of the `external_ref` of a listing in bytes.
+pub const MAX_LEN_EXTERNAL_REF: usize = 64;
+/// Maximum length of the message for an `on_purchase` hook in bytes.
+pub const MAX_LEN_PURCHASE_MSG: usize = 256;
+/// Gas for calling `nft_on_purchase` on the `on_purchase` hook of a buyer.
+/// This is reserved in addition to the gas for resolving the payout, so
+/// buyers specifying a hook need to attach it on top of the usual gas.
+pub const NFT_ON_PURCHASE_GAS: Gas = Gas(10_000_000_000_000);
+/// Maximum length of a cached FT symbol in bytes.
+pub const MAX_LEN_FT_SYMBOL: usize = 32;
+/// Part of the deposit for an unlisted offer that covers its storage. It is
+/// refunded together with the offer or once the offer is accepted. For FT
+/// offers, it is taken from the storage deposit of the offerer instead.
+pub const UNLISTED_OFFER_STORAGE_DEPOSIT: Balance = TEN_MILLINEAR;
+pub const MAX_UNLISTED_OFFERS_PER_TOKEN: usize = 10;
+pub const RESOLVE_ACCEPT_UNLISTED_OFFER_GAS: Gas = Gas(205_000_000_000_000);
+/// Gas for `resolve_accept_unlisted_offer` on FT offers, which covers
+/// `nft_transfer_payout` and `nft_resolve_payout_ft`.
+pub const RESOLVE_ACCEPT_UNLISTED_FT_OFFER_GAS: Gas = Gas(260_000_000_000_000);
 
 /// A listing as it is stored on the blockchain.
 ///
@@ -47,16 +135,19 @@ pub const NFT_RESOLVE_PAYOUT_FT_GAS: Gas = Gas(235_000_000_000_000);
 /// | `price`            | 16 bytes                                |
 /// | `currency`         | 65 bytes                                |
 /// | `created_at`       | 8 bytes                                 |
-/// | `current_offer`    | 149 bytes                               |
-/// | total              | 496 bytes                               |
+/// | `current_offer`    | 157 bytes                               |
+/// | total              | 504 bytes                               |
 ///
 /// Additionally, storing this requires a `token_key` with a maximum of 128 +
-/// 64 + 3 = 195 bytes. Each lister also has one-time storages:
+/// 64 + 3 = 195 bytes. Listings with an `external_ref` store it separately,
+/// requiring another 195 + 64 = 259 bytes, which still fits into the deposit
+/// for all but the first listing of an account. Each lister also has one-time
+/// storages:
 ///
 /// - `storage_deposits_by_account`: 64 (Account ID) + 16 (u128) = 80 bytes
 /// - `listings_number_by_account`: 64 (Account ID) + 8 (u64) = 72 bytes
 ///
-/// The first listing should thus has to require a total deposit of 0.00843
+/// The first listing should thus has to require a total deposit of 0.00851
 /// NEAR. For simplicity and to discourage stale listings, each listing is
 /// required to be backed by a storage deposit of 0.01 NEAR.
 #[derive(BorshSerialize, BorshDeserialize)]
@@ -99,6 +190,8 @@ pub struct ListingJson {
     pub currency: String,
     pub created_at: U64,
     pub current_offer: Option<OfferJson>,
+    pub external_ref: Option<String>,
+    pub auction: Option<AuctionJson>,
 }
 
 impl Listing {
@@ -122,23 +215,25 @@ impl Listing {
     }
 
     pub fn token_key(&self) -> String {
-        format!("{}<$>{}", self.nft_contract_id, self.nft_token_id)
+        token_key(&self.nft_contract_id, &self.nft_token_id)
     }
 }
 
-impl From<Listing> for ListingJson {
-    fn from(listing: Listing) -> ListingJson {
-        ListingJson {
-            nft_token_id: listing.nft_token_id,
-            nft_approval_id: listing.nft_approval_id,
-            nft_owner_id: listing.nft_owner_id,
-            nft_contract_id: listing.nft_contract_id,
-            price: listing.price.into(),
-            currency: listing.currency.to_string(),
-            created_at: listing.created_at.into(),
-            current_offer: listing.current_offer.map(|offer| offer.into()),
-        }
-    }
+/// Separates the NFT contract and the token ID in token keys
+pub const TOKEN_KEY_SEPARATOR: &str = "<$>";
+
+/// Key under which listings and offers for a token are stored.
+pub fn token_key(nft_contract_id: &AccountId, token_id: &str) -> String {
+    format!("{}{}{}", nft_contract_id, TOKEN_KEY_SEPARATOR, token_id)
+}
+
+/// Inverse of `token_key`. Account IDs cannot contain the separator, so the
+/// first occurrence always ends the NFT contract.
+#[allow(dead_code)]
+pub fn parse_token_key(token_key: &str) -> Option<(AccountId, String)> {
+    let (nft_contract_id, token_id) =
+        token_key.split_once(TOKEN_KEY_SEPARATOR)?;
+    Some((nft_contract_id.parse().ok()?, token_id.to_string()))
 }
 
 /// An offer as it is stored on the blockchain.
@@ -152,7 +247,11 @@ impl From<Listing> for ListingJson {
 /// | `referrer_id`      | 65 bytes                      |
 /// | `referral_cut`     | 3 bytes                       |
 /// | total              | 148 bytes                     |
-#[derive(BorshSerialize, BorshDeserialize)]
+///
+/// The timestamp at which the offer has been made is stored separately in
+/// `Market::offer_started_at`, so that offers of listings that have been
+/// created before its introduction remain readable.
+#[derive(BorshSerialize, BorshDeserialize, Clone)]
 pub struct Offer {
     /// The account that created the offer.
     pub offerer_id: AccountId,
@@ -176,22 +275,77 @@ pub struct OfferJson {
     pub amount: U128,
     pub referrer_id: Option<AccountId>,
     pub referral_cut: Option<u16>,
+    pub started_at: U64,
+}
+
+impl Offer {
+    /// The offer serialized as `OfferJson`, e.g. for use in events.
+    pub fn to_json_string(&self, started_at: Timestamp) -> String {
+        near_sdk::serde_json::to_string(&OfferJson::new(
+            self.clone(),
+            started_at,
+        ))
+        .unwrap()
+    }
 }
 
-impl From<Offer> for OfferJson {
-    fn from(offer: Offer) -> OfferJson {
+impl OfferJson {
+    /// `started_at` is the timestamp stored in `Market::offer_started_at`.
+    pub fn new(offer: Offer, started_at: Timestamp) -> OfferJson {
         OfferJson {
             offerer_id: offer.offerer_id,
             amount: offer.amount.into(),
             referrer_id: offer.referrer_id,
             referral_cut: offer.referral_cut,
+            started_at: started_at.into(),
+        }
+    }
+}
+
+/// An offer on a token that is not listed on the market, or that is listed
+/// in a different currency. The offered amount is held in escrow until the
+/// offer is accepted by the token owner, withdrawn, or removed after expiry.
+/// The currency of FT offers is stored in `Market::unlisted_offer_currencies`.
+#[derive(BorshSerialize, BorshDeserialize, Clone)]
+pub struct UnlistedOffer {
+    /// The account that created the offer.
+    pub offerer_id: AccountId,
+    /// The amount being offered in yoctoNEAR or atomic FT units, excluding
+    /// the storage deposit.
+    pub amount: Balance,
+    /// Timestamp of the block in which this offer was created.
+    pub created_at: Timestamp,
+    /// Timestamp after which the offer can no longer be accepted.
+    pub expires_at: Timestamp,
+}
+
+/// An unlisted offer as it is serialized towards the end user. For field
+/// descriptions see the `UnlistedOffer` struct.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UnlistedOfferJson {
+    pub offerer_id: AccountId,
+    pub currency: String,
+    pub amount: U128,
+    pub created_at: U64,
+    pub expires_at: U64,
+}
+
+impl UnlistedOfferJson {
+    pub fn new(offer: UnlistedOffer, currency: &Currency) -> UnlistedOfferJson {
+        UnlistedOfferJson {
+            offerer_id: offer.offerer_id,
+            currency: currency.to_string(),
+            amount: offer.amount.into(),
+            created_at: offer.created_at.into(),
+            expires_at: offer.expires_at.into(),
         }
     }
 }
 
 /// Enum to hold payment methods, which can be either native NEAR, or fungible
 /// tokens on NEAR protocol.
-#[derive(BorshSerialize, BorshDeserialize, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Eq)]
 pub enum Currency {
     Near,
     FtContract(AccountId),
@@ -230,6 +384,25 @@ impl ToString for Currency {
     }
 }
 
+/// Parses the representation returned by `to_string`, i.e. either `"near"` or
+/// `"ft::<ft_contract_id>"`.
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Currency, Self::Err> {
+        if s == "near" {
+            return Ok(Currency::Near);
+        }
+        match s.strip_prefix("ft::").map(AccountId::from_str) {
+            Some(Ok(ft_contract_id)) => {
+                Ok(Currency::FtContract(ft_contract_id))
+            }
+            Some(Err(e)) => Err(format!("Invalid FT contract ID: {}", e)),
+            None => Err(format!("Invalid currency: {}", s)),
+        }
+    }
+}
+
 /// The message that will be passed from the NFT contract to the market to
 /// specify listing parameters.
 #[derive(Deserialize, Clone)]
@@ -239,6 +412,61 @@ pub struct CreateListingMsg {
     pub price: U128,
     /// FT contract to use. If none, the token is listed for native NEAR.
     pub ft_contract: Option<AccountId>,
+    /// Opaque reference of up to 64 bytes, e.g. the ID of the DAO proposal
+    /// that created the listing. It is echoed in the `nft_list` and
+    /// `nft_sale` events.
+    pub external_ref: Option<String>,
+    /// If set, the token is auctioned until this timestamp (in nanoseconds)
+    /// instead of being sold to the first buyer.
+    pub auction_ends_at: Option<U64>,
+    /// Minimum amount for the first bid on an auction, defaults to `price`.
+    pub min_bid: Option<U128>,
+}
+
+impl CreateListingMsg {
+    /// The auction configuration requested by this message, if any.
+    pub fn auction(&self) -> Option<Auction> {
+        near_assert!(
+            self.min_bid.is_none() || self.auction_ends_at.is_some(),
+            "A minimum bid can only be specified for auctions"
+        );
+        self.auction_ends_at.map(|ends_at| Auction {
+            ends_at: ends_at.0,
+            min_bid: self.min_bid.unwrap_or(self.price).0,
+        })
+    }
+}
+
+/// Configuration of a timed auction. It is stored separately from the
+/// listing, under the same token key. While the auction runs, the highest bid
+/// is escrowed as the `current_offer` of the listing.
+///
+/// Storage calculation: 195 bytes (token key) + 8 bytes (`ends_at`) + 16
+/// bytes (`min_bid`) = 219 bytes.
+#[derive(BorshSerialize, BorshDeserialize, Clone)]
+pub struct Auction {
+    /// Timestamp (in nanoseconds) after which no more bids are accepted and
+    /// the auction can be settled
+    pub ends_at: Timestamp,
+    /// Minimum amount for the first bid
+    pub min_bid: Balance,
+}
+
+/// Auction configuration as it is serialized towards end-users.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionJson {
+    pub ends_at: U64,
+    pub min_bid: U128,
+}
+
+impl From<Auction> for AuctionJson {
+    fn from(auction: Auction) -> AuctionJson {
+        AuctionJson {
+            ends_at: auction.ends_at.into(),
+            min_bid: auction.min_bid.into(),
+        }
+    }
 }
 
 /// The message that will be passed form the FT contract to the market to
@@ -250,4 +478,53 @@ pub struct BuyWithFtMessage {
     pub token_id: String,
     pub referrer_id: Option<AccountId>,
     pub affiliate_id: Option<AccountId>,
+    /// Contract and message for calling `nft_on_purchase` once the token has
+    /// been transferred to the buyer, see `Market::buy`.
+    pub on_purchase: Option<(AccountId, String)>,
+}
+
+/// The message that will be passed from the FT contract to the market to
+/// make an offer on a token that is not listed for this FT, see
+/// `Market::make_unlisted_offer`.
+#[derive(Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UnlistedOfferWithFtMessage {
+    pub nft_contract_id: AccountId,
+    pub token_id: String,
+    pub expires_at: U64,
+}
+
+/// Projected split of a sale, as returned by `preview_affiliate_earning`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PreviewJson {
+    pub affiliate_amount: U128,
+    pub mintbase_amount: U128,
+    pub seller_payout_before_royalty: U128,
+}
+
+/// Result of `can_buy`, with `reason` explaining why a buy would fail.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CanBuyJson {
+    pub ok: bool,
+    pub reason: Option<String>,
+}
+
+/// Display information for an FT that listings can be priced in, as cached
+/// by the market owner.
+#[derive(BorshSerialize, BorshDeserialize, Deserialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtInfo {
+    pub decimals: u8,
+    pub symbol: String,
+}
+
+/// The parts of a NEP-171 token that are required to check whether a listing
+/// is still valid. `approved_account_ids` is optional in the standard.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftTokenJson {
+    pub owner_id: AccountId,
+    pub approved_account_ids: Option<HashMap<AccountId, u64>>,
 }
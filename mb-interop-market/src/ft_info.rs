@@ -0,0 +1,85 @@
+use mb_sdk::{
+    interfaces::{
+        ext_ft,
+        ext_new_market,
+    },
+    near_assert,
+    near_sdk::{
+        self,
+        env,
+        serde_json,
+        AccountId,
+        Promise,
+        PromiseResult,
+    },
+};
+
+use crate::{
+    data::*,
+    Market,
+    MarketExt,
+};
+
+#[near_sdk::near_bindgen]
+impl Market {
+    /// Register the decimals and symbol of an FT contract, such that
+    /// frontends can display listings priced in this FT without querying the
+    /// FT contract. Overwrites previously registered information. Only the
+    /// owner can call this.
+    #[payable]
+    pub fn register_ft(
+        &mut self,
+        ft_contract_id: AccountId,
+        decimals: u8,
+        symbol: String,
+    ) {
+        self.assert_predecessor_is_owner();
+        near_assert!(
+            symbol.len() <= MAX_LEN_FT_SYMBOL,
+            "FT symbol cannot be longer than {} bytes",
+            MAX_LEN_FT_SYMBOL
+        );
+        self.ft_infos
+            .insert(&ft_contract_id, &FtInfo { decimals, symbol });
+    }
+
+    /// Update the registered information for an FT contract from its
+    /// [NEP-148](https://nomicon.io/Standards/Tokens/FungibleToken/Metadata)
+    /// `ft_metadata`. Only the owner can call this.
+    #[payable]
+    pub fn refresh_ft(&mut self, ft_contract_id: AccountId) -> Promise {
+        self.assert_predecessor_is_owner();
+        ext_ft::ext(ft_contract_id.clone())
+            .with_static_gas(FT_METADATA_GAS)
+            .ft_metadata()
+            .then(
+                ext_new_market::ext(env::current_account_id())
+                    .with_static_gas(FT_RESOLVE_REFRESH_GAS)
+                    .ft_resolve_refresh(ft_contract_id),
+            )
+    }
+
+    /// Callback for `refresh_ft`. Returns whether the information for the FT
+    /// contract has been updated.
+    #[private]
+    pub fn ft_resolve_refresh(&mut self, ft_contract_id: AccountId) -> bool {
+        let ft_info = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                serde_json::from_slice::<FtInfo>(&value).ok()
+            }
+            _ => None,
+        };
+        match ft_info {
+            Some(ft_info) if ft_info.symbol.len() <= MAX_LEN_FT_SYMBOL => {
+                self.ft_infos.insert(&ft_contract_id, &ft_info);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Show the registered information for an FT contract.
+    pub fn get_ft_info(&self, ft_contract_id: AccountId) -> Option<FtInfo> {
+        self.ft_infos.get(&ft_contract_id)
+    }
+}
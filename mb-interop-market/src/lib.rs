@@ -1,5 +1,7 @@
 use mb_sdk::{
+    events::market_v2 as events,
     near_assert,
+    near_panic,
     near_sdk::{
         self,
         borsh::{
@@ -8,6 +10,8 @@ use mb_sdk::{
             BorshSerialize,
         },
         collections::{
+            LookupMap,
+            LookupSet,
             UnorderedMap,
             UnorderedSet,
         },
@@ -20,12 +24,26 @@ use mb_sdk::{
         Balance,
         Promise,
     },
+    utils::ContractVersionJson,
 };
 
+/// Payouts of small FT amounts that are accrued instead of transferred
+mod accruals;
+/// Timed auctions on listings
+mod auctions;
 /// Contains constants and type definitions
 mod data;
+/// Cached display information for FTs
+mod ft_info;
 mod listing;
 mod offers;
+/// Recovery of assets that ended up on the market by mistake
+mod rescue;
+/// Methods to seed state in integration tests
+#[cfg(feature = "testing")]
+mod testing;
+/// Escrowed offers on tokens that are not listed
+mod unlisted_offers;
 
 use data::*;
 
@@ -60,6 +78,61 @@ pub struct Market {
     pub fallback_cut: u16,
     /// The owner of the market, who is allowed to configure it.
     pub owner: AccountId,
+    /// Account that has been proposed as new owner, but has not yet accepted
+    /// the ownership.
+    pub pending_owner: Option<AccountId>,
+    /// How long (in seconds) an offer may remain unresolved before anyone can
+    /// release it via `release_stuck_offer`
+    pub offer_timeout_seconds: u64,
+    /// FT payouts below this amount are accrued per recipient instead of
+    /// being transferred, and can be claimed via `claim_ft_accruals`
+    pub min_ft_payout: Balance,
+    /// Accrued FT payouts, keyed by FT contract and recipient
+    pub ft_accruals: LookupMap<(AccountId, AccountId), Balance>,
+    /// Sum of accrued FT payouts per FT contract
+    pub ft_accruals_total: LookupMap<AccountId, Balance>,
+    /// Display information for FTs, registered by the owner
+    pub ft_infos: LookupMap<AccountId, FtInfo>,
+    /// Escrowed offers on tokens that are not listed, keyed by token key
+    pub unlisted_offers: UnorderedMap<String, Vec<UnlistedOffer>>,
+    /// Whether the market waives its cut on sales of tokens that have been
+    /// listed by the market owner
+    pub waive_owner_cut: bool,
+    /// Maximum share (in `1 / 10_000`) of a sale after cuts that a payout may
+    /// route to accounts other than the seller
+    pub max_total_payout_bps: u16,
+    /// Token keys of listings whose sale has been handed to the NFT contract
+    /// and awaits resolution of the payout
+    pub resolving: LookupSet<String>,
+    /// Opaque references (e.g. DAO proposal IDs) that have been specified on
+    /// listing, keyed by token key
+    pub external_refs: LookupMap<String, String>,
+    /// Fee in yoctoNEAR that is retained from the storage deposit for each
+    /// full day that a listing has existed before being unlisted or sold
+    pub stale_fee_per_day: Option<Balance>,
+    /// Configuration of listings that are auctioned, keyed by token key
+    pub auctions: LookupMap<String, Auction>,
+    /// Contracts and messages to call `nft_on_purchase` with once the offer
+    /// on a listing has been accepted, keyed by token key
+    pub purchase_hooks: LookupMap<String, (AccountId, String)>,
+    /// Whether token owners may buy or bid on their own listings
+    pub allow_self_purchase: bool,
+    /// FT contracts of unlisted offers that have not been made in NEAR, keyed
+    /// by token key and offerer
+    pub unlisted_offer_currencies: LookupMap<(String, AccountId), AccountId>,
+    /// Token keys of listings whose current offer has been escrowed by the
+    /// market as an unlisted offer, and thus needs to be refunded by the
+    /// market if the sale fails
+    pub escrowed_offers: LookupSet<String>,
+    /// Timestamps at which the current offers on listings have been made,
+    /// keyed by token key
+    pub offer_started_at: LookupMap<String, u64>,
+    /// Sum of FT amounts that are locked in offers per FT contract, which are
+    /// not available to `rescue_ft`
+    pub ft_locked_total: LookupMap<AccountId, Balance>,
+    /// Token keys of listings whose sale is executing without the market cut,
+    /// as `waive_owner_cut` applied when the transfer was started
+    pub cut_waived_offers: LookupSet<String>,
 }
 
 #[near_sdk::near_bindgen]
@@ -82,6 +155,75 @@ impl Market {
             mintbase_cut,
             fallback_cut,
             owner,
+            pending_owner: None,
+            offer_timeout_seconds: DEFAULT_OFFER_TIMEOUT_SECONDS,
+            min_ft_payout: 0,
+            ft_accruals: LookupMap::new(&b"fta"[..]),
+            ft_accruals_total: LookupMap::new(&b"ftt"[..]),
+            ft_infos: LookupMap::new(&b"fti"[..]),
+            unlisted_offers: UnorderedMap::new(&b"k2u"[..]),
+            waive_owner_cut: false,
+            max_total_payout_bps: 10_000,
+            resolving: LookupSet::new(&b"k2r"[..]),
+            external_refs: LookupMap::new(&b"k2x"[..]),
+            stale_fee_per_day: None,
+            auctions: LookupMap::new(&b"k2a"[..]),
+            purchase_hooks: LookupMap::new(&b"k2h"[..]),
+            allow_self_purchase: false,
+            unlisted_offer_currencies: LookupMap::new(&b"k2c"[..]),
+            escrowed_offers: LookupSet::new(&b"k2e"[..]),
+            offer_started_at: LookupMap::new(&b"k2t"[..]),
+            ft_locked_total: LookupMap::new(&b"ftl"[..]),
+            cut_waived_offers: LookupSet::new(&b"k2w"[..]),
+        }
+    }
+
+    /// Migrates the state of a market that has been deployed with the layout
+    /// of `MarketV1` to the current layout, with all newer settings at the
+    /// defaults of `init`. Markets that already have the current layout are
+    /// left untouched. Can only be called by the market account itself, i.e.
+    /// as part of a redeployment.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(b"STATE").expect("ohno ohno state");
+        if let Ok(current) = Self::try_from_slice(&state) {
+            return current;
+        }
+
+        let old = MarketV1::try_from_slice(&state)
+            .unwrap_or_else(|_| env::panic_str("Cannot deserialize state"));
+        Self {
+            listings: old.listings,
+            banned_accounts: old.banned_accounts,
+            referrers: old.referrers,
+            storage_deposits_by_account: old.storage_deposits_by_account,
+            listings_count_by_account: old.listings_count_by_account,
+            listing_storage_deposit: old.listing_storage_deposit,
+            listing_lock_seconds: old.listing_lock_seconds,
+            mintbase_cut: old.mintbase_cut,
+            fallback_cut: old.fallback_cut,
+            owner: old.owner,
+            pending_owner: None,
+            offer_timeout_seconds: DEFAULT_OFFER_TIMEOUT_SECONDS,
+            min_ft_payout: 0,
+            ft_accruals: LookupMap::new(&b"fta"[..]),
+            ft_accruals_total: LookupMap::new(&b"ftt"[..]),
+            ft_infos: LookupMap::new(&b"fti"[..]),
+            unlisted_offers: UnorderedMap::new(&b"k2u"[..]),
+            waive_owner_cut: false,
+            max_total_payout_bps: 10_000,
+            resolving: LookupSet::new(&b"k2r"[..]),
+            external_refs: LookupMap::new(&b"k2x"[..]),
+            stale_fee_per_day: None,
+            auctions: LookupMap::new(&b"k2a"[..]),
+            purchase_hooks: LookupMap::new(&b"k2h"[..]),
+            allow_self_purchase: false,
+            unlisted_offer_currencies: LookupMap::new(&b"k2c"[..]),
+            escrowed_offers: LookupSet::new(&b"k2e"[..]),
+            offer_started_at: LookupMap::new(&b"k2t"[..]),
+            ft_locked_total: LookupMap::new(&b"ftl"[..]),
+            cut_waived_offers: LookupSet::new(&b"k2w"[..]),
         }
     }
 
@@ -89,24 +231,99 @@ impl Market {
     // -------- ownership itself
     /// Sets the owner of the market contract. The owner will be allowed to
     /// modify market settings. Only the owner can call this.
+    ///
+    /// DEPRECATED: Prefer `propose_owner` and `accept_ownership`, which
+    /// prevent handing the market to an account that nobody controls.
     #[payable]
     pub fn set_owner(&mut self, new_owner: AccountId) {
         self.assert_predecessor_is_owner();
+        self.pending_owner = None;
+        env::log_str(
+            &events::MarketOwnershipTransferredData {
+                old_owner: self.owner.clone(),
+                new_owner: new_owner.clone(),
+            }
+            .serialize_event(),
+        );
         self.owner = new_owner;
     }
     /// Show owner of the market contract
     pub fn get_owner(&self) -> AccountId {
         self.owner.clone()
     }
+    /// Propose a new owner for the market contract. The ownership is only
+    /// transferred once the proposed account calls `accept_ownership`. Only
+    /// the owner can call this.
+    #[payable]
+    pub fn propose_owner(&mut self, new_owner: AccountId) {
+        self.assert_predecessor_is_owner();
+        env::log_str(
+            &events::MarketOwnershipProposedData {
+                owner: self.owner.clone(),
+                pending_owner: new_owner.clone(),
+            }
+            .serialize_event(),
+        );
+        self.pending_owner = Some(new_owner);
+    }
+    /// Accept a pending ownership proposal. Only the proposed owner can call
+    /// this.
+    #[payable]
+    pub fn accept_ownership(&mut self) {
+        near_sdk::assert_one_yocto();
+        let new_owner = env::predecessor_account_id();
+        near_assert!(
+            self.pending_owner.as_ref() == Some(&new_owner),
+            "Method is restricted to pending market owner"
+        );
+        env::log_str(
+            &events::MarketOwnershipTransferredData {
+                old_owner: self.owner.clone(),
+                new_owner: new_owner.clone(),
+            }
+            .serialize_event(),
+        );
+        self.owner = new_owner;
+        self.pending_owner = None;
+    }
+    /// Cancel a pending ownership proposal. Only the owner can call this.
+    #[payable]
+    pub fn cancel_ownership_proposal(&mut self) {
+        self.assert_predecessor_is_owner();
+        let pending_owner = match self.pending_owner.take() {
+            None => near_panic!("There is no pending ownership proposal"),
+            Some(pending_owner) => pending_owner,
+        };
+        env::log_str(
+            &events::MarketOwnershipProposalCancelledData {
+                owner: self.owner.clone(),
+                pending_owner,
+            }
+            .serialize_event(),
+        );
+    }
+    /// Show the account that has been proposed as new owner, if any
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner.clone()
+    }
 
     // -------- cut remaining with mintbase in case of referral
     /// Set the cut that the market takes from each affiliate sale. In total,
     /// `mintbase_cut * affiliate_cut * sale_price` will remain with the
-    /// markets. The unit is `1 / 10_000`. Only the owner can call this.
+    /// markets. The unit is `1 / 10_000`, and the cut cannot exceed 10_000.
+    /// Only the owner can call this.
     #[payable]
     pub fn set_mintbase_cut(&mut self, new_cut: u16) {
         self.assert_predecessor_is_owner();
+        assert_valid_cut(new_cut);
         self.mintbase_cut = new_cut;
+        env::log_str(
+            &events::MarketCutsUpdateData {
+                mintbase_cut: Some(new_cut),
+                fallback_cut: None,
+            }
+            .serialize_event(),
+        );
     }
     /// Show cut that mintbase takes from each affiliate sale
     pub fn get_mintbase_cut(&self) -> u16 {
@@ -115,17 +332,99 @@ impl Market {
 
     // -------- fallback cut (no referral)
     /// Set the fallback cut that the market keeps for each non-affiliated sale.
-    /// Again, units are in `1 / 10_000`.  Only the owner can call this.
+    /// Again, units are in `1 / 10_000`, and the cut cannot exceed 10_000.
+    /// Only the owner can call this.
     #[payable]
     pub fn set_fallback_cut(&mut self, new_cut: u16) {
         self.assert_predecessor_is_owner();
+        assert_valid_cut(new_cut);
         self.fallback_cut = new_cut;
+        env::log_str(
+            &events::MarketCutsUpdateData {
+                mintbase_cut: None,
+                fallback_cut: Some(new_cut),
+            }
+            .serialize_event(),
+        );
     }
     /// Show the cut that the market keeps on non-affiliated sales.
     pub fn get_fallback_cut(&self) -> u16 {
         self.fallback_cut
     }
 
+    // -------- both cuts at once
+    /// Set `mintbase_cut` and `fallback_cut` together, emitting a single
+    /// `update_cuts` event. Both are in units of `1 / 10_000` and cannot
+    /// exceed 10_000. Only the owner can call this.
+    #[payable]
+    pub fn set_cuts(&mut self, mintbase_cut: u16, fallback_cut: u16) {
+        self.assert_predecessor_is_owner();
+        assert_valid_cut(mintbase_cut);
+        assert_valid_cut(fallback_cut);
+        self.mintbase_cut = mintbase_cut;
+        self.fallback_cut = fallback_cut;
+        env::log_str(
+            &events::MarketCutsUpdateData {
+                mintbase_cut: Some(mintbase_cut),
+                fallback_cut: Some(fallback_cut),
+            }
+            .serialize_event(),
+        );
+    }
+
+    // -------- cut waiver for the market owner
+    /// Set whether the market waives its cut on sales of tokens listed by the
+    /// market owner. If waived, the seller receives the share that would have
+    /// remained with the market, while affiliates are paid as usual. Only the
+    /// owner can call this.
+    #[payable]
+    pub fn set_waive_owner_cut(&mut self, waive: bool) {
+        self.assert_predecessor_is_owner();
+        self.waive_owner_cut = waive;
+    }
+    /// Show whether the market waives its cut on sales of tokens listed by the
+    /// market owner.
+    pub fn get_waive_owner_cut(&self) -> bool {
+        self.waive_owner_cut
+    }
+
+    // -------- payout guard
+    /// Set the maximum share of a sale (after market and affiliate cuts) that
+    /// the payout of an NFT contract may route to accounts other than the
+    /// seller, in units of `1 / 10_000`. Sales exceeding this are rejected
+    /// and the buyer refunded. Split owners count towards this share, so it
+    /// should be chosen above the largest split that is expected to be sold,
+    /// and exceeding it does not ban the NFT contract. Only the owner can call
+    /// this.
+    #[payable]
+    pub fn set_max_total_payout_bps(&mut self, bps: u16) {
+        self.assert_predecessor_is_owner();
+        near_assert!(
+            bps <= 10_000,
+            "Maximum payout share cannot exceed 10000 basis points"
+        );
+        self.max_total_payout_bps = bps;
+    }
+    /// Show the maximum share of a sale that a payout may route to accounts
+    /// other than the seller.
+    pub fn get_max_total_payout_bps(&self) -> u16 {
+        self.max_total_payout_bps
+    }
+
+    // -------- self-purchases
+    /// Set whether token owners may buy or bid on their own listings. This
+    /// is disallowed by default to keep wash trades from inflating volume,
+    /// and such offers are refunded. Only the owner can call this.
+    #[payable]
+    pub fn set_allow_self_purchase(&mut self, allow: bool) {
+        self.assert_predecessor_is_owner();
+        self.allow_self_purchase = allow;
+    }
+    /// Show whether token owners may buy or bid on their own listings.
+    pub fn get_allow_self_purchase(&self) -> bool {
+        self.allow_self_purchase
+    }
+
     // -------- how long listings are locked
     /// Set the duration (in seconds) that each listing is locked after
     /// creation. Only the owner can call this.
@@ -139,12 +438,61 @@ impl Market {
         self.listing_lock_seconds.into()
     }
 
+    // -------- fees for stale listings
+    /// Set the fee (in yoctoNEAR) that is retained from the storage deposit
+    /// of a listing for each full day since its creation once it is unlisted
+    /// or sold. The fee is capped at the storage deposit of the listing.
+    /// `None` disables the fee. Only the owner can call this.
+    #[payable]
+    pub fn set_stale_fee_per_day(&mut self, fee: Option<U128>) {
+        self.assert_predecessor_is_owner();
+        self.stale_fee_per_day = fee.map(|fee| fee.0);
+    }
+    /// Show the fee that is retained for each day a listing has existed.
+    pub fn get_stale_fee_per_day(&self) -> Option<U128> {
+        self.stale_fee_per_day.map(Into::into)
+    }
+
+    // -------- how long offers may remain unresolved
+    /// Set the duration (in seconds) after which an unresolved offer can be
+    /// released by anyone. Only the owner can call this.
+    #[payable]
+    pub fn set_offer_timeout_seconds(&mut self, secs: U64) {
+        self.assert_predecessor_is_owner();
+        self.offer_timeout_seconds = secs.0;
+    }
+    /// Show duration (in seconds) after which an unresolved offer can be
+    /// released by anyone.
+    pub fn get_offer_timeout_seconds(&self) -> U64 {
+        self.offer_timeout_seconds.into()
+    }
+
+    // -------- minimum FT payout
+    /// Set the minimum amount of FTs that will be transferred in a payout.
+    /// Smaller amounts are accrued for the recipient, who can claim them via
+    /// `claim_ft_accruals`. Only the owner can call this.
+    #[payable]
+    pub fn set_min_ft_payout(&mut self, amount: U128) {
+        self.assert_predecessor_is_owner();
+        self.min_ft_payout = amount.0;
+    }
+    /// Show the minimum amount of FTs that will be transferred in a payout.
+    pub fn get_min_ft_payout(&self) -> U128 {
+        self.min_ft_payout.into()
+    }
+
     // -------- storage deposit for single listing
     /// Set the deposit in yoctoNEAR that each listing will (maximally) require.
-    /// Only the owner can call this.
+    /// This cannot be lower than the storage stake for the worst-case listing
+    /// size (`MIN_LISTING_STORAGE_DEPOSIT`). Only the owner can call this.
     #[payable]
     pub fn set_listing_storage_deposit(&mut self, deposit: U128) {
         self.assert_predecessor_is_owner();
+        near_assert!(
+            deposit.0 >= MIN_LISTING_STORAGE_DEPOSIT,
+            "Listing storage deposit cannot be lower than {}",
+            MIN_LISTING_STORAGE_DEPOSIT
+        );
         self.listing_storage_deposit = deposit.0;
     }
     /// Show current deposit in yoctoNEAR that each listing will (maximally)
@@ -168,11 +516,59 @@ impl Market {
         self.assert_predecessor_is_owner();
         self.banned_accounts.remove(&account_id);
     }
+    /// Add up to 100 accounts to the banlist at once, e.g. when responding to
+    /// an exploit. Only the owner can call this.
+    #[payable]
+    pub fn batch_ban(&mut self, accounts: Vec<AccountId>) {
+        self.assert_predecessor_is_owner();
+        assert_batch_ban_len(&accounts);
+        for account_id in accounts.iter() {
+            self.banned_accounts.insert(account_id);
+        }
+        log_banlist_update(accounts, true);
+    }
+    /// Remove up to 100 accounts from the banlist at once. Only the owner can
+    /// call this.
+    #[payable]
+    pub fn batch_unban(&mut self, accounts: Vec<AccountId>) {
+        self.assert_predecessor_is_owner();
+        assert_batch_ban_len(&accounts);
+        for account_id in accounts.iter() {
+            self.banned_accounts.remove(account_id);
+        }
+        log_banlist_update(accounts, false);
+    }
     /// Show a list of all accounts that are banned from interacting with the
     /// market.
+    ///
+    /// Deprecated: This might exceed gas limits for large banlists, use
+    /// `banned_accounts_paginated` or `is_banned` instead.
     pub fn banned_accounts(&self) -> Vec<AccountId> {
         self.banned_accounts.iter().collect()
     }
+    /// Show a page of accounts that are banned from interacting with the
+    /// market. At most `MAX_LEN_BANNED_ACCOUNTS_PAGE` (100) accounts are
+    /// returned per call.
+    pub fn banned_accounts_paginated(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        self.banned_accounts
+            .as_vector()
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(
+                limit
+                    .unwrap_or(MAX_LEN_BANNED_ACCOUNTS_PAGE)
+                    .min(MAX_LEN_BANNED_ACCOUNTS_PAGE) as usize,
+            )
+            .collect()
+    }
+    /// Check if an account is banned from interacting with the market.
+    pub fn is_banned(&self, account_id: AccountId) -> bool {
+        self.banned_accounts.contains(&account_id)
+    }
 
     // -------- affiliates whitelist
     /// Add a registered affiliate. This allows to set a custom fee whereas
@@ -194,6 +590,12 @@ impl Market {
         self.referrers.iter().collect()
     }
 
+    // ------------------------------- versioning ------------------------------
+    /// Show name, version, and commit hash of the deployed contract binary
+    pub fn get_version(&self) -> ContractVersionJson {
+        mb_sdk::contract_version!()
+    }
+
     // ---------------------- anything related to storage ----------------------
     /// Get the number of listings created by a specific account ID
     pub fn get_listings_count(&self, account: &AccountId) -> U64 {
@@ -234,6 +636,31 @@ impl Market {
         self.storage_deposits_by_account
             .insert(&account, &(old_deposit + new_deposit));
     }
+    /// Like `deposit_storage`, but requires the deposit to cover at least
+    /// `num_listings` listings. Any remainder is credited as storage deposit
+    /// as well. Returns the number of listings that the caller can create
+    /// afterwards, e.g. to make sure that approving `num_listings` tokens for
+    /// the market will succeed.
+    #[payable]
+    pub fn deposit_and_prepare(&mut self, num_listings: u64) -> u64 {
+        let required = self
+            .listing_storage_deposit
+            .checked_mul(num_listings as Balance)
+            .unwrap_or_else(|| {
+                near_panic!(
+                    "Storage deposit for {} listings overflows",
+                    num_listings
+                )
+            });
+        near_assert!(
+            env::attached_deposit() >= required,
+            "Deposit of {} yoctoNEAR is required to cover {} listings",
+            required,
+            num_listings
+        );
+        self.deposit_storage();
+        self.get_affordable_listings(env::predecessor_account_id())
+    }
     /// Claim storage deposits that are not required to cover any listings.
     #[payable]
     pub fn claim_unused_storage_deposit(&mut self) -> Promise {
@@ -251,6 +678,20 @@ impl Market {
         // send the refund
         self.refund_storage_deposit(&account, refund, 0)
     }
+    /// Get the storage deposit of a specific account ID that is not currently
+    /// needed to cover listings.
+    pub fn get_free_storage_deposit(&self, account_id: AccountId) -> U128 {
+        self.free_storage_deposit(&account_id).into()
+    }
+    /// Get the number of listings that a specific account ID can create with
+    /// its current free storage deposit.
+    pub fn get_affordable_listings(&self, account_id: AccountId) -> u64 {
+        // a zero listing deposit means that listings are not restricted
+        self.free_storage_deposit(&account_id)
+            .checked_div(self.listing_storage_deposit)
+            .map(|n| n as u64)
+            .unwrap_or(u64::MAX)
+    }
     /// Get the storage of a specified account.
     fn storage_deposit_by(&self, account: &AccountId) -> Balance {
         self.storage_deposits_by_account.get(account).unwrap_or(0)
@@ -278,6 +719,19 @@ impl Market {
         Promise::new(account.to_owned()).transfer(refund - retain)
     }
 
+    /// Fee that would currently be retained from the storage deposit of a
+    /// listing created at `created_at`, see `set_stale_fee_per_day`.
+    fn stale_fee(&self, created_at: u64) -> Balance {
+        let fee_per_day = match self.stale_fee_per_day {
+            None => return 0,
+            Some(fee) => fee,
+        };
+        let days = (env::block_timestamp() - created_at) / NANOSECONDS_PER_DAY;
+        fee_per_day
+            .saturating_mul(days as Balance)
+            .min(self.listing_storage_deposit)
+    }
+
     /// Decrease listings count and refund the lister with the deposits.
     fn refund_listings(
         &mut self,
@@ -306,6 +760,13 @@ impl Market {
         );
     }
 
+    /// Panics if the sale of a listing is currently being resolved
+    fn assert_not_resolving(&self, token_key: &String) {
+        if self.resolving.contains(token_key) {
+            env::panic_str(ERR_OFFER_RESOLVING);
+        }
+    }
+
     /// Panics if the current call is not from the market owner.
     fn assert_predecessor_is_owner(&self) {
         near_sdk::assert_one_yocto();
@@ -324,3 +785,37 @@ impl Market {
         deposit - required
     }
 }
+
+fn assert_batch_ban_len(accounts: &[AccountId]) {
+    near_assert!(
+        accounts.len() <= MAX_LEN_BATCH_BAN,
+        "Cannot change more than {} banned accounts at once",
+        MAX_LEN_BATCH_BAN
+    );
+}
+
+fn assert_valid_cut(cut: u16) {
+    near_assert!(cut <= 10_000, "Cuts cannot exceed 10000 basis points");
+}
+
+fn log_banlist_update(accounts: Vec<AccountId>, banned: bool) {
+    env::log_str(
+        &events::MarketBanlistUpdateData { accounts, banned }.serialize_event(),
+    );
+}
+
+// State layout before ownership proposals and the subsequent settings were
+// added, required to migrate markets that have been deployed with it
+#[derive(BorshDeserialize)]
+struct MarketV1 {
+    pub listings: UnorderedMap<String, Listing>,
+    pub banned_accounts: UnorderedSet<AccountId>,
+    pub referrers: UnorderedMap<AccountId, u16>,
+    pub storage_deposits_by_account: UnorderedMap<AccountId, Balance>,
+    pub listings_count_by_account: UnorderedMap<AccountId, u64>,
+    pub listing_storage_deposit: Balance,
+    pub listing_lock_seconds: u64,
+    pub mintbase_cut: u16,
+    pub fallback_cut: u16,
+    pub owner: AccountId,
+}
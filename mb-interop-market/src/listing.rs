@@ -1,10 +1,19 @@
 use mb_sdk::{
     events::market_v2 as events,
+    interfaces::{
+        ext_new_market,
+        ext_nft,
+    },
     near_assert,
     near_sdk::{
         self,
         env,
+        json_types::{
+            U128,
+            U64,
+        },
         AccountId,
+        Promise,
     },
     utils::{
         assert_predecessor,
@@ -38,9 +47,74 @@ impl Market {
         let nft_contract_id = env::predecessor_account_id();
         let msg: CreateListingMsg =
             near_parse(&msg, "Invalid arguments to create listing");
-        let listing =
-            Listing::new(token_id, approval_id, owner_id, nft_contract_id, msg);
+        let external_ref = msg.external_ref.clone();
+        let auction = msg.auction();
+        self.create_listing(
+            Listing::new(token_id, approval_id, owner_id, nft_contract_id, msg),
+            external_ref,
+            auction,
+        );
+    }
+
+    /// Batched version of `nft_on_approve`, which lists all tokens for the
+    /// same price. Unlike `nft_on_approve`, this method accepts a deposit,
+    /// which is credited to the storage deposit of `owner_id` before the
+    /// listings are created. This allows funding storage and listing
+    /// atomically, without a prior call to `deposit_storage`.
+    ///
+    /// This requires the NFT contract to forward the attached deposit (minus
+    /// its own storage costs) to this method, as `nft_batch_approve` on
+    /// Mintbase NFT contracts does. Should the listing fail, the deposit is
+    /// refunded to the NFT contract, not to the token owner.
+    #[payable]
+    pub fn nft_on_batch_approve(
+        &mut self,
+        tokens: Vec<String>,
+        approvals: Vec<U64>,
+        owner_id: AccountId,
+        msg: String,
+    ) {
+        near_assert!(
+            tokens.len() == approvals.len(),
+            "Number of token IDs and approval IDs must match"
+        );
+        let nft_contract_id = env::predecessor_account_id();
+        let msg: CreateListingMsg =
+            near_parse(&msg, "Invalid arguments to create listing");
+        let auction = msg.auction();
 
+        let deposit = env::attached_deposit();
+        if deposit > 0 {
+            self.assert_not_banned(&owner_id);
+            let old_deposit = self.storage_deposit_by(&owner_id);
+            self.storage_deposits_by_account
+                .insert(&owner_id, &(old_deposit + deposit));
+        }
+
+        for (token_id, approval_id) in tokens.into_iter().zip(approvals) {
+            self.create_listing(
+                Listing::new(
+                    token_id,
+                    approval_id.0,
+                    owner_id.clone(),
+                    nft_contract_id.clone(),
+                    msg.clone(),
+                ),
+                msg.external_ref.clone(),
+                auction.clone(),
+            );
+        }
+    }
+
+    /// Internally used to validate and store a new listing, and to emit the
+    /// corresponding events. If `auction` is given, the listing is auctioned
+    /// instead of being sold to the first buyer.
+    pub(crate) fn create_listing(
+        &mut self,
+        listing: Listing,
+        external_ref: Option<String>,
+        auction: Option<Auction>,
+    ) {
         // No involved party must be banned from using the market
         self.assert_not_banned(&listing.nft_owner_id);
         self.assert_not_banned(&listing.nft_contract_id);
@@ -53,6 +127,28 @@ impl Market {
             listing.nft_token_id.len() <= 128,
             "Cannot process token IDs with more than 128 bytes"
         );
+        // Token IDs must not contain the token key separator, which keeps token
+        // keys unambiguous
+        if listing.nft_token_id.contains(TOKEN_KEY_SEPARATOR) {
+            env::panic_str(ERR_TOKEN_KEY_SEPARATOR);
+        }
+        // External references are opaque, but limited in size
+        near_assert!(
+            external_ref
+                .as_ref()
+                .map(|r| r.len() <= MAX_LEN_EXTERNAL_REF)
+                .unwrap_or(true),
+            "Cannot process external references with more than {} bytes",
+            MAX_LEN_EXTERNAL_REF
+        );
+        // Auctions must not have ended before they start
+        near_assert!(
+            auction
+                .as_ref()
+                .map(|a| a.ends_at > env::block_timestamp())
+                .unwrap_or(true),
+            "Auction must end in the future"
+        );
         // Lister must have purchased storage for processing
         near_assert!(
             self.free_storage_deposit(&listing.nft_owner_id)
@@ -60,11 +156,17 @@ impl Market {
             "Storage for listing not covered"
         );
 
+        // Listings cannot be replaced while their sale is being resolved
+        self.assert_not_resolving(&listing.token_key());
+
         self.increase_listings_count(&listing.nft_owner_id, 1);
         if let Some(old_listing) =
             self.listings.insert(&listing.token_key(), &listing)
         {
-            if listing.current_offer.is_some() {
+            // Listings holding a bid on their auction cannot be replaced
+            if listing.current_offer.is_some()
+                || old_listing.current_offer.is_some()
+            {
                 env::panic_str(ERR_OFFER_IN_PROGRESS);
             }
             env::log_str(
@@ -76,16 +178,33 @@ impl Market {
                 .serialize_event(),
             );
         }
+        match external_ref.as_ref() {
+            Some(external_ref) => self
+                .external_refs
+                .insert(&listing.token_key(), external_ref),
+            None => self.external_refs.remove(&listing.token_key()),
+        };
+        let kind = match auction.as_ref() {
+            Some(auction) => {
+                self.auctions.insert(&listing.token_key(), auction);
+                LISTING_KIND_AUCTION
+            }
+            None => {
+                self.auctions.remove(&listing.token_key());
+                LISTING_KIND_SIMPLE
+            }
+        };
 
         env::log_str(
             &events::NftListData {
-                kind: LISTING_KIND_SIMPLE.to_string(),
+                kind: kind.to_string(),
                 nft_token_id: listing.nft_token_id,
                 nft_approval_id: listing.nft_approval_id,
                 nft_owner_id: listing.nft_owner_id,
                 nft_contract_id: listing.nft_contract_id,
                 currency: listing.currency.to_string(),
                 price: listing.price.into(),
+                external_ref,
             }
             .serialize_event(),
         )
@@ -95,17 +214,21 @@ impl Market {
     /// storage deposit will be refunded automatically. Unlike listing, multiple
     /// tokens can be unlisted at once, but only if they live on the same smart
     /// contract.
+    ///
+    /// Tokens can be unlisted once `listing_lock_seconds` have passed since
+    /// the listing has been created. With `listing_lock_seconds` set to zero,
+    /// unlisting is possible immediately, even within the same block.
     #[payable]
     pub fn unlist(
         &mut self,
         nft_contract_id: AccountId,
         token_ids: Vec<String>,
     ) {
+        let mut stale_fees = 0;
         for token_id in token_ids.iter() {
-            let listing = self.unlist_single_nft(&format!(
-                "{}<$>{}",
-                nft_contract_id, token_id
-            ));
+            let listing =
+                self.unlist_single_nft(&token_key(&nft_contract_id, token_id));
+            stale_fees += self.stale_fee(listing.created_at);
 
             env::log_str(
                 &events::NftUnlistData {
@@ -120,13 +243,51 @@ impl Market {
         self.refund_listings(
             &env::predecessor_account_id(),
             token_ids.len() as u64,
-            0,
+            stale_fees,
         );
     }
 
+    /// Allows the market owner to remove abusive listings, even before
+    /// `listing_lock_seconds` have passed. Listings with an offer in progress
+    /// cannot be removed. The storage deposits are refunded to the respective
+    /// listers. Only the owner can call this.
+    #[payable]
+    pub fn force_unlist(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_ids: Vec<String>,
+    ) {
+        self.assert_predecessor_is_owner();
+
+        for token_id in token_ids {
+            let token_key = token_key(&nft_contract_id, &token_id);
+            let listing = match self.get_listing_internal(&token_key) {
+                None => env::panic_str(ERR_LISTING_NOT_FOUND),
+                Some(l) => l,
+            };
+            if listing.current_offer.is_some() {
+                env::panic_str(ERR_OFFER_IN_PROGRESS);
+            }
+            self.assert_not_resolving(&token_key);
+            self.remove_listing_internal(&token_key);
+
+            env::log_str(
+                &events::NftUnlistWithReasonData {
+                    nft_contract_id: nft_contract_id.clone(),
+                    nft_token_id: token_id,
+                    nft_approval_id: listing.nft_approval_id,
+                    reason: UNLIST_REASON_FORCED.to_string(),
+                    forced: true,
+                }
+                .serialize_event(),
+            );
+            self.refund_listings(&listing.nft_owner_id, 1, 0);
+        }
+    }
+
     /// Internally used for unlisting NFTs, panics if withdrawal is impossible
     /// or method is not called by token owner
-    fn unlist_single_nft(&mut self, token_key: &String) -> Listing {
+    pub(crate) fn unlist_single_nft(&mut self, token_key: &String) -> Listing {
         let listing = match self.get_listing_internal(token_key) {
             None => env::panic_str(ERR_LISTING_NOT_FOUND),
             Some(l) => l,
@@ -135,32 +296,208 @@ impl Market {
         if listing.current_offer.is_some() {
             env::panic_str(ERR_OFFER_IN_PROGRESS);
         }
+        self.assert_not_resolving(token_key);
 
         let minimum_withdrawal_timestamp =
             listing.created_at + self.listing_lock_seconds * 1_000_000_000;
 
         assert_predecessor(&listing.nft_owner_id);
         near_assert!(
-            env::block_timestamp() > minimum_withdrawal_timestamp,
+            env::block_timestamp() >= minimum_withdrawal_timestamp,
             "Listing cannot be withdrawn before timestamp {}",
             minimum_withdrawal_timestamp / 1_000_000_000
         );
 
-        self.listings.remove(&listing.token_key());
+        self.remove_listing_internal(&listing.token_key());
         listing
     }
 
+    /// Removes a listing that can no longer be bought, because the token has
+    /// been transferred, burned, or its approval for this market has been
+    /// revoked on the NFT contract. The token is queried via `nft_token`, and
+    /// the storage deposit is refunded to the original lister. Anyone can call
+    /// this.
+    pub fn cleanup_stale_listing(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> Promise {
+        let token_key = token_key(&nft_contract_id, &token_id);
+        let listing = match self.get_listing_internal(&token_key) {
+            None => env::panic_str(ERR_LISTING_NOT_FOUND),
+            Some(l) => l,
+        };
+        if listing.current_offer.is_some() {
+            env::panic_str(ERR_OFFER_IN_PROGRESS);
+        }
+        self.assert_not_resolving(&token_key);
+
+        ext_nft::ext(nft_contract_id.clone())
+            .with_static_gas(NFT_TOKEN_GAS)
+            .nft_token(token_id.clone())
+            .then(
+                ext_new_market::ext(env::current_account_id())
+                    .with_static_gas(RESOLVE_STALE_LISTING_GAS)
+                    .resolve_stale_listing(
+                        nft_contract_id,
+                        token_id,
+                        listing.nft_approval_id,
+                    ),
+            )
+    }
+
+    /// Callback for `cleanup_stale_listing`, removing the listing if the
+    /// queried token no longer matches it.
+    #[private]
+    pub fn resolve_stale_listing(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        nft_approval_id: u64,
+    ) {
+        let token: Option<NftTokenJson> = match env::promise_result(0) {
+            near_sdk::PromiseResult::Successful(value) => {
+                match near_sdk::serde_json::from_slice(&value) {
+                    Ok(token) => token,
+                    Err(_) => env::panic_str("Invalid token data"),
+                }
+            }
+            _ => env::panic_str("Failed to query token"),
+        };
+
+        // The listing might have changed while the token was queried
+        let token_key = token_key(&nft_contract_id, &token_id);
+        let listing = match self.get_listing_internal(&token_key) {
+            Some(l) if l.nft_approval_id == nft_approval_id => l,
+            _ => env::panic_str(ERR_LISTING_NOT_FOUND),
+        };
+        if listing.current_offer.is_some() {
+            env::panic_str(ERR_OFFER_IN_PROGRESS);
+        }
+
+        let is_stale = match token {
+            None => true,
+            Some(token) => {
+                token.owner_id != listing.nft_owner_id
+                    || token
+                        .approved_account_ids
+                        .map(|approvals| {
+                            approvals.get(&env::current_account_id())
+                                != Some(&listing.nft_approval_id)
+                        })
+                        .unwrap_or(false)
+            }
+        };
+        near_assert!(is_stale, "Listing is still valid");
+
+        self.remove_listing_internal(&token_key);
+        env::log_str(
+            &events::NftUnlistWithReasonData {
+                nft_contract_id,
+                nft_token_id: token_id,
+                nft_approval_id,
+                reason: UNLIST_REASON_STALE.to_string(),
+                forced: false,
+            }
+            .serialize_event(),
+        );
+        self.refund_listings(&listing.nft_owner_id, 1, 0);
+    }
+
+    /// Show the fee that would currently be retained from the storage deposit
+    /// if the listing was unlisted or sold, see `set_stale_fee_per_day`.
+    pub fn get_accrued_stale_fee(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> U128 {
+        match self.get_listing_internal(&token_key(&nft_contract_id, &token_id))
+        {
+            None => env::panic_str(ERR_LISTING_NOT_FOUND),
+            Some(listing) => self.stale_fee(listing.created_at).into(),
+        }
+    }
+
     /// Show a listing.
     pub fn get_listing(
         &self,
         nft_contract_id: AccountId,
         token_id: String,
     ) -> Option<ListingJson> {
-        self.get_listing_internal(&format!(
-            "{}<$>{}",
-            nft_contract_id, token_id
-        ))
-        .map(Into::into)
+        let token_key = token_key(&nft_contract_id, &token_id);
+        self.get_listing_internal(&token_key)
+            .map(|listing| self.listing_json(&token_key, listing))
+    }
+
+    /// Show a page of all listings on the market, in storage order. At most
+    /// `MAX_LEN_LISTINGS_PAGE` (100) listings are returned per call.
+    pub fn get_listings(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<ListingJson> {
+        self.listings
+            .iter()
+            .skip(from_index.map(|i| i.0).unwrap_or(0) as usize)
+            .take(listings_page_len(limit))
+            .map(|(token_key, listing)| self.listing_json(&token_key, listing))
+            .collect()
+    }
+
+    /// Show a page of the listings created by `owner_id`, with `from_index`
+    /// counting only their listings. As this filters all listings on the
+    /// market, it may exceed gas limits for large markets and high indices.
+    /// At most `MAX_LEN_LISTINGS_PAGE` (100) listings are returned per call.
+    pub fn get_listings_by_owner(
+        &self,
+        owner_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<ListingJson> {
+        self.listings
+            .iter()
+            .filter(|(_, listing)| listing.nft_owner_id == owner_id)
+            .skip(from_index.map(|i| i.0).unwrap_or(0) as usize)
+            .take(listings_page_len(limit))
+            .map(|(token_key, listing)| self.listing_json(&token_key, listing))
+            .collect()
+    }
+
+    /// Serializes a listing together with the data that is stored separately
+    /// under its token key.
+    fn listing_json(
+        &self,
+        token_key: &String,
+        listing: Listing,
+    ) -> ListingJson {
+        ListingJson {
+            nft_token_id: listing.nft_token_id,
+            nft_approval_id: listing.nft_approval_id,
+            nft_owner_id: listing.nft_owner_id,
+            nft_contract_id: listing.nft_contract_id,
+            price: listing.price.into(),
+            currency: listing.currency.to_string(),
+            created_at: listing.created_at.into(),
+            current_offer: listing.current_offer.map(|offer| {
+                OfferJson::new(offer, self.offer_started_at(token_key))
+            }),
+            external_ref: self.external_refs.get(token_key),
+            auction: self.auctions.get(token_key).map(Into::into),
+        }
+    }
+
+    /// Records the current block as the start of the current offer on the
+    /// listing under `token_key`.
+    pub(crate) fn record_offer_start(&mut self, token_key: &String) {
+        self.offer_started_at
+            .insert(token_key, &near_sdk::env::block_timestamp());
+    }
+
+    /// Timestamp at which the current offer on the listing under `token_key`
+    /// has been made. Offers that have been made before this was recorded
+    /// are treated as started at zero.
+    pub(crate) fn offer_started_at(&self, token_key: &String) -> u64 {
+        self.offer_started_at.get(token_key).unwrap_or(0)
     }
 
     pub(crate) fn get_listing_internal(
@@ -169,4 +506,51 @@ impl Market {
     ) -> Option<Listing> {
         self.listings.get(token_key)
     }
+
+    /// Removes a listing along with its external reference, auction
+    /// configuration, purchase hook, escrow marker, and offer timestamp, and
+    /// releases the FTs locked in its offer.
+    pub(crate) fn remove_listing_internal(
+        &mut self,
+        token_key: &String,
+    ) -> Option<Listing> {
+        self.external_refs.remove(token_key);
+        self.auctions.remove(token_key);
+        self.purchase_hooks.remove(token_key);
+        self.escrowed_offers.remove(token_key);
+        self.offer_started_at.remove(token_key);
+        self.cut_waived_offers.remove(token_key);
+        let listing = self.listings.remove(token_key)?;
+        if let Some(offer) = listing.current_offer.as_ref() {
+            self.unlock_ft(&listing.currency, offer.amount);
+        }
+        Some(listing)
+    }
+}
+
+/// Number of listings to return for a page with the requested `limit`.
+fn listings_page_len(limit: Option<u64>) -> usize {
+    limit
+        .unwrap_or(MAX_LEN_LISTINGS_PAGE)
+        .min(MAX_LEN_LISTINGS_PAGE) as usize
+}
+
+/// Emits an `nft_listing_update` event for an in-place modification of
+/// `field` on `listing`.
+pub(crate) fn log_listing_update(
+    listing: &Listing,
+    field: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+) {
+    env::log_str(
+        &events::NftListingUpdateData {
+            nft_contract_id: listing.nft_contract_id.clone(),
+            nft_token_id: listing.nft_token_id.clone(),
+            field: field.to_string(),
+            old_value,
+            new_value,
+        }
+        .serialize_event(),
+    );
 }
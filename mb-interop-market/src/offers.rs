@@ -21,7 +21,9 @@
 //!   transferred and the payout failing because `Listing::current_offer` is
 //!   `None`, and thus the callback panicking. Thus, `remove_offer` should only
 //!   be called for offers that have ended in a failure receipt for
-//!   `nft_resolve_payout_{near,ft}`.
+//!   `nft_resolve_payout_{near,ft}`. Listings awaiting the resolution of their
+//!   payout are tracked in `Market::resolving`, and their offers cannot be
+//!   removed before `offer_timeout_seconds` have passed.
 //! - Additionally a `remove_offer` call might be intercepted. It is an
 //!   expansion of the case above, where another listing and another offer has
 //!   to be injected between a call to `remove_offer` and a potential delay
@@ -30,17 +32,24 @@
 //!   existence of a failure receipt for `nft_resolve_payout_{near,ft}` before
 //!   removing offers closes this attack vector.
 
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+
 use mb_sdk::{
     data::store::Payout,
     events::market_v2::{
         self as events,
-        NftFailedSaleData,
+        NftFailedSaleDataV030,
     },
     interfaces::{
         ext_new_market,
         ext_nft,
+        ext_nft_on_purchase,
     },
     near_assert,
+    near_panic,
     near_sdk::{
         self,
         env,
@@ -50,14 +59,11 @@ use mb_sdk::{
         Promise,
         PromiseOrValue,
     },
-    utils::{
-        ft_transfer,
-        near_parse,
-    },
 };
 
 use crate::{
     data::*,
+    listing::log_listing_update,
     Market,
     MarketExt,
 };
@@ -69,12 +75,16 @@ impl Market {
     /// according to the following rules:
     ///
     /// - The buyer must not be banned from using the market.
+    /// - The buyer must not be the lister, unless `allow_self_purchase` is
+    ///   set. Otherwise the deposit is refunded and an `nft_refund_offer`
+    ///   event is emitted.
     /// - The NFT must be listed for NEAR, not an FT.
-    /// - The listing must exist, otherwise the method panics and the buyer is
-    ///   automatically refunded.
+    /// - The listing must exist, otherwise the deposit is refunded and an
+    ///   `nft_refund_offer` event is emitted. This happens e.g. if another
+    ///   buyer was faster, and does not fail the transaction.
     /// - The attached deposit must equal or be larger than the price the NFT is
-    ///   listed for. If it is larger, the whole deposit will be shared between
-    ///   royalty holders and the market fee applies to the full deposit.
+    ///   listed for. If it is larger, the surplus is refunded to the buyer, and
+    ///   royalties and the market fee only apply to the listing price.
     /// - There must be no other offer currently executing on this listing.
     ///
     /// Should all these requirements be fullfilled, the offer will be inserted
@@ -83,6 +93,17 @@ impl Market {
     /// (processing a max of 50 royalty holders), and a cross-contract call
     /// `resolve_payout_near` on this market processes the payouts or failure
     /// of `nft_transfer_payout`.
+    ///
+    /// If the listing is an auction, the whole deposit is placed as a bid
+    /// instead, see `place_bid`.
+    ///
+    /// Buyers can specify an `on_purchase` hook as a contract and a message
+    /// of up to 256 bytes. Once the token has been transferred to the buyer
+    /// and the payout succeeded, the market calls `nft_on_purchase` on that
+    /// contract, e.g. to let a game know about the purchase. Failure of this
+    /// call does not revert the sale. The call receives `NFT_ON_PURCHASE_GAS`
+    /// (10 TGas), which the buyer needs to attach in addition to the gas
+    /// required for the sale itself.
     #[payable]
     pub fn buy(
         &mut self,
@@ -90,14 +111,46 @@ impl Market {
         token_id: String,
         referrer_id: Option<AccountId>,
         affiliate_id: Option<AccountId>,
-    ) -> Promise {
+        on_purchase: Option<(AccountId, String)>,
+    ) -> PromiseOrValue<()> {
         self.assert_not_banned(&env::predecessor_account_id());
 
-        let token_key = format!("{}<$>{}", nft_contract_id, token_id);
+        let token_key = token_key(&nft_contract_id, &token_id);
         let mut listing = match self.get_listing_internal(&token_key) {
-            None => env::panic_str(ERR_LISTING_NOT_FOUND),
+            None => {
+                let buyer_id = env::predecessor_account_id();
+                let deposit = env::attached_deposit();
+                log_refund_offer(
+                    nft_contract_id,
+                    token_id,
+                    buyer_id.clone(),
+                    &Currency::Near,
+                    deposit,
+                    OFFER_REFUND_REASON_LISTING_NOT_FOUND,
+                );
+                return PromiseOrValue::Promise(
+                    Promise::new(buyer_id).transfer(deposit),
+                );
+            }
             Some(l) => l,
         };
+        if self
+            .is_blocked_self_purchase(&listing, &env::predecessor_account_id())
+        {
+            let buyer_id = env::predecessor_account_id();
+            let deposit = env::attached_deposit();
+            log_refund_offer(
+                nft_contract_id,
+                token_id,
+                buyer_id.clone(),
+                &Currency::Near,
+                deposit,
+                OFFER_REFUND_REASON_SELF_PURCHASE,
+            );
+            return PromiseOrValue::Promise(
+                Promise::new(buyer_id).transfer(deposit),
+            );
+        }
 
         // Referrer/affiliate renaming with backwards compatibility
         // internally, this will be named referrer, externally affiliate
@@ -110,6 +163,11 @@ impl Market {
         let referral_cut = referrer_id.as_ref().map(|account| {
             self.referrers.get(account).unwrap_or(self.fallback_cut)
         });
+        near_assert!(
+            !exceeds_purchase_msg_len(&on_purchase),
+            "Cannot process purchase messages with more than {} bytes",
+            MAX_LEN_PURCHASE_MSG
+        );
 
         // NFT must be listed for NEAR
         if let Currency::FtContract(ft_contract) = listing.currency {
@@ -118,6 +176,20 @@ impl Market {
                 ft_contract
             ))
         }
+        // Auctions escrow the deposit as a bid
+        if let Some(auction) = self.auctions.get(&token_key) {
+            let bid = Offer {
+                offerer_id: env::predecessor_account_id(),
+                amount: env::attached_deposit(),
+                referrer_id,
+                referral_cut,
+            };
+            if let Err(reason) = self.place_bid(listing, &auction, bid) {
+                env::panic_str(&reason);
+            }
+            self.set_purchase_hook(&token_key, on_purchase);
+            return PromiseOrValue::Value(());
+        }
         // NEAR amount needs to be at least NFT asking price
         near_assert!(
             env::attached_deposit() >= listing.price,
@@ -129,15 +201,22 @@ impl Market {
             "Another offer currently executes on this listing"
         );
 
+        // Refund any deposit exceeding the asking price
+        let surplus = env::attached_deposit() - listing.price;
+        if surplus > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(surplus);
+        }
+
         // Happy path: insert offer, log event, process stuff
         let offer = Offer {
             offerer_id: env::predecessor_account_id(),
-            amount: env::attached_deposit(),
+            amount: listing.price,
             referrer_id: referrer_id.clone(),
             referral_cut,
         };
 
-        let (ref_earning, _) = self.get_affiliate_mintbase_amounts(&offer);
+        let (ref_earning, _) = self
+            .get_affiliate_mintbase_amounts(&offer, self.waives_cut(&listing));
         env::log_str(
             &events::NftMakeOfferData {
                 nft_contract_id,
@@ -146,7 +225,7 @@ impl Market {
                 offer_id: 0,
                 offerer_id: env::predecessor_account_id(),
                 currency: listing.currency.to_string(),
-                price: env::attached_deposit().into(),
+                price: listing.price.into(),
                 affiliate_id: referrer_id,
                 affiliate_amount: ref_earning.map(Into::into),
             }
@@ -155,29 +234,200 @@ impl Market {
 
         listing.current_offer = Some(offer);
         self.listings.insert(&token_key, &listing);
+        self.record_offer_start(&token_key);
+        self.set_purchase_hook(&token_key, on_purchase);
 
-        self.execute_transfer(
+        let price = listing.price;
+        PromiseOrValue::Promise(self.execute_transfer(
             listing,
             env::predecessor_account_id(),
-            env::attached_deposit(),
-        )
+            price,
+            0,
+        ))
+    }
+
+    /// Buying multiple NFTs that are listed for NEAR in a single transaction.
+    /// Each purchase is given as `(nft_contract_id, token_id, referrer_id)`,
+    /// and the rules of `buy` apply to each of them. Unlike `buy`, any
+    /// violation fails the whole call before an offer is inserted:
+    ///
+    /// - At most `MAX_LEN_BATCH_BUY` (5) tokens can be bought at once, and
+    ///   each of them only once.
+    /// - All listings must exist, be listed for NEAR, not be auctions, and
+    ///   have no offer in progress.
+    /// - The attached deposit must cover the sum of the listing prices. Any
+    ///   deposit exceeding that sum is refunded to the buyer.
+    ///
+    /// All sales are then executed in parallel. To fit them into the gas
+    /// limit of a single transaction, NFT contracts are asked for at most
+    /// `MAX_LEN_PAYOUT_NEAR_BATCH` (10) royalty holders per token. Each sale
+    /// requires `NFT_TRANSFER_PAYOUT_GAS` and
+    /// `NFT_RESOLVE_PAYOUT_NEAR_BATCH_GAS` (55 TGas in total), so buyers
+    /// should attach 300 TGas.
+    #[payable]
+    pub fn batch_buy(
+        &mut self,
+        purchases: Vec<(AccountId, String, Option<AccountId>)>,
+    ) -> Promise {
+        let buyer_id = env::predecessor_account_id();
+        self.assert_not_banned(&buyer_id);
+        near_assert!(
+            !purchases.is_empty() && purchases.len() <= MAX_LEN_BATCH_BUY,
+            "Can only buy between 1 and {} tokens at once",
+            MAX_LEN_BATCH_BUY
+        );
+
+        // validate all purchases before inserting any offer
+        let mut token_keys = HashSet::new();
+        let mut listings = Vec::with_capacity(purchases.len());
+        let mut total: Balance = 0;
+        for (nft_contract_id, token_id, referrer_id) in purchases {
+            let token_key = token_key(&nft_contract_id, &token_id);
+            near_assert!(
+                token_keys.insert(token_key.clone()),
+                "Cannot buy token {} twice",
+                token_id
+            );
+            let listing = match self.get_listing_internal(&token_key) {
+                None => env::panic_str(ERR_LISTING_NOT_FOUND),
+                Some(l) => l,
+            };
+            near_assert!(
+                listing.currency.is_near(),
+                "Token {} is not listed for NEAR",
+                token_id
+            );
+            near_assert!(
+                !self.auctions.contains_key(&token_key),
+                "Token {} is auctioned and cannot be bought in a batch",
+                token_id
+            );
+            near_assert!(
+                listing.current_offer.is_none(),
+                "Another offer currently executes on this listing"
+            );
+            near_assert!(
+                !self.is_blocked_self_purchase(&listing, &buyer_id),
+                "Cannot buy your own listing"
+            );
+            total = match total.checked_add(listing.price) {
+                None => near_panic!("Sum of listing prices overflows"),
+                Some(total) => total,
+            };
+            listings.push((listing, referrer_id));
+        }
+        near_assert!(
+            env::attached_deposit() >= total,
+            "Deposit needs to cover the sum of listing prices ({})",
+            total
+        );
+
+        // Refund any deposit exceeding the sum of prices
+        let surplus = env::attached_deposit() - total;
+        if surplus > 0 {
+            Promise::new(buyer_id.clone()).transfer(surplus);
+        }
+
+        let mut sales: Option<Promise> = None;
+        for (mut listing, referrer_id) in listings {
+            let referral_cut = referrer_id.as_ref().map(|account| {
+                self.referrers.get(account).unwrap_or(self.fallback_cut)
+            });
+            let offer = Offer {
+                offerer_id: buyer_id.clone(),
+                amount: listing.price,
+                referrer_id,
+                referral_cut,
+            };
+            self.log_make_offer(&listing, &offer);
+            listing.current_offer = Some(offer);
+            self.listings.insert(&listing.token_key(), &listing);
+            self.record_offer_start(&listing.token_key());
+
+            let price = listing.price;
+            let sale = self.execute_transfer_with(
+                listing,
+                buyer_id.clone(),
+                price,
+                0,
+                MAX_LEN_PAYOUT_NEAR_BATCH,
+                NFT_RESOLVE_PAYOUT_NEAR_BATCH_GAS,
+            );
+            sales = Some(match sales {
+                None => sale,
+                Some(sales) => sales.and(sale),
+            });
+        }
+        sales.unwrap()
     }
 
     /// Helper method to execute transfers for both NEAR or FT. Any checks must
-    /// happen prior to calling this.
-    fn execute_transfer(
+    /// happen prior to calling this. For FT payments, `ft_surplus` is the
+    /// amount that has been transferred in excess of `balance` and needs to be
+    /// returned to the FT contract for refunding.
+    pub(crate) fn execute_transfer(
         &mut self,
         listing: Listing,
         receiver_id: AccountId,
         balance: Balance,
+        ft_surplus: Balance,
+    ) -> Promise {
+        self.execute_transfer_with(
+            listing,
+            receiver_id,
+            balance,
+            ft_surplus,
+            MAX_LEN_PAYOUT_NEAR,
+            NFT_RESOLVE_PAYOUT_NEAR_GAS,
+        )
+    }
+
+    /// Same as `execute_transfer`, but NEAR payouts are limited to
+    /// `max_len_payout_near` royalty holders, and resolved with
+    /// `resolve_near_gas`.
+    fn execute_transfer_with(
+        &mut self,
+        listing: Listing,
+        receiver_id: AccountId,
+        balance: Balance,
+        ft_surplus: Balance,
+        max_len_payout_near: u32,
+        resolve_near_gas: near_sdk::Gas,
     ) -> Promise {
         let token_key = listing.token_key();
+        // the resolving callbacks rely on this snapshot, as the owner or the
+        // setting might change before they are executed
+        let waive_cut = self.waives_cut(&listing);
+        if waive_cut {
+            self.cut_waived_offers.insert(&token_key);
+        }
         let offer = listing.current_offer.unwrap();
-        let payout_percentage = match offer.referral_cut {
-            Some(cut) => 10000 - cut,
-            None => 10000 - self.fallback_cut,
+        let payout_amount = if waive_cut {
+            let (ref_earning, _) =
+                self.get_affiliate_mintbase_amounts(&offer, true);
+            balance - ref_earning.unwrap_or(0)
+        } else {
+            let payout_percentage = match offer.referral_cut {
+                Some(cut) => 10000 - cut,
+                None => 10000 - self.fallback_cut,
+            };
+            bps_of(balance, payout_percentage)
         };
 
+        let hook_gas = if self.purchase_hooks.contains_key(&token_key) {
+            NFT_ON_PURCHASE_GAS.0
+        } else {
+            0
+        };
+        let max_len_payout = if listing.currency.is_near() {
+            max_len_payout_near
+        } else {
+            Self::max_len_payout_ft(hook_gas)
+        };
+
+        // the listing must not change until the payout has been resolved
+        self.resolving.insert(&token_key);
+
         let nft_transfer = ext_nft::ext(listing.nft_contract_id)
             .with_attached_deposit(1)
             .with_static_gas(NFT_TRANSFER_PAYOUT_GAS)
@@ -185,27 +435,62 @@ impl Market {
                 receiver_id,
                 listing.nft_token_id,
                 listing.nft_approval_id,
-                (payout_percentage as u128 * balance / 10000).into(),
-                if listing.currency.is_near() {
-                    MAX_LEN_PAYOUT_NEAR
-                } else {
-                    MAX_LEN_PAYOUT_FT
-                },
+                payout_amount.into(),
+                max_len_payout,
             );
 
         let callback = if listing.currency.is_near() {
             ext_new_market::ext(env::current_account_id())
-                .with_static_gas(NFT_RESOLVE_PAYOUT_NEAR_GAS)
+                .with_static_gas(near_sdk::Gas(resolve_near_gas.0 + hook_gas))
                 .nft_resolve_payout_near(token_key)
         } else {
             ext_new_market::ext(env::current_account_id())
-                .with_static_gas(NFT_RESOLVE_PAYOUT_FT_GAS)
-                .nft_resolve_payout_ft(token_key)
+                .with_static_gas(near_sdk::Gas(
+                    Self::resolve_payout_ft_gas(max_len_payout).0 + hook_gas,
+                ))
+                .nft_resolve_payout_ft(
+                    token_key,
+                    ft_surplus.into(),
+                    max_len_payout,
+                )
         };
 
         nft_transfer.then(callback)
     }
 
+    /// The number of royalty holders that an FT sale can pay out, derived from
+    /// the gas that is left in the current call. `MAX_LEN_PAYOUT_FT` holders
+    /// can always be paid out, and each additional holder requires
+    /// `FT_PAYOUT_GAS_PER_RECIPIENT` (15 TGas) to be available on top of what
+    /// the transfer, the payout resolution, and the `hook_gas` for an
+    /// `on_purchase` hook need. The result never exceeds
+    /// `MAX_LEN_PAYOUT_FT_CAP`.
+    ///
+    /// Integrators that want to sell tokens with many royalty holders for FTs
+    /// should thus attach as much gas to `ft_transfer_call` as possible, and
+    /// make sure that the FT contract forwards it to `ft_on_transfer`.
+    fn max_len_payout_ft(hook_gas: u64) -> u32 {
+        let required = NFT_TRANSFER_PAYOUT_GAS.0
+            + NFT_RESOLVE_PAYOUT_FT_GAS.0
+            + FT_ON_TRANSFER_RESERVED_GAS.0
+            + hook_gas;
+        let available = env::prepaid_gas().0 - env::used_gas().0;
+        let additional =
+            available.saturating_sub(required) / FT_PAYOUT_GAS_PER_RECIPIENT.0;
+        (MAX_LEN_PAYOUT_FT as u64 + additional)
+            .min(MAX_LEN_PAYOUT_FT_CAP as u64) as u32
+    }
+
+    /// Static gas that `nft_resolve_payout_ft` requires to pay out
+    /// `max_len_payout` royalty holders.
+    fn resolve_payout_ft_gas(max_len_payout: u32) -> near_sdk::Gas {
+        let additional = max_len_payout.saturating_sub(MAX_LEN_PAYOUT_FT);
+        near_sdk::Gas(
+            NFT_RESOLVE_PAYOUT_FT_GAS.0
+                + additional as u64 * FT_PAYOUT_GAS_PER_RECIPIENT.0,
+        )
+    }
+
     /// Resolving the payout after a token has been bought with NEAR.
     /// The following cases are possible:
     ///
@@ -220,13 +505,15 @@ impl Market {
     ///   lister will regain their storage deposit.
     /// - The transfer succeeded and the payout is legit: Market and affiliate
     ///   cuts are processed, royalty holders will be paid out, and the lister
-    ///   will regain their storage deposit.
+    ///   will regain their storage deposit. Any yoctoNEAR left unassigned by
+    ///   rounding are paid to the seller.
     #[private]
     pub fn nft_resolve_payout_near(
         &mut self,
         token_key: String,
     ) -> PromiseOrValue<()> {
         let listing = self.get_listing_internal(&token_key).unwrap();
+        let waive_cut = self.cut_waived_offers.contains(&token_key);
         let offer = listing.current_offer.unwrap();
         let mut payout = match env::promise_result(0) {
             near_sdk::PromiseResult::NotReady => {
@@ -256,21 +543,44 @@ impl Market {
         };
 
         let (ref_earning, mb_earning) =
-            self.get_affiliate_mintbase_amounts(&offer);
-        let sum: u128 = payout.values().map(|x| x.0).sum();
+            self.get_affiliate_mintbase_amounts(&offer, waive_cut);
+        let max_sum = offer.amount - mb_earning - ref_earning.unwrap_or(0);
 
-        // Given payouts sum is too large
-        if sum > (offer.amount - mb_earning - ref_earning.unwrap_or(0)) {
+        // Given payouts sum is too large (or overflows)
+        let payout_sum =
+            match checked_payout_sum(&payout).filter(|sum| *sum <= max_sum) {
+                None => {
+                    Promise::new(offer.offerer_id).transfer(offer.amount);
+                    self.fail_listing(&token_key, true);
+                    return PromiseOrValue::Value(());
+                }
+                Some(sum) => sum,
+            };
+        // Given payout has too many recipients
+        if payout.len() as u32 > MAX_LEN_PAYOUT_NEAR {
             Promise::new(offer.offerer_id).transfer(offer.amount);
             self.fail_listing(&token_key, true);
             return PromiseOrValue::Value(());
         }
-        // Given payout has too many recipients
-        if payout.len() as u32 > MAX_LEN_PAYOUT_NEAR {
+        // Given payout routes too much away from the seller. Split owners
+        // cannot be told apart from royalty holders, so this is not reason
+        // enough to ban the NFT contract.
+        if self.exceeds_max_total_payout(
+            &payout,
+            &listing.nft_owner_id,
+            max_sum,
+        ) {
             Promise::new(offer.offerer_id).transfer(offer.amount);
-            self.fail_listing(&token_key, true);
+            self.fail_listing(&token_key, false);
             return PromiseOrValue::Value(());
         }
+        // Rounding in the cuts and the payout may leave some yoctoNEAR
+        // unassigned, which must not remain on the market
+        assign_payout_remainder(
+            &mut payout,
+            &listing.nft_owner_id,
+            max_sum - payout_sum,
+        );
 
         env::log_str(
             &events::NftSaleData {
@@ -284,18 +594,27 @@ impl Market {
                 affiliate_id: offer.referrer_id.clone(),
                 affiliate_amount: ref_earning.map(Into::into),
                 mintbase_amount: mb_earning.into(),
+                external_ref: self.external_refs.get(&token_key),
             }
             .serialize_event(),
         );
 
+        // an affiliate that also receives royalties or splits gets a single
+        // transfer
+        merge_referral_into_payout(&mut payout, offer.referrer_id, ref_earning);
         for (account, amount) in payout.drain() {
             Promise::new(account).transfer(amount.0);
         }
-        if let Some(referrer_id) = offer.referrer_id {
-            Promise::new(referrer_id).transfer(ref_earning.unwrap());
-        }
-        self.listings.remove(&token_key);
-        self.refund_listings(&listing.nft_owner_id, 1, 0);
+        self.call_purchase_hook(
+            &token_key,
+            &listing.nft_contract_id,
+            &listing.nft_token_id,
+            offer.offerer_id,
+        );
+        self.remove_listing_internal(&token_key);
+        self.resolving.remove(&token_key);
+        let stale_fee = self.stale_fee(listing.created_at);
+        self.refund_listings(&listing.nft_owner_id, 1, stale_fee);
 
         PromiseOrValue::Value(())
     }
@@ -308,12 +627,17 @@ impl Market {
     /// `ft_transfer_call` on the FT contract. The transfer takes places
     /// according to the same rules as `buy` and:
     ///
-    /// - The FT contract must not be banned.
+    /// - The FT contract must not be banned, otherwise the transfer is
+    ///   refunded, as it is for banned senders.
     /// - The NFT must be listed for tokens from the calling FT contract.
     ///
     /// The following chain of cross-contract calls is the same as for the
-    /// `buy` call. Due to gas constraints, FT listings are restricted to
-    /// paying out 10 royalty holders.
+    /// `buy` call. Bids on auctions are escrowed by the market instead, and
+    /// bids that are not accepted are refunded. A message that specifies
+    /// `expires_at` makes an unlisted offer instead, see
+    /// `make_unlisted_offer`. Due to gas constraints, FT listings are restricted to
+    /// paying out 10 royalty holders, unless enough gas is left to pay out
+    /// more of them (see `max_len_payout_ft`).
     ///
     /// In general gas limits require lots of fine tuning, and might differ from
     /// FT contract to FT contract. If using this, make sure to attach the
@@ -336,29 +660,80 @@ impl Market {
         }
 
         let ft_contract_id = env::predecessor_account_id();
-        let mut msg: BuyWithFtMessage =
-            near_parse(&msg, "Invalid arguments to buy using FT");
+        for account in [&sender_id, &ft_contract_id] {
+            if self.banned_accounts.contains(account) {
+                refund!("{} is banned from the market, refunding.", account);
+            }
+        }
 
-        self.assert_not_banned(&sender_id);
-        self.assert_not_banned(&ft_contract_id);
+        // Messages with an expiry are offers rather than purchases
+        if let Ok(msg) =
+            near_sdk::serde_json::from_str::<UnlistedOfferWithFtMessage>(&msg)
+        {
+            if let Err(reason) = self.make_unlisted_ft_offer(
+                sender_id,
+                ft_contract_id,
+                amount.0,
+                msg,
+            ) {
+                refund!("{}, refunding.", reason);
+            }
+            return PromiseOrValue::Value(U128(0));
+        }
+        // Malformed messages are refunded instead of panicking, as not all FT
+        // contracts handle failing `ft_on_transfer` calls gracefully
+        let mut msg: BuyWithFtMessage =
+            match near_sdk::serde_json::from_str(&msg) {
+                Ok(msg) => msg,
+                Err(_) => {
+                    refund!("Invalid arguments to buy using FT, refunding.");
+                }
+            };
 
-        let token_key = format!("{}<$>{}", msg.nft_contract_id, msg.token_id);
+        let token_key = token_key(&msg.nft_contract_id, &msg.token_id);
         let mut listing = match self.get_listing_internal(&token_key) {
-            None => env::panic_str(ERR_LISTING_NOT_FOUND),
+            None => {
+                log_refund_offer(
+                    msg.nft_contract_id,
+                    msg.token_id,
+                    sender_id,
+                    &Currency::FtContract(ft_contract_id),
+                    amount.0,
+                    OFFER_REFUND_REASON_LISTING_NOT_FOUND,
+                );
+                refund!("Listing not found, refunding.");
+            }
             Some(l) => l,
         };
+        if self.is_blocked_self_purchase(&listing, &sender_id) {
+            log_refund_offer(
+                msg.nft_contract_id,
+                msg.token_id,
+                sender_id,
+                &Currency::FtContract(ft_contract_id),
+                amount.0,
+                OFFER_REFUND_REASON_SELF_PURCHASE,
+            );
+            refund!("Cannot buy your own listing, refunding.");
+        }
 
         // Referrer/affiliate renaming with backwards compatibility
-        near_assert!(
-            msg.referrer_id.is_none() || msg.affiliate_id.is_none(),
-            "You can either specify a referrer_id or an affiliate_id, but not both."
-        );
+        if msg.referrer_id.is_some() && msg.affiliate_id.is_some() {
+            refund!("You can either specify a referrer_id or an affiliate_id, but not both, refunding.");
+        }
         msg.referrer_id = msg.referrer_id.or(msg.affiliate_id);
         // Insert default cut for non-whitelisted referrers
         let referral_cut = msg.referrer_id.as_ref().map(|account| {
             self.referrers.get(account).unwrap_or(self.fallback_cut)
         });
 
+        if exceeds_purchase_msg_len(&msg.on_purchase) {
+            refund!(
+                "Cannot process purchase messages with more than {} bytes, refunding.",
+                MAX_LEN_PURCHASE_MSG
+            );
+        }
+
         // NFT needs to be listed for FT
         if let Currency::Near = listing.currency {
             refund!("This NFT can only be bought with NEAR, refunding.");
@@ -374,6 +749,20 @@ impl Market {
                 );
             }
         }
+        // Auctions escrow the transferred amount as a bid
+        if let Some(auction) = self.auctions.get(&token_key) {
+            let bid = Offer {
+                offerer_id: sender_id,
+                amount: amount.0,
+                referrer_id: msg.referrer_id,
+                referral_cut,
+            };
+            if let Err(reason) = self.place_bid(listing, &auction, bid) {
+                refund!("{}, refunding.", reason);
+            }
+            self.set_purchase_hook(&token_key, msg.on_purchase);
+            return PromiseOrValue::Value(U128(0));
+        }
         // FT amount needs to be at least NFT asking price
         if listing.price > amount.0 {
             refund!("You have not supplied sufficient funds to buy this token, refunding.");
@@ -393,12 +782,13 @@ impl Market {
         // Happy path: insert offer, log event, process stuff
         let offer = Offer {
             offerer_id: sender_id.clone(),
-            amount: amount.0,
+            amount: listing.price,
             referrer_id: msg.referrer_id.clone(),
             referral_cut,
         };
 
-        let (ref_earning, _) = self.get_affiliate_mintbase_amounts(&offer);
+        let (ref_earning, _) = self
+            .get_affiliate_mintbase_amounts(&offer, self.waives_cut(&listing));
         env::log_str(
             &events::NftMakeOfferData {
                 nft_contract_id: msg.nft_contract_id,
@@ -407,7 +797,7 @@ impl Market {
                 offer_id: 0,
                 offerer_id: sender_id.clone(),
                 currency: listing.currency.to_string(),
-                price: amount,
+                price: listing.price.into(),
                 affiliate_id: msg.referrer_id,
                 affiliate_amount: ref_earning.map(Into::into),
             }
@@ -416,32 +806,59 @@ impl Market {
 
         listing.current_offer = Some(offer);
         self.listings.insert(&token_key, &listing);
+        self.record_offer_start(&token_key);
+        // the surplus remains with the market until it is returned for
+        // refunding by `nft_resolve_payout_ft`, and needs to be locked as well
+        self.lock_ft(&listing.currency, amount.0);
+        self.set_purchase_hook(&token_key, msg.on_purchase);
 
-        PromiseOrValue::Promise(
-            self.execute_transfer(listing, sender_id, amount.0),
-        )
+        // Any amount exceeding the asking price is returned to the FT
+        // contract for refunding once the payout resolves
+        let price = listing.price;
+        PromiseOrValue::Promise(self.execute_transfer(
+            listing,
+            sender_id,
+            price,
+            amount.0 - price,
+        ))
     }
 
     /// Payout resolution similar to `resolve_payout_near`, but with FT payouts
-    /// instead of native NEAR tokens.
+    /// instead of native NEAR tokens. The returned value is the amount that
+    /// the FT contract refunds to the buyer, which includes the `surplus`
+    /// paid in excess of the listing price. `max_len_payout` is the number of
+    /// royalty holders that has been requested from the NFT contract.
     #[private]
     pub fn nft_resolve_payout_ft(
         &mut self,
         token_key: String,
+        surplus: U128,
+        max_len_payout: u32,
     ) -> PromiseOrValue<U128> {
         let listing = self.get_listing_internal(&token_key).unwrap();
+        let waive_cut = self.cut_waived_offers.contains(&token_key);
         let offer = listing.current_offer.unwrap();
         let ft_contract_id = listing.currency.get_ft_contract_id().unwrap();
+        let is_escrowed = self.is_escrowed_offer(&token_key);
         let mut payout = match env::promise_result(0) {
             near_sdk::PromiseResult::NotReady => {
                 return PromiseOrValue::Promise(
                     ext_new_market::ext(env::current_account_id())
-                        .nft_resolve_payout_ft(token_key),
+                        .nft_resolve_payout_ft(
+                            token_key,
+                            surplus,
+                            max_len_payout,
+                        ),
                 );
             }
             near_sdk::PromiseResult::Failed => {
                 self.fail_listing(&token_key, false);
-                return PromiseOrValue::Value(offer.amount.into());
+                return self.refund_failed_ft_offer(
+                    is_escrowed,
+                    &ft_contract_id,
+                    offer,
+                    surplus.0,
+                );
             }
 
             near_sdk::PromiseResult::Successful(payout) => {
@@ -449,25 +866,58 @@ impl Market {
                     Ok(payout) => payout.payout,
                     Err(_) => {
                         self.fail_listing(&token_key, true);
-                        return PromiseOrValue::Value(offer.amount.into());
+                        return self.refund_failed_ft_offer(
+                            is_escrowed,
+                            &ft_contract_id,
+                            offer,
+                            surplus.0,
+                        );
                     }
                 }
             }
         };
 
         let (ref_earning, mb_earning) =
-            self.get_affiliate_mintbase_amounts(&offer);
-        let sum: u128 = payout.values().map(|x| x.0).sum();
+            self.get_affiliate_mintbase_amounts(&offer, waive_cut);
+        let max_sum = offer.amount - mb_earning - ref_earning.unwrap_or(0);
 
-        // Given payout sum is too large
-        if sum > (offer.amount - mb_earning - ref_earning.unwrap_or(0)) {
+        // Given payout sum is too large (or overflows)
+        if checked_payout_sum(&payout)
+            .filter(|sum| *sum <= max_sum)
+            .is_none()
+        {
             self.fail_listing(&token_key, true);
-            return PromiseOrValue::Value(offer.amount.into());
+            return self.refund_failed_ft_offer(
+                is_escrowed,
+                &ft_contract_id,
+                offer,
+                surplus.0,
+            );
         }
         // Given payout length is too large
-        if payout.len() as u32 > MAX_LEN_PAYOUT_FT {
+        if payout.len() as u32 > max_len_payout {
             self.fail_listing(&token_key, true);
-            return PromiseOrValue::Value(offer.amount.into());
+            return self.refund_failed_ft_offer(
+                is_escrowed,
+                &ft_contract_id,
+                offer,
+                surplus.0,
+            );
+        }
+        // Given payout routes too much away from the seller, see
+        // `nft_resolve_payout_near` for why this does not ban the NFT contract
+        if self.exceeds_max_total_payout(
+            &payout,
+            &listing.nft_owner_id,
+            max_sum,
+        ) {
+            self.fail_listing(&token_key, false);
+            return self.refund_failed_ft_offer(
+                is_escrowed,
+                &ft_contract_id,
+                offer,
+                surplus.0,
+            );
         }
 
         env::log_str(
@@ -482,54 +932,308 @@ impl Market {
                 affiliate_id: offer.referrer_id.clone(),
                 affiliate_amount: ref_earning.map(Into::into),
                 mintbase_amount: mb_earning.into(),
+                external_ref: self.external_refs.get(&token_key),
             }
             .serialize_event(),
         );
 
-        let payout_len = payout.len();
+        // an affiliate that also receives royalties or splits gets a single
+        // `ft_transfer`
+        merge_referral_into_payout(&mut payout, offer.referrer_id, ref_earning);
+
+        // each transfer requires one yoctoNEAR to be retained
+        let mut n_transfers = 0;
         for (account, amount) in payout.drain() {
-            ft_transfer(ft_contract_id.clone(), account, amount.0);
-        }
-        if let Some(referrer_id) = offer.referrer_id {
-            ft_transfer(ft_contract_id, referrer_id, ref_earning.unwrap());
+            if self.ft_payout(&ft_contract_id, account, amount.0) {
+                n_transfers += 1;
+            }
         }
-        self.listings.remove(&token_key);
-        self.refund_listings(&listing.nft_owner_id, 1, payout_len as u128 + 1);
+        self.call_purchase_hook(
+            &token_key,
+            &listing.nft_contract_id,
+            &listing.nft_token_id,
+            offer.offerer_id,
+        );
+        self.remove_listing_internal(&token_key);
+        self.resolving.remove(&token_key);
+        let retain = (self.stale_fee(listing.created_at) + n_transfers)
+            .min(self.listing_storage_deposit);
+        self.refund_listings(&listing.nft_owner_id, 1, retain);
+
+        self.unlock_ft(&listing.currency, surplus.0);
+        PromiseOrValue::Value(surplus)
+    }
 
-        PromiseOrValue::Value(0.into())
+    /// The return value of `nft_resolve_payout_ft` after a failed sale. The
+    /// FT contract refunds the returned amount to the buyer, except for
+    /// escrowed offers (see `is_escrowed_offer`), which have been transferred
+    /// in an earlier `ft_on_transfer` call and are thus refunded by the market
+    /// itself.
+    fn refund_failed_ft_offer(
+        &mut self,
+        is_escrowed: bool,
+        ft_contract_id: &AccountId,
+        offer: Offer,
+        surplus: Balance,
+    ) -> PromiseOrValue<U128> {
+        self.unlock_ft(&Currency::FtContract(ft_contract_id.clone()), surplus);
+        if is_escrowed {
+            self.ft_payout(ft_contract_id, offer.offerer_id, offer.amount);
+            return PromiseOrValue::Value(surplus.into());
+        }
+        PromiseOrValue::Value((offer.amount + surplus).into())
     }
 
     // ---------------------------- offers (common) ----------------------------
+    /// Shows how a sale at `price` would be split if it was made via
+    /// `referrer_id`, e.g. for affiliates to display projected earnings. The
+    /// seller payout is the amount that will be distributed by the NFT
+    /// contract, and thus still includes any royalties.
+    pub fn preview_affiliate_earning(
+        &self,
+        price: U128,
+        referrer_id: Option<AccountId>,
+    ) -> PreviewJson {
+        let referral_cut = referrer_id.as_ref().map(|account| {
+            self.referrers.get(account).unwrap_or(self.fallback_cut)
+        });
+        let payout_percentage = match referral_cut {
+            Some(cut) => 10000 - cut,
+            None => 10000 - self.fallback_cut,
+        };
+        let offer = Offer {
+            offerer_id: env::current_account_id(),
+            amount: price.0,
+            referrer_id,
+            referral_cut,
+        };
+        let (affiliate_amount, mintbase_amount) =
+            self.get_affiliate_mintbase_amounts(&offer, false);
+
+        PreviewJson {
+            affiliate_amount: affiliate_amount.unwrap_or(0).into(),
+            mintbase_amount: mintbase_amount.into(),
+            seller_payout_before_royalty: bps_of(price.0, payout_percentage)
+                .into(),
+        }
+    }
+
+    /// Show the offer that is currently being processed for a listing, if any.
+    /// Offers only exist while the sale is being executed.
+    pub fn get_offer(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> Option<OfferJson> {
+        let token_key = token_key(&nft_contract_id, &token_id);
+        self.get_listing_internal(&token_key)
+            .and_then(|listing| listing.current_offer)
+            .map(|offer| {
+                OfferJson::new(offer, self.offer_started_at(&token_key))
+            })
+    }
+
+    /// Checks whether `buyer_id` could currently buy a token by attaching
+    /// `deposit` to `buy` (without `currency`) or transferring `deposit` FTs
+    /// from the `currency` FT contract. This applies the same checks as `buy`
+    /// and `ft_on_transfer`, but the sale itself may still fail, e.g. if the
+    /// NFT contract rejects the transfer.
+    pub fn can_buy(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        buyer_id: AccountId,
+        deposit: U128,
+        currency: Option<AccountId>,
+    ) -> CanBuyJson {
+        let reason = self.buy_blocked_reason(
+            &token_key(&nft_contract_id, &token_id),
+            &buyer_id,
+            deposit.0,
+            currency.into(),
+        );
+        CanBuyJson {
+            ok: reason.is_none(),
+            reason,
+        }
+    }
+
+    /// Reason why a buy would currently be refunded or panic, `None` if it
+    /// would pass the checks of `buy` and `ft_on_transfer`.
+    fn buy_blocked_reason(
+        &self,
+        token_key: &String,
+        buyer_id: &AccountId,
+        deposit: Balance,
+        currency: Currency,
+    ) -> Option<String> {
+        if self.banned_accounts.contains(buyer_id) {
+            return Some(format!("{} is banned from the market", buyer_id));
+        }
+        if let Currency::FtContract(ft_contract_id) = &currency {
+            if self.banned_accounts.contains(ft_contract_id) {
+                return Some(format!(
+                    "{} is banned from the market",
+                    ft_contract_id
+                ));
+            }
+        }
+        let listing = match self.get_listing_internal(token_key) {
+            None => return Some(ERR_LISTING_NOT_FOUND.to_string()),
+            Some(listing) => listing,
+        };
+        if self.is_blocked_self_purchase(&listing, buyer_id) {
+            return Some("Cannot buy your own listing".into());
+        }
+        if listing.currency != currency {
+            return Some(format!(
+                "This NFT is listed for {}",
+                listing.currency.to_string()
+            ));
+        }
+        if let Some(auction) = self.auctions.get(token_key) {
+            return self.bid_blocked_reason(&listing, &auction, deposit);
+        }
+        if deposit < listing.price {
+            return Some(
+                "Deposit needs to be higher than listing price".into(),
+            );
+        }
+        if listing.current_offer.is_some() {
+            return Some(
+                "Another offer currently executes on this listing".into(),
+            );
+        }
+        None
+    }
+
     /// Calculate the amount that should be transferred to the affiliate and
-    /// retained by the market, based on an offer.
-    fn get_affiliate_mintbase_amounts(
+    /// retained by the market, based on an offer. If `waive_cut` is set, the
+    /// market retains nothing and the affiliate amount is unaffected.
+    pub(crate) fn get_affiliate_mintbase_amounts(
         &self,
         offer: &Offer,
+        waive_cut: bool,
     ) -> (Option<Balance>, Balance) {
         match offer.referral_cut {
             Some(cut) => {
-                let total_cut_amount = cut as u128 * offer.amount / 10_000;
-                let mb_amount =
-                    total_cut_amount * self.mintbase_cut as u128 / 10_000;
+                let total_cut_amount = bps_of(offer.amount, cut);
+                let mb_amount = bps_of(total_cut_amount, self.mintbase_cut);
                 let referrer_amount = total_cut_amount - mb_amount;
-                (Some(referrer_amount), mb_amount)
+                if waive_cut {
+                    (Some(referrer_amount), 0)
+                } else {
+                    (Some(referrer_amount), mb_amount)
+                }
             }
-            None => (None, self.fallback_cut as u128 * offer.amount / 10_000),
+            None if waive_cut => (None, 0),
+            None => (None, bps_of(offer.amount, self.fallback_cut)),
+        }
+    }
+
+    /// Whether a payout routes more than `max_total_payout_bps` of the amount
+    /// remaining after cuts to accounts other than the seller. Must only be
+    /// called once the payout sum has been checked against `max_sum`.
+    fn exceeds_max_total_payout(
+        &self,
+        payout: &HashMap<AccountId, U128>,
+        seller_id: &AccountId,
+        max_sum: Balance,
+    ) -> bool {
+        let to_others: Balance = payout
+            .iter()
+            .filter(|(account, _)| *account != seller_id)
+            .map(|(_, amount)| amount.0)
+            .sum();
+        to_others > bps_of(max_sum, self.max_total_payout_bps)
+    }
+
+    /// Stores the `on_purchase` hook for the offer that has just been inserted
+    /// into a listing, replacing the hook of any previous offer.
+    fn set_purchase_hook(
+        &mut self,
+        token_key: &String,
+        on_purchase: Option<(AccountId, String)>,
+    ) {
+        match on_purchase {
+            Some(hook) => self.purchase_hooks.insert(token_key, &hook),
+            None => self.purchase_hooks.remove(token_key),
+        };
+    }
+
+    /// Calls `nft_on_purchase` on the hook of a completed sale, if the buyer
+    /// specified one. No callback is attached, so the sale stands regardless
+    /// of the outcome.
+    fn call_purchase_hook(
+        &self,
+        token_key: &String,
+        nft_contract_id: &AccountId,
+        token_id: &str,
+        buyer_id: AccountId,
+    ) {
+        if let Some((hook_id, msg)) = self.purchase_hooks.get(token_key) {
+            ext_nft_on_purchase::ext(hook_id)
+                .with_static_gas(NFT_ON_PURCHASE_GAS)
+                .nft_on_purchase(
+                    buyer_id,
+                    nft_contract_id.clone(),
+                    token_id.to_string(),
+                    msg,
+                );
         }
     }
 
+    /// Emits an `nft_make_offer` event for an offer that is inserted into
+    /// `listing`.
+    pub(crate) fn log_make_offer(&self, listing: &Listing, offer: &Offer) {
+        let (ref_earning, _) = self
+            .get_affiliate_mintbase_amounts(offer, self.waives_cut(listing));
+        env::log_str(
+            &events::NftMakeOfferData {
+                nft_contract_id: listing.nft_contract_id.clone(),
+                nft_token_id: listing.nft_token_id.clone(),
+                nft_approval_id: listing.nft_approval_id,
+                offer_id: 0,
+                offerer_id: offer.offerer_id.clone(),
+                currency: listing.currency.to_string(),
+                price: offer.amount.into(),
+                affiliate_id: offer.referrer_id.clone(),
+                affiliate_amount: ref_earning.map(Into::into),
+            }
+            .serialize_event(),
+        );
+    }
+
+    /// Whether `buyer_id` is the lister and self-purchases are disallowed.
+    fn is_blocked_self_purchase(
+        &self,
+        listing: &Listing,
+        buyer_id: &AccountId,
+    ) -> bool {
+        !self.allow_self_purchase && listing.nft_owner_id == *buyer_id
+    }
+
+    /// Whether the market waives its cut on a sale of this listing, which is
+    /// the case for listings by the market owner if `waive_owner_cut` is set.
+    pub(crate) fn waives_cut(&self, listing: &Listing) -> bool {
+        self.waive_owner_cut && listing.nft_owner_id == self.owner
+    }
+
     /// Removes a listing, refunds the storage deposit to the lister, and bans
     /// the NFT contract from using the market. This does explicitly NOT refund
     /// the offer amount, as the mechanism for differs between payments with
     /// FTs and payments with NEAR.
     fn fail_listing(&mut self, token_key: &String, ban: bool) {
-        let listing = self.listings.remove(token_key).unwrap();
+        let listing = self.remove_listing_internal(token_key).unwrap();
+        let offer = listing.current_offer.as_ref().unwrap();
+        self.resolving.remove(token_key);
         env::log_str(
-            &NftFailedSaleData {
+            &NftFailedSaleDataV030 {
                 nft_contract_id: listing.nft_contract_id.clone(),
-                nft_token_id: listing.nft_token_id,
+                nft_token_id: listing.nft_token_id.clone(),
                 nft_approval_id: listing.nft_approval_id,
-                offer_id: 0,
+                offerer_id: offer.offerer_id.clone(),
+                amount: offer.amount.into(),
             }
             .serialize_event(),
         );
@@ -548,6 +1252,8 @@ impl Market {
     /// an offer "is stuck" and the listing can no longer be processed. Make
     /// sure that the XCC originating from the call to `buy` or
     /// `ft_transfer_call` that created the offer has terminated in a failure.
+    /// Offers whose payout has not been resolved can only be removed after
+    /// `offer_timeout_seconds`.
     #[payable]
     pub fn remove_offer(
         &mut self,
@@ -556,9 +1262,36 @@ impl Market {
     ) {
         // only owner is allowed to call this
         self.assert_predecessor_is_owner();
+        self.remove_offer_internal(nft_contract_id, token_id);
+    }
 
+    /// Same as `remove_offer`, but the removed offer is also refunded, like
+    /// with `release_stuck_offer`. NEAR offers are transferred back to the
+    /// offerer. FT offers are only refunded if they are escrowed auction bids
+    /// or unlisted offers, as the FT contract has already refunded any other
+    /// FT offer when the `ft_on_transfer` chain failed. Only the owner can
+    /// call this.
+    #[payable]
+    pub fn remove_offer_and_refund(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) {
+        self.assert_predecessor_is_owner();
+        let (listing, offer) =
+            self.remove_offer_internal(nft_contract_id, token_id);
+        self.refund_removed_offer(&listing, offer);
+    }
+
+    /// Removes the offer from a listing for `remove_offer` and
+    /// `remove_offer_and_refund`, returning both.
+    fn remove_offer_internal(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> (Listing, Offer) {
         // fetch listing
-        let token_key = format!("{}<$>{}", nft_contract_id, token_id);
+        let token_key = token_key(&nft_contract_id, &token_id);
         let listing = self.get_listing_internal(&token_key);
         near_assert!(listing.is_some(), "Listing does not exist");
         let mut listing = listing.unwrap();
@@ -567,8 +1300,261 @@ impl Market {
             "Listing does not have an offer"
         );
 
+        self.assert_not_running_auction(&token_key);
+
+        // offers that are being resolved can only be removed once they have
+        // timed out
+        let offer = listing.current_offer.take().unwrap();
+        let started_at = self.offer_started_at(&token_key);
+        if self.resolving.contains(&token_key) {
+            near_assert!(
+                env::block_timestamp()
+                    > started_at + self.offer_timeout_seconds * 1_000_000_000,
+                "{}",
+                ERR_OFFER_RESOLVING
+            );
+            self.resolving.remove(&token_key);
+        }
+
         // remove offer and store
-        listing.current_offer = None;
         self.listings.insert(&token_key, &listing);
+        self.purchase_hooks.remove(&token_key);
+        self.offer_started_at.remove(&token_key);
+        self.cut_waived_offers.remove(&token_key);
+        self.unlock_ft(&listing.currency, offer.amount);
+        log_listing_update(
+            &listing,
+            LISTING_FIELD_CURRENT_OFFER,
+            Some(offer.to_json_string(started_at)),
+            None,
+        );
+        (listing, offer)
+    }
+
+    /// Allows anyone to remove an offer that has not been resolved within
+    /// `offer_timeout_seconds`, which usually means that its processing failed
+    /// due to insufficient gas. NEAR offers are refunded to the offerer. FT
+    /// offers are not, as the FT contract refunds the offerer when the
+    /// `ft_on_transfer` chain fails. Bids on auctions and accepted unlisted
+    /// offers have been escrowed by the market and are refunded in either
+    /// currency, but bids can only be released while the auction is being
+    /// settled.
+    ///
+    /// To not refund offers for which the NFT has been transferred anyways,
+    /// the token is queried from the NFT contract, and the offer is only
+    /// released if the lister still owns it. Other stuck offers need to be
+    /// removed by the owner via `remove_offer_and_refund`.
+    ///
+    /// The same caveats as for `remove_offer` apply, so the timeout should be
+    /// chosen well above the time that processing an offer takes.
+    pub fn release_stuck_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> Promise {
+        let token_key = token_key(&nft_contract_id, &token_id);
+        let listing = self.get_stuck_offer_listing(&token_key);
+
+        ext_nft::ext(nft_contract_id.clone())
+            .with_static_gas(NFT_TOKEN_GAS)
+            .nft_token(token_id.clone())
+            .then(
+                ext_new_market::ext(env::current_account_id())
+                    .with_static_gas(RESOLVE_RELEASE_STUCK_OFFER_GAS)
+                    .resolve_release_stuck_offer(
+                        nft_contract_id,
+                        token_id,
+                        listing.nft_approval_id,
+                        self.offer_started_at(&token_key),
+                    ),
+            )
+    }
+
+    /// Callback for `release_stuck_offer`, releasing the offer if the lister
+    /// still owns the queried token.
+    #[private]
+    pub fn resolve_release_stuck_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        nft_approval_id: u64,
+        offer_started_at: u64,
+    ) {
+        let token: Option<NftTokenJson> = match env::promise_result(0) {
+            near_sdk::PromiseResult::Successful(value) => {
+                match near_sdk::serde_json::from_slice(&value) {
+                    Ok(token) => token,
+                    Err(_) => env::panic_str("Invalid token data"),
+                }
+            }
+            _ => env::panic_str("Failed to query token"),
+        };
+
+        // The offer might have been resolved while the token was queried
+        let token_key = token_key(&nft_contract_id, &token_id);
+        let mut listing = self.get_stuck_offer_listing(&token_key);
+        near_assert!(
+            listing.nft_approval_id == nft_approval_id
+                && self.offer_started_at(&token_key) == offer_started_at,
+            "Offer has changed while querying the token"
+        );
+        near_assert!(
+            token.map(|token| token.owner_id == listing.nft_owner_id)
+                == Some(true),
+            "Token is no longer owned by the lister, the offer can only be removed by the market owner"
+        );
+
+        // remove offer and store, refund NEAR
+        let offer = listing.current_offer.take().unwrap();
+        self.resolving.remove(&token_key);
+        self.listings.insert(&token_key, &listing);
+        self.purchase_hooks.remove(&token_key);
+        self.offer_started_at.remove(&token_key);
+        self.cut_waived_offers.remove(&token_key);
+        self.unlock_ft(&listing.currency, offer.amount);
+        log_listing_update(
+            &listing,
+            LISTING_FIELD_CURRENT_OFFER,
+            Some(offer.to_json_string(offer_started_at)),
+            None,
+        );
+        self.refund_removed_offer(&listing, offer);
     }
+
+    /// Fetches the listing of a stuck offer for `release_stuck_offer`,
+    /// panicking if it has no offer or the offer has not yet timed out.
+    fn get_stuck_offer_listing(&self, token_key: &String) -> Listing {
+        let listing = match self.get_listing_internal(token_key) {
+            None => env::panic_str(ERR_LISTING_NOT_FOUND),
+            Some(l) => l,
+        };
+        near_assert!(
+            listing.current_offer.is_some(),
+            "Listing does not have an offer"
+        );
+        self.assert_not_running_auction(token_key);
+
+        // offer must have timed out
+        let release_timestamp = self.offer_started_at(token_key)
+            + self.offer_timeout_seconds * 1_000_000_000;
+        near_assert!(
+            env::block_timestamp() > release_timestamp,
+            "Offer cannot be released before timestamp {}",
+            release_timestamp / 1_000_000_000
+        );
+        listing
+    }
+
+    /// Refunds an offer that has been removed from `listing` without a sale.
+    /// FT offers are only refunded if they are escrowed, see
+    /// `release_stuck_offer`.
+    fn refund_removed_offer(&mut self, listing: &Listing, offer: Offer) {
+        match listing.currency.get_ft_contract_id() {
+            None => {
+                Promise::new(offer.offerer_id).transfer(offer.amount);
+            }
+            Some(ft_contract_id) => {
+                if self.is_escrowed_offer(&listing.token_key()) {
+                    self.ft_payout(
+                        &ft_contract_id,
+                        offer.offerer_id,
+                        offer.amount,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Whether the current offer on a listing has been escrowed by the
+    /// market, either as a bid on an auction or as an accepted unlisted
+    /// offer, instead of being attached to the call that executes the sale.
+    fn is_escrowed_offer(&self, token_key: &String) -> bool {
+        self.auctions.contains_key(token_key)
+            || self.escrowed_offers.contains(token_key)
+    }
+}
+
+/// Whether the message of an `on_purchase` hook exceeds
+/// `MAX_LEN_PURCHASE_MSG`.
+fn exceeds_purchase_msg_len(on_purchase: &Option<(AccountId, String)>) -> bool {
+    on_purchase
+        .as_ref()
+        .map(|(_, msg)| msg.len() > MAX_LEN_PURCHASE_MSG)
+        .unwrap_or(false)
+}
+
+/// Adds the referral earnings to the payout, summing up the amounts if the
+/// referrer is already a payout recipient.
+fn merge_referral_into_payout(
+    payout: &mut HashMap<AccountId, U128>,
+    referrer_id: Option<AccountId>,
+    ref_earning: Option<Balance>,
+) {
+    if let (Some(referrer_id), Some(ref_earning)) = (referrer_id, ref_earning) {
+        let amount = payout.entry(referrer_id).or_insert(U128(0));
+        *amount = U128(amount.0 + ref_earning);
+    }
+}
+
+/// Adds `remainder` to the payout of the seller, or to the largest payout if
+/// the seller is not a payout recipient, such that the payout accounts for
+/// the full offer amount after cuts.
+fn assign_payout_remainder(
+    payout: &mut HashMap<AccountId, U128>,
+    seller_id: &AccountId,
+    remainder: Balance,
+) {
+    if remainder == 0 {
+        return;
+    }
+    let receiver_id = if payout.contains_key(seller_id) {
+        seller_id.clone()
+    } else {
+        payout
+            .iter()
+            .max_by_key(|(_, amount)| amount.0)
+            .map(|(account, _)| account.clone())
+            .unwrap_or_else(|| seller_id.clone())
+    };
+    let amount = payout.entry(receiver_id).or_insert(U128(0));
+    *amount = U128(amount.0 + remainder);
+}
+
+/// Sums up the payout amounts, returning `None` on overflow. A malicious NFT
+/// contract could otherwise return a payout that wraps around to pass the
+/// check against the offer amount.
+fn checked_payout_sum(payout: &HashMap<AccountId, U128>) -> Option<Balance> {
+    payout
+        .values()
+        .try_fold(0u128, |sum, amount| sum.checked_add(amount.0))
+}
+
+/// Computes `amount * bps / 10_000` (rounded down) without overflowing for
+/// amounts close to `u128::MAX`, which FT offers can reach.
+fn bps_of(amount: Balance, bps: u16) -> Balance {
+    let bps = bps as u128;
+    amount / 10_000 * bps + amount % 10_000 * bps / 10_000
+}
+
+/// Emits an `nft_refund_offer` event for an offer that has been refunded
+/// before it could be inserted into a listing.
+pub(crate) fn log_refund_offer(
+    nft_contract_id: AccountId,
+    nft_token_id: String,
+    offerer_id: AccountId,
+    currency: &Currency,
+    amount: Balance,
+    reason: &str,
+) {
+    env::log_str(
+        &events::NftRefundOfferData {
+            nft_contract_id,
+            nft_token_id,
+            offerer_id,
+            currency: currency.to_string(),
+            amount: amount.into(),
+            reason: reason.to_string(),
+        }
+        .serialize_event(),
+    );
 }
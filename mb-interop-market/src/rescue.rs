@@ -0,0 +1,157 @@
+use mb_sdk::{
+    events::market_v2 as events,
+    interfaces::{
+        ext_ft,
+        ext_new_market,
+        ext_nft,
+    },
+    near_assert,
+    near_sdk::{
+        self,
+        env,
+        json_types::U128,
+        AccountId,
+        Balance,
+        Promise,
+    },
+    utils::ft_transfer,
+};
+
+use crate::{
+    data::*,
+    Market,
+    MarketExt,
+};
+
+#[near_sdk::near_bindgen]
+impl Market {
+    /// Transfers an NFT that is owned by the market account itself (e.g. due
+    /// to an `nft_transfer` where `nft_approve` was intended) to
+    /// `receiver_id`. Tokens that are currently listed on the market cannot be
+    /// rescued. Only the owner can call this.
+    #[payable]
+    pub fn rescue_nft(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        receiver_id: AccountId,
+    ) -> Promise {
+        self.assert_predecessor_is_owner();
+
+        let token_key = token_key(&nft_contract_id, &token_id);
+        near_assert!(
+            self.get_listing_internal(&token_key).is_none(),
+            "Cannot rescue a token that is listed on the market"
+        );
+
+        env::log_str(
+            &events::NftRescueData {
+                nft_contract_id: nft_contract_id.clone(),
+                nft_token_id: token_id.clone(),
+                receiver_id: receiver_id.clone(),
+            }
+            .serialize_event(),
+        );
+
+        ext_nft::ext(nft_contract_id)
+            .with_attached_deposit(1)
+            .with_static_gas(NFT_TRANSFER_GAS)
+            .nft_transfer(receiver_id, token_id, None, None)
+    }
+
+    /// Transfers FTs that are stranded on the market account (e.g. from failed
+    /// refunds or direct transfers) to `receiver_id`. The FT balance of the
+    /// market is queried first, and amounts that are locked in offers (bids,
+    /// escrowed unlisted offers, and offers currently being processed) or
+    /// accrued for payout recipients are not available for rescue. Only the
+    /// owner can call this.
+    #[payable]
+    pub fn rescue_ft(
+        &mut self,
+        ft_contract_id: AccountId,
+        amount: U128,
+        receiver_id: AccountId,
+    ) -> Promise {
+        self.assert_predecessor_is_owner();
+
+        ext_ft::ext(ft_contract_id.clone())
+            .with_static_gas(FT_BALANCE_OF_GAS)
+            .ft_balance_of(env::current_account_id())
+            .then(
+                ext_new_market::ext(env::current_account_id())
+                    .with_static_gas(FT_RESOLVE_RESCUE_GAS)
+                    .ft_resolve_rescue(ft_contract_id, amount, receiver_id),
+            )
+    }
+
+    /// Callback for `rescue_ft`, executing the transfer if the rescued amount
+    /// does not exceed the FT balance that is not locked in offers.
+    #[private]
+    pub fn ft_resolve_rescue(
+        &mut self,
+        ft_contract_id: AccountId,
+        amount: U128,
+        receiver_id: AccountId,
+    ) -> Promise {
+        let balance: U128 = match env::promise_result(0) {
+            near_sdk::PromiseResult::Successful(value) => {
+                match near_sdk::serde_json::from_slice::<U128>(&value) {
+                    Ok(balance) => balance,
+                    Err(_) => env::panic_str("Invalid FT balance"),
+                }
+            }
+            _ => env::panic_str("Failed to query FT balance"),
+        };
+        let locked = self.ft_locked_total(&ft_contract_id)
+            + self.ft_accruals_total(&ft_contract_id);
+        let available = balance.0.saturating_sub(locked);
+        near_assert!(
+            amount.0 <= available,
+            "Cannot rescue {} of {}, only {} are not locked in offers or accruals",
+            amount.0,
+            ft_contract_id,
+            available
+        );
+
+        env::log_str(
+            &events::FtRescueData {
+                ft_contract_id: ft_contract_id.clone(),
+                amount,
+                receiver_id: receiver_id.clone(),
+            }
+            .serialize_event(),
+        );
+
+        ft_transfer(ft_contract_id, receiver_id, amount.0)
+    }
+
+    /// Sum of all FT amounts from the given contract that are currently
+    /// locked in offers, including escrowed unlisted offers.
+    pub(crate) fn ft_locked_total(
+        &self,
+        ft_contract_id: &AccountId,
+    ) -> Balance {
+        self.ft_locked_total.get(ft_contract_id).unwrap_or(0)
+    }
+
+    /// Marks `amount` as locked in an offer if `currency` is an FT, such that
+    /// it cannot be rescued.
+    pub(crate) fn lock_ft(&mut self, currency: &Currency, amount: Balance) {
+        if let Some(ft_contract_id) = currency.get_ft_contract_id() {
+            let total = self.ft_locked_total(&ft_contract_id);
+            self.ft_locked_total
+                .insert(&ft_contract_id, &(total + amount));
+        }
+    }
+
+    /// Releases `amount` that has been locked via `lock_ft` once the offer has
+    /// been paid out or refunded. Offers from before the total was tracked
+    /// have never been locked, hence the saturation.
+    pub(crate) fn unlock_ft(&mut self, currency: &Currency, amount: Balance) {
+        if let Some(ft_contract_id) = currency.get_ft_contract_id() {
+            let total = self.ft_locked_total(&ft_contract_id);
+            self.ft_locked_total
+                .insert(&ft_contract_id, &total.saturating_sub(amount));
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use mb_sdk::near_sdk::{
+    self,
+    borsh,
+    env,
+};
+
+use crate::{
+    data::*,
+    Market,
+    MarketExt,
+};
+
+#[near_sdk::near_bindgen]
+impl Market {
+    /// Inserts a listing without going through `nft_on_approve`, e.g. to test
+    /// edge cases of resolving payouts. No checks are performed on the listing
+    /// and the NFT contract is not queried. The attached deposit is credited
+    /// as storage deposit of the lister, as if it had been deposited via
+    /// `deposit_storage`. The listing is expected in borsh serialization.
+    /// Only the market itself can call this.
+    #[private]
+    #[payable]
+    pub fn debug_seed_listing(
+        &mut self,
+        #[serializer(borsh)] listing: Listing,
+    ) {
+        let deposit = env::attached_deposit();
+        let old_deposit = self.storage_deposit_by(&listing.nft_owner_id);
+        self.storage_deposits_by_account
+            .insert(&listing.nft_owner_id, &(old_deposit + deposit));
+        self.increase_listings_count(&listing.nft_owner_id, 1);
+        self.listings.insert(&listing.token_key(), &listing);
+    }
+}
@@ -0,0 +1,600 @@
+//! Offers on tokens that are not listed on the market. The offered NEAR or FTs
+//! are held in escrow by the market, and the token owner can accept an offer
+//! by approving the market on the NFT contract and calling
+//! `accept_unlisted_offer` with the resulting approval ID. Ownership and
+//! approval are verified via `nft_token` before the sale is executed like any
+//! other sale on the market, including the fallback cut.
+//!
+//! Offers can also be made on listed tokens, as long as they are made in a
+//! different currency than the listing (counter-currency offers). Accepting
+//! such an offer replaces the listing, and the sale is settled in the
+//! currency of the offer. This includes royalties, which are thus paid out in
+//! the offer currency rather than the listing currency.
+//!
+//! Offerers can withdraw their offers at any time, and expired offers can be
+//! removed by anyone, refunding the escrowed amounts to the offerers.
+
+use mb_sdk::{
+    events::market_v2 as events,
+    interfaces::{
+        ext_new_market,
+        ext_nft,
+    },
+    near_assert,
+    near_panic,
+    near_sdk::{
+        self,
+        env,
+        json_types::U64,
+        AccountId,
+        Balance,
+        Promise,
+        PromiseOrValue,
+    },
+};
+
+use crate::{
+    data::*,
+    Market,
+    MarketExt,
+};
+
+#[near_sdk::near_bindgen]
+impl Market {
+    /// Make an offer in NEAR on a token that is not listed on the market, or
+    /// that is listed for an FT. The attached deposit minus
+    /// `UNLISTED_OFFER_STORAGE_DEPOSIT` is the offered amount, which is held
+    /// in escrow until the offer is accepted, withdrawn, or removed after
+    /// `expires_at` (nanosecond timestamp). Each account may only have one
+    /// offer per token, and each token may only have
+    /// `MAX_UNLISTED_OFFERS_PER_TOKEN` offers. Beyond that, an offer needs to
+    /// outbid the lowest offer in the same currency, which is then refunded.
+    ///
+    /// Offers in FTs are made via `ft_transfer_call` with a message containing
+    /// `nft_contract_id`, `token_id`, and `expires_at`. The FT contract must
+    /// have been registered via `register_ft`, and the offerer needs a storage
+    /// deposit of at least `UNLISTED_OFFER_STORAGE_DEPOSIT` on the market,
+    /// which is refunded in NEAR like for NEAR offers.
+    #[payable]
+    pub fn make_unlisted_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        expires_at: U64,
+    ) {
+        let offerer_id = env::predecessor_account_id();
+        self.assert_not_banned(&offerer_id);
+        self.assert_not_banned(&nft_contract_id);
+        let deposit = env::attached_deposit();
+        near_assert!(
+            deposit > UNLISTED_OFFER_STORAGE_DEPOSIT,
+            "Deposit must exceed the storage deposit of {} yoctoNEAR",
+            UNLISTED_OFFER_STORAGE_DEPOSIT
+        );
+
+        let offer = UnlistedOffer {
+            offerer_id,
+            amount: deposit - UNLISTED_OFFER_STORAGE_DEPOSIT,
+            created_at: env::block_timestamp(),
+            expires_at: expires_at.0,
+        };
+        if let Err(reason) = self.insert_unlisted_offer(
+            nft_contract_id,
+            token_id,
+            offer,
+            Currency::Near,
+        ) {
+            near_panic!("{}", reason);
+        }
+    }
+
+    /// Withdraw your own unlisted offer, refunding the escrowed amount and the
+    /// storage deposit.
+    #[payable]
+    pub fn withdraw_unlisted_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) {
+        near_sdk::assert_one_yocto();
+        let offerer_id = env::predecessor_account_id();
+        let token_key = token_key(&nft_contract_id, &token_id);
+        let (offer, currency) =
+            match self.take_unlisted_offer(&token_key, &offerer_id) {
+                None => near_panic!("You have no offer on this token"),
+                Some(offer) => offer,
+            };
+        self.refund_unlisted_offer(
+            nft_contract_id,
+            token_id,
+            offer,
+            &currency,
+            OFFER_REMOVAL_REASON_WITHDRAWN,
+        );
+    }
+
+    /// Remove all expired offers on a token, refunding the escrowed amounts
+    /// and storage deposits to the respective offerers. Anyone can call this.
+    pub fn remove_expired_unlisted_offers(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) {
+        let token_key = token_key(&nft_contract_id, &token_id);
+        let now = env::block_timestamp();
+        let (expired, valid): (Vec<_>, Vec<_>) = self
+            .unlisted_offers
+            .get(&token_key)
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|offer| offer.expires_at < now);
+        near_assert!(!expired.is_empty(), "No expired offers on this token");
+
+        self.store_unlisted_offers(&token_key, valid);
+        for offer in expired {
+            let currency = self
+                .take_unlisted_offer_currency(&token_key, &offer.offerer_id);
+            self.unlock_ft(&currency, offer.amount);
+            self.refund_unlisted_offer(
+                nft_contract_id.clone(),
+                token_id.clone(),
+                offer,
+                &currency,
+                OFFER_REMOVAL_REASON_EXPIRED,
+            );
+        }
+    }
+
+    /// Accept the offer of `offerer_id` on your token. The market needs to be
+    /// approved for the token with `approval_id`. Like a listing, the sale
+    /// requires a storage deposit of `listing_storage_deposit`, which is
+    /// refunded after the sale.
+    ///
+    /// If the token is listed in a different currency than the offer, the
+    /// listing is replaced, which requires that it could be unlisted. The sale
+    /// is settled in the currency of the offer. Listings in the currency of
+    /// the offer and auctions need to be unlisted before accepting offers.
+    ///
+    /// The market temporarily lists the token with the offer in progress and
+    /// verifies ownership and approval via `nft_token`. If this fails, the
+    /// offer is restored, but a replaced listing is not.
+    pub fn accept_unlisted_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        offerer_id: AccountId,
+        approval_id: u64,
+    ) -> Promise {
+        let owner_id = env::predecessor_account_id();
+        let token_key = token_key(&nft_contract_id, &token_id);
+        let (offer, currency) =
+            match self.take_unlisted_offer(&token_key, &offerer_id) {
+                None => {
+                    near_panic!("{} has no offer on this token", offerer_id)
+                }
+                Some(offer) => offer,
+            };
+        near_assert!(
+            offer.expires_at >= env::block_timestamp(),
+            "This offer has expired"
+        );
+
+        // listings in another currency are replaced, but keep accruing their
+        // stale fee
+        let created_at = match self.get_listing_internal(&token_key) {
+            None => env::block_timestamp(),
+            Some(listing) => {
+                near_assert!(
+                    listing.currency != currency
+                        && !self.auctions.contains_key(&token_key),
+                    "This token is listed, unlist it before accepting offers"
+                );
+                let listing = self.unlist_single_nft(&token_key);
+                self.decrease_listings_count(&owner_id, 1);
+                env::log_str(
+                    &events::NftUnlistData {
+                        nft_contract_id: nft_contract_id.clone(),
+                        nft_token_id: token_id.clone(),
+                        nft_approval_id: listing.nft_approval_id,
+                    }
+                    .serialize_event(),
+                );
+                listing.created_at
+            }
+        };
+        let resolve_gas = match currency {
+            Currency::Near => RESOLVE_ACCEPT_UNLISTED_OFFER_GAS,
+            Currency::FtContract(_) => RESOLVE_ACCEPT_UNLISTED_FT_OFFER_GAS,
+        };
+
+        // lock the token by listing it with the offer in progress
+        self.lock_ft(&currency, offer.amount);
+        self.create_listing(
+            Listing {
+                nft_token_id: token_id.clone(),
+                nft_approval_id: approval_id,
+                nft_owner_id: owner_id,
+                nft_contract_id: nft_contract_id.clone(),
+                price: offer.amount,
+                currency,
+                created_at,
+                current_offer: Some(Offer {
+                    offerer_id: offer.offerer_id,
+                    amount: offer.amount,
+                    referrer_id: None,
+                    referral_cut: None,
+                }),
+            },
+            None,
+            None,
+        );
+        self.record_offer_start(&token_key);
+
+        ext_nft::ext(nft_contract_id)
+            .with_static_gas(NFT_TOKEN_GAS)
+            .nft_token(token_id)
+            .then(
+                ext_new_market::ext(env::current_account_id())
+                    .with_static_gas(resolve_gas)
+                    .resolve_accept_unlisted_offer(
+                        token_key,
+                        approval_id,
+                        offer.created_at,
+                        offer.expires_at,
+                    ),
+            )
+    }
+
+    /// Callback for `accept_unlisted_offer`. Executes the sale if the caller
+    /// owns the token and the market is approved, otherwise removes the
+    /// temporary listing and restores the offer. `offer_created_at` and
+    /// `offer_expires_at` are required to restore the offer.
+    #[private]
+    pub fn resolve_accept_unlisted_offer(
+        &mut self,
+        token_key: String,
+        approval_id: u64,
+        offer_created_at: u64,
+        offer_expires_at: u64,
+    ) -> PromiseOrValue<()> {
+        let listing = self.get_listing_internal(&token_key).unwrap();
+        let offer = listing.current_offer.clone().unwrap();
+
+        let token: Option<NftTokenJson> = match env::promise_result(0) {
+            near_sdk::PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice(&value).ok().flatten()
+            }
+            _ => None,
+        };
+        let is_valid = token
+            .map(|token| {
+                token.owner_id == listing.nft_owner_id
+                    && token
+                        .approved_account_ids
+                        .map(|approvals| {
+                            approvals.get(&env::current_account_id())
+                                == Some(&approval_id)
+                        })
+                        .unwrap_or(true)
+            })
+            .unwrap_or(false);
+
+        if !is_valid {
+            self.remove_listing_internal(&token_key);
+            env::log_str(
+                &events::NftUnlistWithReasonData {
+                    nft_contract_id: listing.nft_contract_id.clone(),
+                    nft_token_id: listing.nft_token_id.clone(),
+                    nft_approval_id: approval_id,
+                    reason: UNLIST_REASON_STALE.to_string(),
+                    forced: false,
+                }
+                .serialize_event(),
+            );
+            self.refund_listings(&listing.nft_owner_id, 1, 0);
+
+            if let Some(ft_contract_id) = listing.currency.get_ft_contract_id()
+            {
+                self.unlisted_offer_currencies.insert(
+                    &(token_key.clone(), offer.offerer_id.clone()),
+                    &ft_contract_id,
+                );
+            }
+            self.lock_ft(&listing.currency, offer.amount);
+            let mut offers =
+                self.unlisted_offers.get(&token_key).unwrap_or_default();
+            offers.push(UnlistedOffer {
+                offerer_id: offer.offerer_id,
+                amount: offer.amount,
+                created_at: offer_created_at,
+                expires_at: offer_expires_at,
+            });
+            self.unlisted_offers.insert(&token_key, &offers);
+            return PromiseOrValue::Value(());
+        }
+
+        // the listing now covers the storage, and the market refunds the
+        // escrowed offer should the sale fail
+        Promise::new(offer.offerer_id.clone())
+            .transfer(UNLISTED_OFFER_STORAGE_DEPOSIT);
+        self.escrowed_offers.insert(&token_key);
+        env::log_str(
+            &events::NftMakeOfferData {
+                nft_contract_id: listing.nft_contract_id.clone(),
+                nft_token_id: listing.nft_token_id.clone(),
+                nft_approval_id: approval_id,
+                offer_id: 0,
+                offerer_id: offer.offerer_id.clone(),
+                currency: listing.currency.to_string(),
+                price: offer.amount.into(),
+                affiliate_id: None,
+                affiliate_amount: None,
+            }
+            .serialize_event(),
+        );
+        PromiseOrValue::Promise(self.execute_transfer(
+            listing,
+            offer.offerer_id,
+            offer.amount,
+            0,
+        ))
+    }
+
+    /// Show all unlisted offers on a token, including counter-currency
+    /// offers on listed tokens.
+    pub fn get_unlisted_offers(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> Vec<UnlistedOfferJson> {
+        let token_key = token_key(&nft_contract_id, &token_id);
+        self.unlisted_offers
+            .get(&token_key)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|offer| {
+                let currency: Currency = self
+                    .unlisted_offer_currencies
+                    .get(&(token_key.clone(), offer.offerer_id.clone()))
+                    .into();
+                UnlistedOfferJson::new(offer, &currency)
+            })
+            .collect()
+    }
+
+    /// Escrows an FT offer that has been transferred via `ft_transfer_call`,
+    /// see `make_unlisted_offer`. Returns the reason if the offer cannot be
+    /// made, in which case the caller needs to refund it.
+    pub(crate) fn make_unlisted_ft_offer(
+        &mut self,
+        offerer_id: AccountId,
+        ft_contract_id: AccountId,
+        amount: Balance,
+        msg: UnlistedOfferWithFtMessage,
+    ) -> Result<(), String> {
+        if self.banned_accounts.contains(&msg.nft_contract_id) {
+            return Err(format!(
+                "{} is banned from the market",
+                msg.nft_contract_id
+            ));
+        }
+        if self.ft_infos.get(&ft_contract_id).is_none() {
+            return Err(format!(
+                "Offers in FTs from {} are not accepted",
+                ft_contract_id
+            ));
+        }
+        if amount == 0 {
+            return Err("Cannot make an offer without an amount".to_string());
+        }
+        if self.free_storage_deposit(&offerer_id)
+            < UNLISTED_OFFER_STORAGE_DEPOSIT
+        {
+            return Err("Storage for offer not covered".to_string());
+        }
+
+        let offer = UnlistedOffer {
+            offerer_id: offerer_id.clone(),
+            amount,
+            created_at: env::block_timestamp(),
+            expires_at: msg.expires_at.0,
+        };
+        let currency = Currency::FtContract(ft_contract_id);
+        self.insert_unlisted_offer(
+            msg.nft_contract_id,
+            msg.token_id,
+            offer,
+            currency.clone(),
+        )?;
+        self.lock_ft(&currency, amount);
+        let storage_deposit = self.storage_deposit_by(&offerer_id);
+        self.storage_deposits_by_account.insert(
+            &offerer_id,
+            &(storage_deposit - UNLISTED_OFFER_STORAGE_DEPOSIT),
+        );
+        Ok(())
+    }
+
+    /// Validates and stores an unlisted offer in `currency`, and emits the
+    /// corresponding event. Returns the reason if the offer cannot be made.
+    fn insert_unlisted_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        offer: UnlistedOffer,
+        currency: Currency,
+    ) -> Result<(), String> {
+        if token_id.len() > 128 {
+            return Err(
+                "Cannot process token IDs with more than 128 bytes".to_string()
+            );
+        }
+        if token_id.contains(TOKEN_KEY_SEPARATOR) {
+            return Err(ERR_TOKEN_KEY_SEPARATOR.to_string());
+        }
+
+        // listed tokens only accept offers in another currency
+        let token_key = token_key(&nft_contract_id, &token_id);
+        if let Some(listing) = self.get_listing_internal(&token_key) {
+            if listing.currency == currency
+                || self.auctions.contains_key(&token_key)
+            {
+                return Err(
+                    "This token is listed, use `buy` instead".to_string()
+                );
+            }
+        }
+        if offer.expires_at <= env::block_timestamp() {
+            return Err("Offer expiry must be in the future".to_string());
+        }
+
+        let mut offers =
+            self.unlisted_offers.get(&token_key).unwrap_or_default();
+        if offers.iter().any(|o| o.offerer_id == offer.offerer_id) {
+            return Err("You already have an offer on this token".to_string());
+        }
+        if offers.len() >= MAX_UNLISTED_OFFERS_PER_TOKEN {
+            // Once a token has the maximum number of offers, new offers need
+            // to outbid the lowest one in the same currency, which is then
+            // refunded. Otherwise the token could be blocked by dust offers.
+            let lowest = offers
+                .iter()
+                .enumerate()
+                .filter(|(_, o)| {
+                    self.unlisted_offer_currency(&token_key, &o.offerer_id)
+                        == currency
+                })
+                .min_by_key(|(_, o)| o.amount)
+                .filter(|(_, o)| o.amount < offer.amount)
+                .map(|(index, _)| index);
+            let index = match lowest {
+                None => {
+                    return Err(format!(
+                        "Cannot make more than {} offers on a single token without outbidding the lowest offer in the same currency",
+                        MAX_UNLISTED_OFFERS_PER_TOKEN
+                    ))
+                }
+                Some(index) => index,
+            };
+            let outbid = offers.remove(index);
+            let outbid_currency = self
+                .take_unlisted_offer_currency(&token_key, &outbid.offerer_id);
+            self.unlock_ft(&outbid_currency, outbid.amount);
+            self.refund_unlisted_offer(
+                nft_contract_id.clone(),
+                token_id.clone(),
+                outbid,
+                &outbid_currency,
+                OFFER_REFUND_REASON_OUTBID,
+            );
+        }
+
+        env::log_str(
+            &events::NftUnlistedOfferData {
+                nft_contract_id,
+                nft_token_id: token_id,
+                offerer_id: offer.offerer_id.clone(),
+                currency: currency.to_string(),
+                amount: offer.amount.into(),
+                expires_at: offer.expires_at.into(),
+            }
+            .serialize_event(),
+        );
+        if let Some(ft_contract_id) = currency.get_ft_contract_id() {
+            self.unlisted_offer_currencies.insert(
+                &(token_key.clone(), offer.offerer_id.clone()),
+                &ft_contract_id,
+            );
+        }
+        offers.push(offer);
+        self.unlisted_offers.insert(&token_key, &offers);
+        Ok(())
+    }
+
+    /// Removes the offer of `offerer_id` from the unlisted offers on a token,
+    /// along with its currency, and releases the FTs locked in it.
+    fn take_unlisted_offer(
+        &mut self,
+        token_key: &String,
+        offerer_id: &AccountId,
+    ) -> Option<(UnlistedOffer, Currency)> {
+        let mut offers = self.unlisted_offers.get(token_key)?;
+        let index = offers
+            .iter()
+            .position(|offer| &offer.offerer_id == offerer_id)?;
+        let offer = offers.remove(index);
+        self.store_unlisted_offers(token_key, offers);
+        let currency = self.take_unlisted_offer_currency(token_key, offerer_id);
+        self.unlock_ft(&currency, offer.amount);
+        Some((offer, currency))
+    }
+
+    /// Currency of the unlisted offer of `offerer_id` on a token.
+    fn unlisted_offer_currency(
+        &self,
+        token_key: &str,
+        offerer_id: &AccountId,
+    ) -> Currency {
+        self.unlisted_offer_currencies
+            .get(&(token_key.to_string(), offerer_id.clone()))
+            .into()
+    }
+
+    /// Removes the currency of the unlisted offer of `offerer_id` on a token.
+    fn take_unlisted_offer_currency(
+        &mut self,
+        token_key: &str,
+        offerer_id: &AccountId,
+    ) -> Currency {
+        self.unlisted_offer_currencies
+            .remove(&(token_key.to_string(), offerer_id.clone()))
+            .into()
+    }
+
+    /// Stores the unlisted offers for a token, removing the entry if there
+    /// are none left.
+    fn store_unlisted_offers(
+        &mut self,
+        token_key: &String,
+        offers: Vec<UnlistedOffer>,
+    ) {
+        if offers.is_empty() {
+            self.unlisted_offers.remove(token_key);
+        } else {
+            self.unlisted_offers.insert(token_key, &offers);
+        }
+    }
+
+    /// Refunds the escrowed amount and the storage deposit of an unlisted
+    /// offer that has been removed without a sale. The storage deposit is
+    /// always refunded in NEAR.
+    fn refund_unlisted_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        offer: UnlistedOffer,
+        currency: &Currency,
+        reason: &str,
+    ) {
+        env::log_str(
+            &events::NftRemoveUnlistedOfferData {
+                nft_contract_id,
+                nft_token_id: token_id,
+                offerer_id: offer.offerer_id.clone(),
+                reason: reason.to_string(),
+            }
+            .serialize_event(),
+        );
+        match currency.get_ft_contract_id() {
+            None => {
+                Promise::new(offer.offerer_id)
+                    .transfer(offer.amount + UNLISTED_OFFER_STORAGE_DEPOSIT);
+            }
+            Some(ft_contract_id) => {
+                Promise::new(offer.offerer_id.clone())
+                    .transfer(UNLISTED_OFFER_STORAGE_DEPOSIT);
+                self.ft_payout(&ft_contract_id, offer.offerer_id, offer.amount);
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@ use std::str::FromStr;
 use mb_sdk::{
     constants::{
         StorageCostsMarket,
+        MAX_LEN_ACCOUNTS_PAGE,
         YOCTO_PER_BYTE,
     },
     data::market_v1::{
@@ -10,9 +11,12 @@ use mb_sdk::{
         TokenListingJson,
         TokenOfferJson,
     },
-    events::market_v1::{
-        UpdateAllowlistData,
-        UpdateBanlistData,
+    events::{
+        market_v1::{
+            UpdateAllowlistData,
+            UpdateBanlistData,
+        },
+        market_v2::MarketOwnershipTransferredData,
     },
     near_assert,
     near_panic,
@@ -36,6 +40,7 @@ use mb_sdk::{
     },
     utils::{
         ntoy,
+        ContractVersionJson,
         SafeFraction,
         TokenKey,
     },
@@ -71,6 +76,11 @@ pub struct Marketplace {
     pub storage_costs: StorageCostsMarket,
 }
 
+/// Storage key of the minimum increment in basis points by which a new `Offer`
+/// must exceed the currently active `Offer` on a token. It is stored outside
+/// of `Marketplace`, as the state layout cannot be migrated.
+const MIN_OFFER_INCREMENT_BPS_KEY: &[u8] = b"min_offer_increment_bps";
+
 #[near_bindgen]
 impl Marketplace {
     /// Create a new `Marketplace`. Validate that owner must is a valid
@@ -106,6 +116,7 @@ impl Marketplace {
     #[payable]
     pub fn set_owner(&mut self, new_owner: AccountId) {
         self.assert_owner_marketplace();
+        log_ownership_transferred(&self.owner_id, &new_owner);
         self.owner_id = new_owner;
     }
 
@@ -127,6 +138,24 @@ impl Marketplace {
         self.min_offer_hours = min_offer_hours;
     }
 
+    /// Set the minimum increment (in basis points) by which an `Offer` must
+    /// exceed the current `Offer` to replace it.
+    #[payable]
+    pub fn set_min_offer_increment_bps(
+        &mut self,
+        min_offer_increment_bps: u32,
+    ) {
+        self.assert_owner_marketplace();
+        near_assert!(
+            min_offer_increment_bps <= 10_000,
+            "Cannot set minimum offer increment above 100%"
+        );
+        env::storage_write(
+            MIN_OFFER_INCREMENT_BPS_KEY,
+            &min_offer_increment_bps.to_le_bytes(),
+        );
+    }
+
     /// Owner of this `Marketplace` may call to remove Near deposited from
     /// contract storage cost, and Market royalty fees.
     #[payable]
@@ -265,14 +294,69 @@ impl Marketplace {
         self.min_offer_hours
     }
 
+    /// Get `Marketplace` minimum increment (in basis points) by which an
+    /// `Offer` must exceed the current `Offer`.
+    pub fn get_min_offer_increment_bps(&self) -> u32 {
+        min_offer_increment_bps()
+    }
+
+    /// Deprecated: This might exceed gas limits for large banlists, use
+    /// `get_banlist_paginated` or `is_banned` instead.
     pub fn get_banlist(&self) -> Vec<AccountId> {
         self.banlist.iter().collect()
     }
 
+    /// Get a page of the accounts banned from the `Marketplace`. At most
+    /// `MAX_LEN_ACCOUNTS_PAGE` (100) accounts are returned per call.
+    pub fn get_banlist_paginated(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        self.banlist
+            .as_vector()
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(accounts_page_len(limit))
+            .collect()
+    }
+
+    /// Check if an account is banned from the `Marketplace`.
+    pub fn is_banned(&self, account_id: AccountId) -> bool {
+        self.banlist.contains(&account_id)
+    }
+
+    /// Deprecated: This might exceed gas limits for large allowlists, use
+    /// `get_allowlist_paginated` or `is_allowlisted` instead.
     pub fn get_allowlist(&self) -> Vec<AccountId> {
         self.allowlist.iter().collect()
     }
 
+    /// Get a page of the accounts allowed to list on the `Marketplace`. At
+    /// most `MAX_LEN_ACCOUNTS_PAGE` (100) accounts are returned per call.
+    pub fn get_allowlist_paginated(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        self.allowlist
+            .as_vector()
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(accounts_page_len(limit))
+            .collect()
+    }
+
+    /// Check if an account is on the `Marketplace` allowlist.
+    pub fn is_allowlisted(&self, account_id: AccountId) -> bool {
+        self.allowlist.contains(&account_id)
+    }
+
+    /// Show name, version, and commit hash of the deployed contract binary
+    pub fn get_version(&self) -> ContractVersionJson {
+        mb_sdk::contract_version!()
+    }
+
     /// Get the Token with `TokenKey`.
     pub(crate) fn get_token_internal(&self, token_key: String) -> TokenListing {
         let key: TokenKey = token_key.as_str().into();
@@ -319,6 +403,14 @@ pub(crate) fn log_banlist_update(account_id: &AccountId, state: bool) {
     env::log_str(&data.serialize_event());
 }
 
+fn log_ownership_transferred(old_owner: &AccountId, new_owner: &AccountId) {
+    let data = MarketOwnershipTransferredData {
+        old_owner: old_owner.clone(),
+        new_owner: new_owner.clone(),
+    };
+    env::log_str(&data.serialize_event());
+}
+
 fn log_allowlist_update(account_id: &AccountId, state: bool) {
     let data = UpdateAllowlistData {
         account_id: account_id.to_string(),
@@ -326,3 +418,17 @@ fn log_allowlist_update(account_id: &AccountId, state: bool) {
     };
     env::log_str(&data.serialize_event());
 }
+
+/// Reads the minimum offer increment, which defaults to 0 if it has never
+/// been set.
+pub(crate) fn min_offer_increment_bps() -> u32 {
+    env::storage_read(MIN_OFFER_INCREMENT_BPS_KEY)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .unwrap_or(0)
+}
+
+fn accounts_page_len(limit: Option<u64>) -> usize {
+    limit
+        .unwrap_or(MAX_LEN_ACCOUNTS_PAGE)
+        .min(MAX_LEN_ACCOUNTS_PAGE) as usize
+}
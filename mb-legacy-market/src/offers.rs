@@ -37,10 +37,14 @@ use mb_sdk::{
         Balance,
         Promise,
     },
-    utils::TokenKey,
+    utils::{
+        SafeFraction,
+        TokenKey,
+    },
 };
 
 use crate::{
+    min_offer_increment_bps,
     Marketplace,
     MarketplaceExt,
 };
@@ -292,7 +296,13 @@ impl Marketplace {
                 token.current_offer = Some(offer);
             }
             Some(old_offer) => {
-                if !old_offer.is_active() || offer.price > old_offer.price {
+                let min_price = old_offer.price
+                    + SafeFraction::new(min_offer_increment_bps())
+                        .multiply_balance(old_offer.price);
+                if !old_offer.is_active()
+                    || (offer.price > old_offer.price
+                        && offer.price >= min_price)
+                {
                     let old_offer = std::mem::replace(
                         &mut token.current_offer,
                         Some(offer),
@@ -304,6 +314,12 @@ impl Marketplace {
                     );
                     // refund the prior offerer
                     self.tx_send(old_offer.from, old_offer.price);
+                } else if offer.price > old_offer.price {
+                    near_panic!(
+                        "The offer must be at least {} to exceed the current offer price of {} by the minimum increment",
+                        min_price,
+                        old_offer.price
+                    );
                 } else {
                     near_panic!(
                         "The offer must exceed the current offer price of {}",
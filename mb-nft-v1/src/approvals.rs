@@ -5,6 +5,7 @@ use mb_sdk::{
     constants::{
         gas,
         MAX_APPROVALS_PER_TOKEN,
+        MAX_LEN_APPROVAL_IDS_QUERY,
     },
     data::store::Token,
     events::store::{
@@ -131,8 +132,9 @@ impl MintbaseStore {
     // -------------------------- change methods ---------------------------
     /// Like `nft_approve`, but it allows approving multiple tokens in one call.
     /// The `msg` argument will be forwarded towards a `nft_on_batch_approve`.
-    /// As this is not standardized and only supported by the legacy Mintbase
-    /// market.
+    /// This is not standardized and only supported by Mintbase markets. Any
+    /// deposit exceeding the storage costs for the approvals is forwarded,
+    /// which the interop market uses to fund storage for the listings.
     #[payable]
     pub fn nft_batch_approve(
         &mut self,
@@ -172,6 +174,23 @@ impl MintbaseStore {
         }
     }
 
+    /// Like `nft_approve` without `msg`, but returns the assigned
+    /// `approval_id` instead of requiring callers to parse it from the event.
+    /// Useful when approving and calling the approved account separately.
+    #[payable]
+    pub fn nft_approve_and_return(
+        &mut self,
+        token_id: U64,
+        account_id: AccountId,
+    ) -> u64 {
+        assert_storage_deposit!(self.storage_costs.common);
+        let token_idu64 = token_id.into();
+        // validates owner and loaned
+        let approval_id = self.approve_internal(token_idu64, &account_id);
+        log_approve(token_idu64, approval_id, &account_id);
+        approval_id
+    }
+
     // -------------------------- view methods -----------------------------
     /// Returns the most recent `approval_id` for `account_id` on `token_id`.
     /// If the account doesn't have approval on the token, it will return
@@ -187,6 +206,45 @@ impl MintbaseStore {
         token.approvals.get(&account_id).cloned()
     }
 
+    /// Like `nft_approval_id`, but for up to 100 tokens at once. Returns the
+    /// approval IDs in the order of `token_ids`, with `None` for tokens that
+    /// the account has no approval for or that don't exist.
+    pub fn nft_approval_ids(
+        &self,
+        token_ids: Vec<U64>,
+        account_id: AccountId,
+    ) -> Vec<Option<u64>> {
+        near_assert!(
+            token_ids.len() <= MAX_LEN_APPROVAL_IDS_QUERY,
+            "Cannot query more than {} tokens at once",
+            MAX_LEN_APPROVAL_IDS_QUERY
+        );
+        token_ids
+            .into_iter()
+            .map(|token_id| {
+                self.tokens
+                    .get(&token_id.0)
+                    .and_then(|token| token.approvals.get(&account_id).cloned())
+            })
+            .collect()
+    }
+
+    /// Returns the number of accounts that are approved on `token_id`, which
+    /// is cheaper than querying the whole approvals map via `nft_token`.
+    ///
+    /// Panics if the token doesn't exist.
+    pub fn nft_approvals_count(&self, token_id: U64) -> u32 {
+        let token = self.nft_token_internal(token_id.into());
+        token.approvals.len() as u32
+    }
+
+    /// Returns the storage deposit in yoctoNEAR that needs to be attached to
+    /// `nft_approve` or `nft_batch_approve` for `num_approvals` approvals.
+    /// Listing a token on a market requires one approval.
+    pub fn estimate_approval_storage(&self, num_approvals: u64) -> U128 {
+        (num_approvals as u128 * self.storage_costs.common).into()
+    }
+
     // -------------------------- private methods --------------------------
     // -------------------------- internal methods -------------------------
 
@@ -15,6 +15,7 @@ use mb_sdk::{
         Owner,
         Token,
         TokenCompliant,
+        TokenStatus,
     },
     events::store::{
         NftTransferData,
@@ -200,12 +201,13 @@ impl MintbaseStore {
             }
             // restore split owners
             token.split_owners = split_owners;
-            if let Some(split_owners) = token.split_owners {
+            if let Some(split_owners) = token.split_owners.as_ref() {
                 crate::payout::log_set_split_owners(
                     vec![token.id.into()],
                     split_owners,
                 );
             }
+            self.tokens.insert(&token.id, &token);
             false
         }
     }
@@ -269,8 +271,55 @@ impl MintbaseStore {
         log_nft_batch_transfer(&tokens, &accounts, old_owners);
     }
 
+    /// Like `nft_batch_transfer`, but sends all tokens to the same receiver.
+    /// Emits a single transfer log for all tokens.
+    #[payable]
+    pub fn nft_send_all(
+        &mut self,
+        token_ids: Vec<U64>,
+        receiver_id: AccountId,
+    ) {
+        assert_one_yocto();
+        near_assert!(!token_ids.is_empty(), "Token IDs cannot be empty");
+        let pred = env::predecessor_account_id();
+        near_assert!(
+            receiver_id != pred,
+            "Tokens are already owned by {}",
+            receiver_id
+        ); // can't transfer to self
+        let mut set_owned =
+            self.tokens_per_owner.get(&pred).expect("none owned");
+        for token_id in token_ids.iter() {
+            let mut token = self.nft_token_internal(token_id.0);
+            assert_token_unloaned!(token);
+            assert_token_owned_by!(token, &pred);
+            self.transfer_internal(&mut token, receiver_id.clone(), false);
+            set_owned.remove(&token_id.0);
+        }
+        self.tokens_per_owner.insert(&pred, &set_owned);
+        log_nft_send_all(
+            token_ids.iter().map(|id| id.0.to_string()).collect(),
+            &receiver_id,
+            pred.to_string(),
+        );
+    }
+
     // -------------------------- view methods -----------------------------
 
+    /// Whether a token exists, has been burned, or has never been minted.
+    /// Unlike `nft_token`, this allows telling apart burned tokens. Token IDs
+    /// are assigned sequentially, so any missing ID below `tokens_minted` has
+    /// been burned.
+    pub fn nft_token_status(&self, token_id: U64) -> TokenStatus {
+        if self.tokens.contains_key(&token_id.0) {
+            TokenStatus::Exists
+        } else if token_id.0 < self.tokens_minted {
+            TokenStatus::Burned
+        } else {
+            TokenStatus::Never
+        }
+    }
+
     // -------------------------- private methods --------------------------
 
     // -------------------------- internal methods -------------------------
@@ -393,6 +442,22 @@ fn log_nft_transfer(
     env::log_str(data.serialize_event().as_str());
 }
 
+fn log_nft_send_all(
+    token_ids: Vec<String>,
+    receiver_id: &AccountId,
+    old_owner_id: String,
+) {
+    let data = NftTransferData(vec![NftTransferLog {
+        authorized_id: None,
+        old_owner_id,
+        new_owner_id: receiver_id.to_string(),
+        token_ids,
+        memo: None,
+    }]);
+
+    env::log_str(data.serialize_event().as_str());
+}
+
 fn log_nft_batch_transfer(
     tokens: &[U64],
     accounts: &[AccountId],
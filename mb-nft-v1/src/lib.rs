@@ -34,6 +34,7 @@ use mb_sdk::{
         AccountId,
         StorageUsage,
     },
+    utils::ContractVersionJson,
 };
 
 /// Implementing approval management as [described in the Nomicon](https://nomicon.io/Standards/NonFungibleToken/ApprovalManagement).
@@ -107,6 +108,12 @@ pub struct MintbaseStore {
     ///
     /// If false, disallow users to call `nft_move`.
     pub allow_moves: bool,
+    /// Receives `MINTING_FEE` instead of the parent account if set. Required
+    /// for stores deployed to top-level accounts to pay a minting fee.
+    pub fee_recipient: Option<AccountId>,
+    /// Account that has been proposed as new owner, together with the
+    /// `keep_old_minters` setting to apply once the proposal is accepted.
+    pub pending_owner: Option<(AccountId, bool)>,
 }
 
 impl Default for MintbaseStore {
@@ -139,6 +146,8 @@ impl MintbaseStore {
             owner_id,
             storage_costs: StorageCosts::new(YOCTO_PER_BYTE), // 10^19
             allow_moves: true,
+            fee_recipient: None,
+            pending_owner: None,
         }
     }
 
@@ -179,17 +188,50 @@ impl MintbaseStore {
         self.num_approved
     }
 
+    /// Show name, version, and commit hash of the deployed contract binary
+    pub fn get_version(&self) -> ContractVersionJson {
+        mb_sdk::contract_version!()
+    }
+
     // -------------------------- private methods --------------------------
 
     /// Contract metadata and methods in the API may be updated. All other
     /// elements of the state should be copied over. This method may only be
     /// called by the holder of the Store public key, in this case the
     /// Factory.
+    ///
+    /// Stores that still have the state layout of `MintbaseStoreV3` are
+    /// migrated to the current layout, with all newer settings disabled.
     #[private]
     #[init(ignore_state)]
     pub fn migrate_metadata(metadata: NFTContractMetadata) -> Self {
-        let old = env::state_read().expect("ohno ohno state");
-        Self { metadata, ..old }
+        let state = env::storage_read(b"STATE").expect("ohno ohno state");
+        if let Ok(current) = Self::try_from_slice(&state) {
+            return Self {
+                metadata,
+                ..current
+            };
+        }
+
+        let old = MintbaseStoreV3::try_from_slice(&state)
+            .unwrap_or_else(|_| env::panic_str("Cannot deserialize state"));
+        Self {
+            minters: old.minters,
+            metadata,
+            token_metadata: old.token_metadata,
+            token_royalty: old.token_royalty,
+            tokens: old.tokens,
+            tokens_per_owner: old.tokens_per_owner,
+            composables: old.composables,
+            tokens_minted: old.tokens_minted,
+            tokens_burned: old.tokens_burned,
+            num_approved: old.num_approved,
+            owner_id: old.owner_id,
+            storage_costs: old.storage_costs,
+            allow_moves: old.allow_moves,
+            fee_recipient: None,
+            pending_owner: None,
+        }
     }
 
     // Required because storage has been botched at some point with different
@@ -221,6 +263,8 @@ impl MintbaseStore {
             owner_id: old_state.owner_id,
             storage_costs,
             allow_moves: old_state.allow_moves,
+            fee_recipient: None,
+            pending_owner: None,
         }
     }
 
@@ -346,3 +390,23 @@ struct StorageCostsV2 {
     #[allow(dead_code)]
     pub balance: u128,
 }
+
+// State layout before fee recipients and ownership proposals were added,
+// required to migrate stores that have been deployed with it
+#[derive(BorshDeserialize)]
+struct MintbaseStoreV3 {
+    pub minters: UnorderedSet<AccountId>,
+    #[allow(dead_code)]
+    pub metadata: NFTContractMetadata,
+    pub token_metadata: LookupMap<u64, (u16, TokenMetadata)>,
+    pub token_royalty: LookupMap<u64, (u16, Royalty)>,
+    pub tokens: LookupMap<u64, Token>,
+    pub tokens_per_owner: LookupMap<AccountId, UnorderedSet<u64>>,
+    pub composables: LookupMap<String, UnorderedSet<String>>,
+    pub tokens_minted: u64,
+    pub tokens_burned: u64,
+    pub num_approved: u64,
+    pub owner_id: AccountId,
+    pub storage_costs: StorageCosts,
+    pub allow_moves: bool,
+}
@@ -1,7 +1,6 @@
-use std::convert::TryInto;
-
 use mb_sdk::{
     constants::{
+        MAX_LEN_MINTERS_PAGE,
         MAX_LEN_PAYOUT,
         MINIMUM_FREE_STORAGE_STAKE,
         MINTING_FEE,
@@ -31,6 +30,7 @@ use mb_sdk::{
         Promise,
         PromiseOrValue,
     },
+    utils::parent_account_id,
 };
 
 use crate::*;
@@ -56,12 +56,183 @@ impl MintbaseStore {
     pub fn nft_batch_mint(
         &mut self,
         owner_id: AccountId,
-        #[allow(unused_mut)] // cargo complains, but it's required
-        mut metadata: TokenMetadata,
+        metadata: TokenMetadata,
         num_to_mint: u64,
         royalty_args: Option<RoyaltyArgs>,
         split_owners: Option<SplitBetweenUnparsed>,
     ) -> PromiseOrValue<()> {
+        self.batch_mint_internal(
+            vec![(owner_id, num_to_mint)],
+            metadata,
+            royalty_args,
+            split_owners,
+        )
+    }
+
+    /// Like `nft_batch_mint`, but mints to multiple owners, e.g. for
+    /// airdrops. Each entry of `owner_token_pairs` specifies an owner and the
+    /// number of tokens to mint for them. All tokens share the same metadata
+    /// and royalties, and the same restrictions as for `nft_batch_mint` apply
+    /// to the total number of minted tokens. One `nft_mint` event is emitted
+    /// per owner.
+    #[payable]
+    pub fn nft_batch_mint_to(
+        &mut self,
+        owner_token_pairs: Vec<(AccountId, u64)>,
+        metadata: TokenMetadata,
+        royalty_args: Option<RoyaltyArgs>,
+        split_owners: Option<SplitBetweenUnparsed>,
+    ) -> PromiseOrValue<()> {
+        self.batch_mint_internal(
+            owner_token_pairs,
+            metadata,
+            royalty_args,
+            split_owners,
+        )
+    }
+
+    /// Adapter for tooling that expects the `nft_mint` signature of
+    /// `near-contract-standards`, minting a single token to `receiver_id`
+    /// without royalties or splits. Token IDs on this contract are assigned
+    /// sequentially, so `token_id` must be the next token ID as returned by
+    /// `get_tokens_minted`. Otherwise, the same restrictions as for
+    /// `nft_batch_mint` apply.
+    #[payable]
+    pub fn nft_mint(
+        &mut self,
+        token_id: String,
+        receiver_id: AccountId,
+        token_metadata: TokenMetadata,
+    ) -> PromiseOrValue<()> {
+        near_assert!(
+            token_id == self.tokens_minted.to_string(),
+            "Token IDs are assigned sequentially, the next token ID is {}",
+            self.tokens_minted
+        );
+        self.batch_mint_internal(
+            vec![(receiver_id, 1)],
+            token_metadata,
+            None,
+            None,
+        )
+    }
+
+    /// Tries to remove an acount ID from the minters list, will only fail
+    /// if the owner should be removed from the minters list.
+    fn revoke_minter_internal(&mut self, account_id: &AccountId) {
+        near_assert!(
+            *account_id != self.owner_id,
+            "Owner cannot be removed from minters"
+        );
+        // does nothing if account_id wasn't a minter
+        if self.minters.remove(account_id) {
+            log_revoke_minter(account_id);
+            // } else {
+            //     near_panic!("{} was not a minter", account_id)
+        }
+    }
+
+    /// Allows batched granting and revoking of minting rights in a single
+    /// transaction. Subject to the same restrictions as `grant_minter`
+    /// and `revoke_minter`.
+    ///
+    /// Should you include an account in both lists, it will end up becoming
+    /// approved and immediately revoked in the same step.
+    #[payable]
+    pub fn batch_change_minters(
+        &mut self,
+        grant: Option<Vec<AccountId>>,
+        revoke: Option<Vec<AccountId>>,
+    ) {
+        self.assert_store_owner();
+        near_assert!(
+            grant.is_some() || revoke.is_some(),
+            "You need to either grant or revoke at least one account"
+        );
+
+        if let Some(grant_ids) = grant {
+            for account_id in grant_ids {
+                // does nothing if account_id is already a minter
+                if self.minters.insert(&account_id) {
+                    log_grant_minter(&account_id);
+                }
+            }
+        }
+
+        if let Some(revoke_ids) = revoke {
+            for account_id in revoke_ids {
+                self.revoke_minter_internal(&account_id)
+            }
+        }
+    }
+
+    /// The calling account will try to withdraw as minter from this NFT smart
+    /// contract. If the calling account is not a minter on the NFT smart
+    /// contract, this will still succeed but have no effect.
+    #[payable]
+    pub fn withdraw_minter(&mut self) {
+        assert_one_yocto();
+        self.revoke_minter_internal(&env::predecessor_account_id())
+    }
+
+    // -------------------------- view methods -----------------------------
+
+    /// Check if `account_id` is a minter.
+    pub fn check_is_minter(&self, account_id: AccountId) -> bool {
+        self.minters.contains(&account_id)
+    }
+
+    /// Lists all account IDs that are currently allowed to mint on this
+    /// contract.
+    ///
+    /// Deprecated: This might exceed gas limits for large sets of minters, use
+    /// `list_minters_paginated` instead.
+    pub fn list_minters(&self) -> Vec<AccountId> {
+        self.minters.iter().collect()
+    }
+
+    /// Lists a page of account IDs that are currently allowed to mint on this
+    /// contract. At most `MAX_LEN_MINTERS_PAGE` (100) accounts are returned
+    /// per call.
+    pub fn list_minters_paginated(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        let limit = limit
+            .unwrap_or(MAX_LEN_MINTERS_PAGE)
+            .min(MAX_LEN_MINTERS_PAGE);
+        self.minters
+            .as_vector()
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Number of account IDs that are currently allowed to mint on this
+    /// contract.
+    pub fn minters_count(&self) -> u64 {
+        self.minters.len()
+    }
+
+    // -------------------------- private methods --------------------------
+    // -------------------------- internal methods -------------------------
+
+    /// Mints tokens for each owner in `owner_token_pairs`, sharing the
+    /// metadata and royalty entries between all of them.
+    fn batch_mint_internal(
+        &mut self,
+        owner_token_pairs: Vec<(AccountId, u64)>,
+        mut metadata: TokenMetadata,
+        royalty_args: Option<RoyaltyArgs>,
+        split_owners: Option<SplitBetweenUnparsed>,
+    ) -> PromiseOrValue<()> {
+        near_assert!(
+            owner_token_pairs.iter().all(|(_, n)| *n > 0),
+            "No tokens to mint"
+        );
+        let num_to_mint: u64 = owner_token_pairs.iter().map(|(_, n)| n).sum();
         near_assert!(num_to_mint > 0, "No tokens to mint");
         near_assert!(
             num_to_mint <= 125,
@@ -112,8 +283,12 @@ impl MintbaseStore {
             "Number of payout addresses may not exceed {}",
             MAX_LEN_PAYOUT
         );
+        // every owner beyond the first might require a new `tokens_per_owner`
+        // entry
         let expected_storage_consumption: Balance =
-            self.storage_cost_to_mint(num_to_mint, md_size, roy_len, split_len);
+            self.storage_cost_to_mint(num_to_mint, md_size, roy_len, split_len)
+                + (owner_token_pairs.len() as u128 - 1)
+                    * self.storage_costs.common;
         near_assert!(
             covered_storage >= expected_storage_consumption,
             "This mint would exceed the current storage coverage of {} yoctoNEAR. Requires at least {} yoctoNEAR",
@@ -124,8 +299,6 @@ impl MintbaseStore {
         let checked_royalty = royalty_args.map(Royalty::new);
         let checked_split = split_owners.map(SplitOwners::new);
 
-        let mut owned_set = self.get_or_make_new_owner_set(&owner_id);
-
         // Lookup Id is used by the token to lookup Royalty and Metadata fields on
         // the contract (to avoid unnecessary duplication)
         let lookup_id: u64 = self.tokens_minted;
@@ -141,23 +314,30 @@ impl MintbaseStore {
             .insert(&lookup_id, &(num_to_mint as u16, metadata));
 
         // Mint em up hot n fresh with a side of vegan bacon
-        (0..num_to_mint).for_each(|i| {
-            let token_id = self.tokens_minted + i;
-            let token = Token::new(
+        let mut minted_ranges = Vec::with_capacity(owner_token_pairs.len());
+        for (owner_id, num_tokens) in owner_token_pairs {
+            let mut owned_set = self.get_or_make_new_owner_set(&owner_id);
+            (0..num_tokens).for_each(|i| {
+                let token_id = self.tokens_minted + i;
+                let token = Token::new(
+                    owner_id.clone(),
+                    token_id,
+                    lookup_id,
+                    royalty_id,
+                    checked_split.clone(),
+                    minter_id.clone(),
+                );
+                owned_set.insert(&token_id);
+                self.tokens.insert(&token_id, &token);
+            });
+            minted_ranges.push((
                 owner_id.clone(),
-                token_id,
-                lookup_id,
-                royalty_id,
-                checked_split.clone(),
-                minter_id.clone(),
-            );
-            owned_set.insert(&token_id);
-            self.tokens.insert(&token_id, &token);
-        });
-        self.tokens_minted += num_to_mint;
-        self.tokens_per_owner.insert(&owner_id, &owned_set);
-
-        let minted = self.tokens_minted;
+                self.tokens_minted,
+                self.tokens_minted + num_tokens - 1,
+            ));
+            self.tokens_minted += num_tokens;
+            self.tokens_per_owner.insert(&owner_id, &owned_set);
+        }
 
         // check if sufficient storage stake (e.g. 0.5 NEAR) remains
         let used_storage_stake: Balance =
@@ -171,103 +351,39 @@ impl MintbaseStore {
             free_storage_stake
         );
 
-        log_nft_batch_mint(
-            minted - num_to_mint,
-            minted - 1,
-            minter_id.as_ref(),
-            owner_id.as_ref(),
-            &checked_royalty,
-            &checked_split,
-            &meta_ref,
-            &meta_extra,
-        );
+        for (owner_id, first_token_id, last_token_id) in minted_ranges {
+            log_nft_batch_mint(
+                first_token_id,
+                last_token_id,
+                minter_id.as_ref(),
+                owner_id.as_ref(),
+                &checked_royalty,
+                &checked_split,
+                &meta_ref,
+                &meta_extra,
+            );
+        }
 
-        // Transfer minting fee if parent is a valid account (assuming this is
-        // a factory). If parent is not valid, e.g. this contract was deployed
-        // to a random top-level account, do nothing.
-        match parent_account_id(&env::current_account_id()) {
-            Some(factory) => {
-                let p = Promise::new(factory).transfer(MINTING_FEE);
+        // Transfer minting fee to the configured recipient, or the parent
+        // account (assuming this is a factory). If neither exists, e.g. this
+        // contract was deployed to a random top-level account, do nothing.
+        match self.minting_fee_recipient() {
+            Some(recipient) => {
+                let p = Promise::new(recipient).transfer(MINTING_FEE);
                 PromiseOrValue::Promise(p)
             }
             _ => PromiseOrValue::Value(()),
         }
     }
 
-    /// Tries to remove an acount ID from the minters list, will only fail
-    /// if the owner should be removed from the minters list.
-    fn revoke_minter_internal(&mut self, account_id: &AccountId) {
-        near_assert!(
-            *account_id != self.owner_id,
-            "Owner cannot be removed from minters"
-        );
-        // does nothing if account_id wasn't a minter
-        if self.minters.remove(account_id) {
-            log_revoke_minter(account_id);
-            // } else {
-            //     near_panic!("{} was not a minter", account_id)
-        }
-    }
-
-    /// Allows batched granting and revoking of minting rights in a single
-    /// transaction. Subject to the same restrictions as `grant_minter`
-    /// and `revoke_minter`.
-    ///
-    /// Should you include an account in both lists, it will end up becoming
-    /// approved and immediately revoked in the same step.
-    #[payable]
-    pub fn batch_change_minters(
-        &mut self,
-        grant: Option<Vec<AccountId>>,
-        revoke: Option<Vec<AccountId>>,
-    ) {
-        self.assert_store_owner();
-        near_assert!(
-            grant.is_some() || revoke.is_some(),
-            "You need to either grant or revoke at least one account"
-        );
-
-        if let Some(grant_ids) = grant {
-            for account_id in grant_ids {
-                // does nothing if account_id is already a minter
-                if self.minters.insert(&account_id) {
-                    log_grant_minter(&account_id);
-                }
-            }
-        }
-
-        if let Some(revoke_ids) = revoke {
-            for account_id in revoke_ids {
-                self.revoke_minter_internal(&account_id)
-            }
-        }
-    }
-
-    /// The calling account will try to withdraw as minter from this NFT smart
-    /// contract. If the calling account is not a minter on the NFT smart
-    /// contract, this will still succeed but have no effect.
-    #[payable]
-    pub fn withdraw_minter(&mut self) {
-        assert_one_yocto();
-        self.revoke_minter_internal(&env::predecessor_account_id())
-    }
-
-    // -------------------------- view methods -----------------------------
-
-    /// Check if `account_id` is a minter.
-    pub fn check_is_minter(&self, account_id: AccountId) -> bool {
-        self.minters.contains(&account_id)
+    /// The account that receives `MINTING_FEE`: the explicitly configured
+    /// `fee_recipient` if set, otherwise the parent account (factory).
+    pub(crate) fn minting_fee_recipient(&self) -> Option<AccountId> {
+        self.fee_recipient
+            .clone()
+            .or_else(|| parent_account_id(&env::current_account_id()))
     }
 
-    /// Lists all account IDs that are currently allowed to mint on this
-    /// contract.
-    pub fn list_minters(&self) -> Vec<AccountId> {
-        self.minters.iter().collect()
-    }
-
-    // -------------------------- private methods --------------------------
-    // -------------------------- internal methods -------------------------
-
     /// Get the storage in bytes to mint `num_tokens` each with
     /// `metadata_storage` and `len_map` royalty receivers.
     /// Internal
@@ -351,14 +467,3 @@ pub(crate) fn log_revoke_minter(account_id: &AccountId) {
         .serialize_event(),
     );
 }
-
-fn parent_account_id(child: &AccountId) -> Option<AccountId> {
-    child
-        .as_str()
-        .split_once('.')
-        .unwrap()
-        .1
-        .to_string()
-        .try_into()
-        .ok()
-}
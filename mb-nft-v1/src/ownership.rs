@@ -1,7 +1,12 @@
 use mb_sdk::{
     constants::StorageCostsJson,
-    events::store::MbStoreChangeSettingDataV010,
+    events::store::{
+        MbStoreChangeSettingDataV010,
+        OwnershipProposalCancelledData,
+        OwnershipProposedData,
+    },
     near_assert,
+    near_panic,
     near_sdk::{
         self,
         assert_one_yocto,
@@ -42,17 +47,63 @@ impl MintbaseStore {
             "{} already owns this store",
             new_owner
         );
-        if !keep_old_minters {
-            for minter in self.minters.iter() {
-                log_revoke_minter(&minter);
+        self.pending_owner = None;
+        self.transfer_store_ownership_internal(new_owner, keep_old_minters);
+    }
+
+    /// Propose a new owner for this `Store`. Ownership is only transferred
+    /// once the proposed account calls `accept_ownership`, which prevents
+    /// handing the store to an account that nobody controls. The
+    /// `keep_old_minters` setting is applied on acceptance, see
+    /// `transfer_store_ownership`.
+    ///
+    /// Only the store owner may call this function.
+    #[payable]
+    pub fn propose_owner(
+        &mut self,
+        new_owner: AccountId,
+        keep_old_minters: bool,
+    ) {
+        self.assert_store_owner();
+        near_assert!(
+            new_owner != self.owner_id,
+            "{} already owns this store",
+            new_owner
+        );
+        log_ownership_proposed(&self.owner_id, &new_owner);
+        self.pending_owner = Some((new_owner, keep_old_minters));
+    }
+
+    /// Accept a pending ownership proposal.
+    ///
+    /// Only the proposed owner may call this function.
+    #[payable]
+    pub fn accept_ownership(&mut self) {
+        assert_one_yocto();
+        let (new_owner, keep_old_minters) = match self.pending_owner.take() {
+            Some((pending_owner, keep_old_minters))
+                if pending_owner == env::predecessor_account_id() =>
+            {
+                (pending_owner, keep_old_minters)
             }
-            self.minters.clear();
-        }
-        log_grant_minter(&new_owner);
-        // add the new_owner to the minter set (insert does nothing if they already are a minter).
-        self.minters.insert(&new_owner);
-        log_transfer_store(&new_owner);
-        self.owner_id = new_owner;
+            _ => near_panic!(
+                "This method can only be called by the pending store owner"
+            ),
+        };
+        self.transfer_store_ownership_internal(new_owner, keep_old_minters);
+    }
+
+    /// Cancel a pending ownership proposal.
+    ///
+    /// Only the store owner may call this function.
+    #[payable]
+    pub fn cancel_ownership_proposal(&mut self) {
+        self.assert_store_owner();
+        let (pending_owner, _) = match self.pending_owner.take() {
+            None => near_panic!("There is no pending ownership proposal"),
+            Some(pending) => pending,
+        };
+        log_ownership_proposal_cancelled(&self.owner_id, &pending_owner);
     }
 
     /// Owner of this `Store` may call to withdraw Near deposited onto
@@ -86,7 +137,8 @@ impl MintbaseStore {
     #[payable]
     pub fn set_storage_price_per_byte(&mut self, new_price: U128) {
         self.assert_store_owner();
-        self.storage_costs = StorageCosts::new(new_price.into())
+        self.storage_costs = StorageCosts::new(new_price.into());
+        log_storage_price_per_byte(new_price);
     }
 
     /// Remove a key from the smart contract.
@@ -96,6 +148,7 @@ impl MintbaseStore {
     pub fn del_key(&mut self, key: String) -> Promise {
         self.assert_store_owner();
         let key: near_sdk::PublicKey = key.parse().expect("Cannot parse key");
+        log_deleted_access_key(&key);
         Promise::new(env::current_account_id()).delete_key(key)
     }
 
@@ -106,15 +159,50 @@ impl MintbaseStore {
     pub fn add_key(&mut self, key: String) -> Promise {
         self.assert_store_owner();
         let key: near_sdk::PublicKey = key.parse().expect("Cannot parse key");
+        log_added_access_key(&key);
         Promise::new(env::current_account_id()).add_full_access_key(key)
     }
 
+    /// Set the account that receives `MINTING_FEE`, overriding the parent
+    /// account. Only the current fee recipient may redirect the fee. If there
+    /// is none, e.g. for stores deployed to top-level accounts, the store
+    /// owner may set it.
+    #[payable]
+    pub fn set_fee_recipient(&mut self, fee_recipient: AccountId) {
+        assert_one_yocto();
+        let caller = env::predecessor_account_id();
+        match self.minting_fee_recipient() {
+            Some(current) => near_assert!(
+                caller == current,
+                "This method can only be called by the current fee recipient"
+            ),
+            None => near_assert!(
+                caller == self.owner_id,
+                "This method can only be called by the store owner"
+            ),
+        }
+        log_fee_recipient(&fee_recipient);
+        self.fee_recipient = Some(fee_recipient);
+    }
+
     // -------------------------- view methods -----------------------------
     /// Show the current owner of this NFT contract
     pub fn get_owner_id(&self) -> AccountId {
         self.owner_id.clone()
     }
 
+    /// Show the account that has been proposed as new owner, if any
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner
+            .as_ref()
+            .map(|(account_id, _)| account_id.clone())
+    }
+
+    /// Show the account that receives the minting fee, if any
+    pub fn get_fee_recipient(&self) -> Option<AccountId> {
+        self.minting_fee_recipient()
+    }
+
     /// Show the current owner of this NFT contract
     pub fn get_storage_costs(&self) -> StorageCostsJson {
         (&self.storage_costs).into()
@@ -123,6 +211,26 @@ impl MintbaseStore {
     // -------------------------- private methods --------------------------
     // -------------------------- internal methods -------------------------
 
+    /// Hand the store over to `new_owner`, optionally revoking all existing
+    /// minters.
+    fn transfer_store_ownership_internal(
+        &mut self,
+        new_owner: AccountId,
+        keep_old_minters: bool,
+    ) {
+        if !keep_old_minters {
+            for minter in self.minters.iter() {
+                log_revoke_minter(&minter);
+            }
+            self.minters.clear();
+        }
+        log_grant_minter(&new_owner);
+        // add the new_owner to the minter set (insert does nothing if they already are a minter).
+        self.minters.insert(&new_owner);
+        log_transfer_store(&new_owner);
+        self.owner_id = new_owner;
+    }
+
     /// Validate the caller of this method matches the owner of this `Store`.
     pub(crate) fn assert_store_owner(&self) {
         assert_one_yocto();
@@ -142,3 +250,66 @@ fn log_transfer_store(account_id: &AccountId) {
         .serialize_event(),
     );
 }
+
+fn log_storage_price_per_byte(price: U128) {
+    env::log_str(
+        &MbStoreChangeSettingDataV010 {
+            new_storage_price_per_byte: Some(price),
+            ..MbStoreChangeSettingDataV010::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_added_access_key(key: &near_sdk::PublicKey) {
+    env::log_str(
+        &MbStoreChangeSettingDataV010 {
+            added_access_key: Some(key.into()),
+            ..MbStoreChangeSettingDataV010::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_deleted_access_key(key: &near_sdk::PublicKey) {
+    env::log_str(
+        &MbStoreChangeSettingDataV010 {
+            deleted_access_key: Some(key.into()),
+            ..MbStoreChangeSettingDataV010::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_fee_recipient(account_id: &AccountId) {
+    env::log_str(
+        &MbStoreChangeSettingDataV010 {
+            new_fee_recipient: Some(account_id.to_string()),
+            ..MbStoreChangeSettingDataV010::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_ownership_proposed(owner_id: &AccountId, pending_owner_id: &AccountId) {
+    env::log_str(
+        &OwnershipProposedData {
+            owner_id: owner_id.clone(),
+            pending_owner_id: pending_owner_id.clone(),
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_ownership_proposal_cancelled(
+    owner_id: &AccountId,
+    pending_owner_id: &AccountId,
+) {
+    env::log_str(
+        &OwnershipProposalCancelledData {
+            owner_id: owner_id.clone(),
+            pending_owner_id: pending_owner_id.clone(),
+        }
+        .serialize_event(),
+    );
+}
@@ -98,10 +98,6 @@ impl MintbaseStore {
         split_between: SplitBetweenUnparsed,
     ) {
         near_assert!(!token_ids.is_empty(), "Requires token IDs");
-        // near_assert!(
-        //     split_between.len() >= 2,
-        //     "Requires at least two accounts to split between"
-        // );
         assert_storage_deposit!(
             (self.storage_costs.common * split_between.len() as u128)
                 * token_ids.len() as u128
@@ -139,7 +135,7 @@ impl MintbaseStore {
             token.split_owners = Some(splits.clone());
             self.tokens.insert(&token_id.into(), &token);
         });
-        log_set_split_owners(token_ids, splits);
+        log_set_split_owners(token_ids, &splits);
     }
 
     // -------------------------- view methods -----------------------------
@@ -256,16 +252,12 @@ impl OwnershipFractions {
 
 pub(crate) fn log_set_split_owners(
     token_ids: Vec<U64>,
-    mut split_owners: SplitOwners,
+    split_owners: &SplitOwners,
 ) {
     env::log_str(
         &mb_sdk::events::store::NftSetSplitOwnerData {
             token_ids: token_ids.into_iter().map(|x| x.0.to_string()).collect(),
-            split_owners: split_owners
-                .split_between
-                .drain()
-                .map(|(acc, fraction)| (acc, fraction.numerator as u16))
-                .collect(),
+            split_owners: split_owners.to_unparsed(),
         }
         .serialize_event(),
     );
@@ -5,6 +5,7 @@ use mb_sdk::{
     constants::{
         gas,
         MAX_APPROVALS_PER_TOKEN,
+        MAX_LEN_APPROVAL_IDS_QUERY,
     },
     data::store::Token,
     events::store::{
@@ -21,6 +22,7 @@ use mb_sdk::{
         json_types::U64,
         near_bindgen,
         AccountId,
+        Balance,
         Promise,
         PromiseOrValue,
     },
@@ -34,19 +36,35 @@ impl MintbaseStore {
     // -------------------------- change methods ---------------------------
     /// Granting NFT transfer approval as specified by
     /// [NEP-178](https://nomicon.io/Standards/Tokens/NonFungibleToken/ApprovalManagement)
+    ///
+    /// If `expires_at` (nanoseconds) is given, the approval can no longer be
+    /// used for transfers after that point in time. Storing the expiry
+    /// requires the storage deposit of another approval, which is refunded
+    /// along with that of the approval itself when it is revoked or pruned.
     #[payable]
     pub fn nft_approve(
         &mut self,
         token_id: String,
         account_id: AccountId,
         msg: Option<String>,
+        expires_at: Option<U64>,
     ) -> Option<Promise> {
         // Note: This method only guarantees that the store-storage is covered.
         // The market may still reject.
-        assert_storage_deposit!(self.storage_costs.common);
+        let num_entries = if expires_at.is_some() { 2 } else { 1 };
+        assert_storage_deposit!(self.storage_costs.common * num_entries);
+        if let Some(expires_at) = expires_at {
+            near_assert!(
+                expires_at.0 > env::block_timestamp(),
+                "Approval expiry must be in the future"
+            );
+        }
         let token_id_tuple = parse_token_id(&token_id);
         // validates owner and loaned
         let approval_id = self.approve_internal(token_id_tuple, &account_id);
+        if let Some(expires_at) = expires_at {
+            self.approval_expiries.insert(&approval_id, &expires_at.0);
+        }
         log_approve(token_id_tuple, approval_id, &account_id);
 
         if let Some(msg) = msg {
@@ -78,12 +96,13 @@ impl MintbaseStore {
         assert_token_owned_by_predecessor!(token);
         assert_one_yocto();
 
-        if token.approvals.remove(&account_id).is_some() {
+        if let Some(approval_id) = token.approvals.remove(&account_id) {
+            let refund = self.storage_costs.common
+                + self.remove_approval_expiries([&approval_id]);
             self.save_token(&token);
             log_revoke(token_id_tuple, &account_id);
             PromiseOrValue::Promise(
-                Promise::new(env::predecessor_account_id())
-                    .transfer(self.storage_costs.common),
+                Promise::new(env::predecessor_account_id()).transfer(refund),
             )
         } else {
             PromiseOrValue::Value(())
@@ -100,7 +119,8 @@ impl MintbaseStore {
         assert_token_owned_by_predecessor!(token);
         assert_one_yocto();
 
-        let refund = token.approvals.len() as u128 * self.storage_costs.common;
+        let refund = token.approvals.len() as u128 * self.storage_costs.common
+            + self.remove_approval_expiries(token.approvals.values());
 
         if !token.approvals.is_empty() {
             token.approvals.clear();
@@ -132,8 +152,9 @@ impl MintbaseStore {
     // -------------------------- change methods ---------------------------
     /// Like `nft_approve`, but it allows approving multiple tokens in one call.
     /// The `msg` argument will be forwarded towards a `nft_on_batch_approve`.
-    /// As this is not standardized and only supported by the legacy Mintbase
-    /// market.
+    /// This is not standardized and only supported by Mintbase markets. Any
+    /// deposit exceeding the storage costs for the approvals is forwarded,
+    /// which the interop market uses to fund storage for the listings.
     #[payable]
     pub fn nft_batch_approve(
         &mut self,
@@ -174,6 +195,71 @@ impl MintbaseStore {
         }
     }
 
+    /// Like `nft_approve` without `msg`, but returns the assigned
+    /// `approval_id` instead of requiring callers to parse it from the event.
+    /// Useful when approving and calling the approved account separately.
+    #[payable]
+    pub fn nft_approve_and_return(
+        &mut self,
+        token_id: String,
+        account_id: AccountId,
+    ) -> u64 {
+        assert_storage_deposit!(self.storage_costs.common);
+        let token_id_tuple = parse_token_id(&token_id);
+        // validates owner and loaned
+        let approval_id = self.approve_internal(token_id_tuple, &account_id);
+        log_approve(token_id_tuple, approval_id, &account_id);
+        approval_id
+    }
+
+    /// Removes all expired approvals from the given tokens, which all need to
+    /// be owned by the caller. Approvals without expiry or that have not yet
+    /// expired are kept. The storage deposit of each removed approval and its
+    /// expiry is refunded to the caller.
+    #[payable]
+    pub fn prune_expired_approvals(
+        &mut self,
+        token_ids: Vec<String>,
+    ) -> PromiseOrValue<()> {
+        assert_one_yocto();
+        let mut pruned = 0;
+        let mut refund = 0;
+        for token_id in token_ids {
+            let token_id_tuple = parse_token_id(&token_id);
+            let mut token = self.nft_token_internal(token_id_tuple);
+            assert_token_unloaned!(token);
+            assert_token_owned_by_predecessor!(token);
+
+            let expired: Vec<(AccountId, u64)> = token
+                .approvals
+                .iter()
+                .filter(|(_, &approval_id)| self.approval_expired(approval_id))
+                .map(|(account_id, &approval_id)| {
+                    (account_id.clone(), approval_id)
+                })
+                .collect();
+            if expired.is_empty() {
+                continue;
+            }
+            for (account_id, approval_id) in expired {
+                token.approvals.remove(&account_id);
+                refund += self.storage_costs.common
+                    + self.remove_approval_expiries([&approval_id]);
+                log_revoke(token_id_tuple, &account_id);
+                pruned += 1;
+            }
+            self.save_token(&token);
+        }
+
+        if pruned > 0 {
+            PromiseOrValue::Promise(
+                Promise::new(env::predecessor_account_id()).transfer(refund),
+            )
+        } else {
+            PromiseOrValue::Value(())
+        }
+    }
+
     // -------------------------- view methods -----------------------------
     /// Returns the most recent `approval_id` for `account_id` on `token_id`.
     /// If the account doesn't have approval on the token, it will return
@@ -190,6 +276,49 @@ impl MintbaseStore {
         token.approvals.get(&account_id).cloned()
     }
 
+    /// Like `nft_approval_id`, but for up to 100 tokens at once. Returns the
+    /// approval IDs in the order of `token_ids`, with `None` for tokens that
+    /// the account has no approval for or that don't exist.
+    pub fn nft_approval_ids(
+        &self,
+        token_ids: Vec<String>,
+        account_id: AccountId,
+    ) -> Vec<Option<u64>> {
+        near_assert!(
+            token_ids.len() <= MAX_LEN_APPROVAL_IDS_QUERY,
+            "Cannot query more than {} tokens at once",
+            MAX_LEN_APPROVAL_IDS_QUERY
+        );
+        token_ids
+            .iter()
+            .map(|token_id| {
+                let (metadata_id, id) = parse_token_id(token_id);
+                self.tokens
+                    .get(&metadata_id)
+                    .and_then(|metadata_tokens| metadata_tokens.get(&id))
+                    .flatten()
+                    .and_then(|token| token.approvals.get(&account_id).cloned())
+            })
+            .collect()
+    }
+
+    /// Returns the number of accounts that are approved on `token_id`, which
+    /// is cheaper than querying the whole approvals map via `nft_token`.
+    ///
+    /// Panics if the token doesn't exist.
+    pub fn nft_approvals_count(&self, token_id: String) -> u32 {
+        let token = self.nft_token_internal(parse_token_id(&token_id));
+        token.approvals.len() as u32
+    }
+
+    /// Returns the storage deposit in yoctoNEAR that needs to be attached to
+    /// `nft_approve` or `nft_batch_approve` for `num_approvals` approvals.
+    /// Listing a token on a market requires one approval, and approvals with
+    /// an expiry count twice.
+    pub fn estimate_approval_storage(&self, num_approvals: u64) -> U128 {
+        (num_approvals as u128 * self.storage_costs.common).into()
+    }
+
     // -------------------------- private methods --------------------------
     // -------------------------- internal methods -------------------------
 
@@ -204,6 +333,11 @@ impl MintbaseStore {
         // token.assert_owned_by_predecessor();
         assert_token_unloaned!(token);
         assert_token_owned_by_predecessor!(token);
+        near_assert!(
+            !self.soulbound_metadata.contains(&token.metadata_id),
+            "Token {} is soulbound and cannot be approved",
+            token.fmt_id()
+        );
         near_assert!(
             token.approvals.len() as u64 <= MAX_APPROVALS_PER_TOKEN,
             "Cannot approve more than {} accounts for a token",
@@ -212,11 +346,24 @@ impl MintbaseStore {
 
         let approval_id = self.num_approved;
         self.num_approved += 1;
-        token.approvals.insert(account_id.clone(), approval_id);
+        if let Some(replaced_id) =
+            token.approvals.insert(account_id.clone(), approval_id)
+        {
+            self.remove_approval_expiries([&replaced_id]);
+        }
         self.save_token(&token);
         approval_id
     }
 
+    /// Whether the approval with `approval_id` has been given an expiry that
+    /// has passed.
+    pub(crate) fn approval_expired(&self, approval_id: u64) -> bool {
+        self.approval_expiries
+            .get(&approval_id)
+            .map(|expires_at| expires_at <= env::block_timestamp())
+            .unwrap_or(false)
+    }
+
     /// Same as `nft_is_approved`, but uses internal u64 (u64) typing for
     /// Copy-efficiency.
     pub(crate) fn nft_is_approved_internal(
@@ -232,7 +379,10 @@ impl MintbaseStore {
             let stored_approval = token.approvals.get(approved_account_id);
             match stored_approval {
                 None => false,
-                Some(&stored_approval_id) => stored_approval_id == approval_id,
+                Some(&stored_approval_id) => {
+                    stored_approval_id == approval_id
+                        && !self.approval_expired(approval_id)
+                }
             }
         }
     }
@@ -288,3 +438,23 @@ fn log_revoke_all(token_id: (u64, u64)) {
         .serialize_event(),
     );
 }
+
+// Kept outside of `#[near_bindgen]`, which cannot handle `impl Trait`
+// arguments.
+impl MintbaseStore {
+    /// Removes the expiries of the given approvals, which need to be called
+    /// whenever approvals are dropped. Returns the storage deposit that has
+    /// been paid for the removed expiries.
+    pub(crate) fn remove_approval_expiries<'a>(
+        &mut self,
+        approval_ids: impl IntoIterator<Item = &'a u64>,
+    ) -> Balance {
+        let removed = approval_ids
+            .into_iter()
+            .filter(|approval_id| {
+                self.approval_expiries.remove(approval_id).is_some()
+            })
+            .count();
+        removed as u128 * self.storage_costs.common
+    }
+}
@@ -1,12 +1,14 @@
 use mb_sdk::{
     assert_token_owned_by,
     assert_token_unloaned,
+    data::store::Token,
     events::store::NftBurnLog,
     near_sdk::{
         self,
         assert_one_yocto,
         env,
         near_bindgen,
+        AccountId,
     },
 };
 
@@ -36,25 +38,8 @@ impl MintbaseStore {
             assert_token_unloaned!(token);
             assert_token_owned_by!(token, &account_id);
 
-            // update the counts on token metadata and royalties stored
-            let metadata_id =
-                self.nft_token_internal(token_id_tuple).metadata_id;
-            let mut minting_metadata =
-                self.token_metadata.get(&metadata_id).unwrap();
-            let count = minting_metadata.minted - minting_metadata.burned;
-            if count > 1 {
-                minting_metadata.burned += 1;
-                self.token_metadata.insert(&metadata_id, &minting_metadata);
-            }
-
             set_owned.remove(&token_id_tuple);
-            let (metadata_id, token_id) = token.id_tuple();
-            let mut metadata_tokens = self
-                .tokens
-                .get(&metadata_id)
-                .expect("This metadata does not yet exist in storage!");
-            metadata_tokens.insert(&token_id, &None);
-            self.tokens.insert(&metadata_id, &metadata_tokens);
+            self.burn_token_internal(&token);
         });
 
         if set_owned.is_empty() {
@@ -69,6 +54,60 @@ impl MintbaseStore {
     // -------------------------- view methods -----------------------------
     // -------------------------- private methods --------------------------
     // -------------------------- internal methods -------------------------
+
+    /// Burns a token that has been transferred to the burn sink account via
+    /// `nft_transfer`. Checks on the caller must happen prior to calling this.
+    pub(crate) fn burn_to_sink(
+        &mut self,
+        token: Token,
+        authorized_id: Option<String>,
+        memo: Option<String>,
+    ) {
+        let owner_id = AccountId::try_from(token.owner_id.to_string()).unwrap();
+        self.update_tokens_per_owner(
+            token.id_tuple(),
+            Some(owner_id.clone()),
+            None,
+        );
+        self.burn_token_internal(&token);
+        self.tokens_burned += 1;
+        env::log_str(
+            NftBurnLog {
+                owner_id: owner_id.to_string(),
+                authorized_id,
+                token_ids: vec![token.fmt_id()],
+                memo,
+            }
+            .serialize_event()
+            .as_str(),
+        );
+    }
+
+    /// Removes a token from storage and updates the counts on its metadata.
+    /// Does not update `tokens_per_owner` or `tokens_burned`.
+    fn burn_token_internal(&mut self, token: &Token) {
+        // update the counts on token metadata and royalties stored
+        let mut minting_metadata =
+            self.token_metadata.get(&token.metadata_id).unwrap();
+        let count = minting_metadata.minted - minting_metadata.burned;
+        if count > 1 {
+            minting_metadata.burned += 1;
+            self.token_metadata
+                .insert(&token.metadata_id, &minting_metadata);
+        }
+
+        let token_id_tuple = token.id_tuple();
+        self.remove_approval_expiries(token.approvals.values());
+        self.tokens_minted_at.remove(&token_id_tuple);
+        self.token_overrides.remove(&token_id_tuple);
+        let (metadata_id, token_id) = token_id_tuple;
+        let mut metadata_tokens = self
+            .tokens
+            .get(&metadata_id)
+            .expect("This metadata does not yet exist in storage!");
+        metadata_tokens.insert(&token_id, &None);
+        self.tokens.insert(&metadata_id, &metadata_tokens);
+    }
 }
 
 fn log_nft_batch_burn(token_ids: Vec<String>, owner_id: String) {
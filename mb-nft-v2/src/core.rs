@@ -1,22 +1,33 @@
 use std::{
-    collections::HashMap,
+    collections::{
+        HashMap,
+        HashSet,
+    },
     convert::TryFrom,
 };
 
 use mb_sdk::{
     assert_token_owned_by,
     assert_token_unloaned,
-    constants::gas,
+    constants::{
+        gas,
+        MAX_LEN_BATCH_TRANSFER_CALL,
+    },
     data::store::{
         Owner,
         Token,
         TokenCompliant,
+        TokenStatus,
     },
     events::store::{
         NftTransferData,
         NftTransferLog,
+        TransferFeeData,
+    },
+    interfaces::{
+        ext_nft_on_batch_transfer,
+        ext_nft_on_transfer,
     },
-    interfaces::ext_nft_on_transfer,
     near_assert,
     near_panic,
     near_sdk::{
@@ -46,25 +57,8 @@ impl MintbaseStore {
         approval_id: Option<u64>,
         memo: Option<String>,
     ) {
-        assert_one_yocto();
-        let token_id_tuple = parse_token_id(&token_id);
-        let mut token = self.nft_token_internal(token_id_tuple);
-        let old_owner = token.owner_id.to_string();
-        assert_token_unloaned!(token);
-        let authorized_id = assert_token_owned_or_approved(
-            &token,
-            &env::predecessor_account_id(),
-            approval_id,
-        );
-
-        self.transfer_internal(&mut token, receiver_id.clone(), true);
-        log_nft_transfer(
-            &receiver_id,
-            token_id_tuple,
-            &memo,
-            old_owner,
-            authorized_id,
-        );
+        self.pay_transfer_fee(&[token_id.as_str()]);
+        self.nft_transfer_internal(receiver_id, token_id, approval_id, memo);
     }
 
     /// Transfer-and-call function as specified by [NEP-171](https://nomicon.io/Standards/Tokens/NonFungibleToken/Core).
@@ -77,12 +71,15 @@ impl MintbaseStore {
         approval_id: Option<u64>,
         memo: Option<String>,
     ) -> Promise {
-        assert_one_yocto();
+        self.pay_transfer_fee(&[token_id.as_str()]);
+        self.assert_not_burn_sink(&receiver_id);
         let token_id_tuple = parse_token_id(&token_id);
         let mut token = self.nft_token_internal(token_id_tuple);
         let pred = env::predecessor_account_id();
         assert_token_unloaned!(token);
-        let authorized_id = assert_token_owned_or_approved(
+        self.assert_transfer_cooldown_elapsed(&token);
+        self.assert_not_soulbound(&token);
+        let authorized_id = self.assert_token_owned_or_approved(
             &token,
             &env::predecessor_account_id(),
             approval_id,
@@ -90,7 +87,8 @@ impl MintbaseStore {
 
         let previous_owner_id =
             AccountId::new_unchecked(token.owner_id.to_string());
-        let approved_account_ids = token.approvals.clone();
+        // approvals are taken out to keep their expiries in case of a revert
+        let approved_account_ids = std::mem::take(&mut token.approvals);
         let split_owners = token.split_owners.clone();
         // prevent race condition, temporarily lock-replace owner
         self.transfer_internal(&mut token, receiver_id.clone(), true);
@@ -176,34 +174,16 @@ impl MintbaseStore {
             PromiseResult::Failed => true,
         };
         if !must_revert {
+            self.remove_approval_expiries(approved_account_ids.values());
             true
         } else {
-            self.transfer_internal(&mut token, previous_owner_id.clone(), true);
-            log_nft_transfer(
-                &previous_owner_id,
-                token_id_tuple,
-                &None,
-                receiver_id.to_string(),
-                None,
+            self.revert_transfer_internal(
+                &mut token,
+                previous_owner_id,
+                receiver_id,
+                approved_account_ids,
+                split_owners,
             );
-            // restore approvals
-            token.approvals = approved_account_ids;
-            for (account_id, &approval_id) in token.approvals.iter() {
-                crate::approvals::log_approve(
-                    token.id_tuple(),
-                    approval_id,
-                    account_id,
-                );
-            }
-            // restore split owners
-            token.split_owners = split_owners;
-            if let Some(split_owners) = token.split_owners.as_ref() {
-                crate::payout::log_set_split_owners(
-                    vec![token.fmt_id()],
-                    // clone needed because this is drained in the logging function
-                    split_owners.clone(),
-                );
-            }
             false
         }
     }
@@ -234,19 +214,27 @@ impl MintbaseStore {
     /// single call.
     #[payable]
     pub fn nft_batch_transfer(&mut self, token_ids: Vec<(String, AccountId)>) {
-        assert_one_yocto();
         near_assert!(!token_ids.is_empty(), "Token IDs cannot be empty");
+        self.pay_transfer_fee(
+            &token_ids
+                .iter()
+                .map(|(token_id, _)| token_id.as_str())
+                .collect::<Vec<_>>(),
+        );
         let pred = env::predecessor_account_id();
         let mut set_owned =
             self.tokens_per_owner.get(&pred).expect("none owned");
         let (tokens, accounts, old_owners) = token_ids
             .into_iter()
             .map(|(token_id, account_id)| {
+                self.assert_not_burn_sink(&account_id);
                 let token_id_tuple = parse_token_id(&token_id);
                 let mut token = self.nft_token_internal(token_id_tuple);
                 let old_owner = token.owner_id.to_string();
                 assert_token_unloaned!(token);
                 assert_token_owned_by!(token, &pred);
+                self.assert_transfer_cooldown_elapsed(&token);
+                self.assert_not_soulbound(&token);
                 near_assert!(
                     account_id.to_string() != token.owner_id.to_string(),
                     "Token {}:{} is already owned by {}",
@@ -268,12 +256,383 @@ impl MintbaseStore {
         log_nft_batch_transfer(tokens, &accounts, old_owners);
     }
 
+    /// Like `nft_batch_transfer`, but sends all tokens to the same receiver.
+    /// Emits a single transfer log for all tokens.
+    #[payable]
+    pub fn nft_send_all(
+        &mut self,
+        token_ids: Vec<String>,
+        receiver_id: AccountId,
+    ) {
+        near_assert!(!token_ids.is_empty(), "Token IDs cannot be empty");
+        self.pay_transfer_fee(
+            &token_ids.iter().map(String::as_str).collect::<Vec<_>>(),
+        );
+        let pred = env::predecessor_account_id();
+        near_assert!(
+            receiver_id != pred,
+            "Tokens are already owned by {}",
+            receiver_id
+        ); // can't transfer to self
+        self.assert_not_burn_sink(&receiver_id);
+        let mut set_owned =
+            self.tokens_per_owner.get(&pred).expect("none owned");
+        for token_id in token_ids.iter() {
+            let token_id_tuple = parse_token_id(token_id);
+            let mut token = self.nft_token_internal(token_id_tuple);
+            assert_token_unloaned!(token);
+            assert_token_owned_by!(token, &pred);
+            self.assert_transfer_cooldown_elapsed(&token);
+            self.assert_not_soulbound(&token);
+            self.transfer_internal(&mut token, receiver_id.clone(), false);
+            set_owned.remove(&token_id_tuple);
+        }
+        self.tokens_per_owner.insert(&pred, &set_owned);
+        log_nft_send_all(token_ids, &receiver_id, pred.to_string());
+    }
+
+    /// Like `nft_transfer_call`, but transfers multiple tokens to the same
+    /// receiver and notifies it with a single `nft_on_batch_transfer` call.
+    /// The receiver returns a list of token IDs that it rejects, and only
+    /// these are returned to the previous owner. If the receiver call fails,
+    /// all tokens are returned. At most `MAX_LEN_BATCH_TRANSFER_CALL` tokens
+    /// can be transferred at once.
+    ///
+    /// Only the tokens' owner may call this function.
+    #[payable]
+    pub fn nft_batch_transfer_call(
+        &mut self,
+        token_ids: Vec<String>,
+        receiver_id: AccountId,
+        msg: String,
+    ) -> Promise {
+        near_assert!(!token_ids.is_empty(), "Token IDs cannot be empty");
+        near_assert!(
+            token_ids.len() <= MAX_LEN_BATCH_TRANSFER_CALL,
+            "Cannot transfer more than {} tokens at once",
+            MAX_LEN_BATCH_TRANSFER_CALL
+        );
+        self.pay_transfer_fee(
+            &token_ids.iter().map(String::as_str).collect::<Vec<_>>(),
+        );
+        self.assert_not_burn_sink(&receiver_id);
+        let pred = env::predecessor_account_id();
+
+        let mut approved_account_ids = Vec::with_capacity(token_ids.len());
+        let mut split_owners = Vec::with_capacity(token_ids.len());
+        let mut old_owners = Vec::with_capacity(token_ids.len());
+        for token_id in token_ids.iter() {
+            let mut token = self.nft_token_internal(parse_token_id(token_id));
+            assert_token_unloaned!(token);
+            assert_token_owned_by!(token, &pred);
+            self.assert_transfer_cooldown_elapsed(&token);
+            self.assert_not_soulbound(&token);
+            near_assert!(
+                receiver_id != pred,
+                "Token {} is already owned by {}",
+                token.fmt_id(),
+                receiver_id
+            );
+
+            // approvals are taken out to keep their expiries in case of a
+            // revert
+            approved_account_ids.push(std::mem::take(&mut token.approvals));
+            split_owners.push(token.split_owners.clone());
+            old_owners.push(pred.to_string());
+            // prevent race condition, temporarily lock-replace owner
+            self.transfer_internal(&mut token, receiver_id.clone(), true);
+            self.lock_token(&mut token);
+        }
+        log_nft_batch_transfer(
+            token_ids.clone(),
+            &vec![receiver_id.clone(); token_ids.len()],
+            old_owners,
+        );
+
+        let resolve_gas = gas::RESOLVE_TRANSFER
+            + gas::RESOLVE_BATCH_TRANSFER_PER_TOKEN * token_ids.len() as u64;
+        ext_nft_on_batch_transfer::ext(receiver_id.clone())
+            .with_static_gas(gas::NFT_BATCH_TRANSFER_CALL)
+            .nft_on_batch_transfer(
+                pred.clone(),
+                pred.clone(),
+                token_ids.clone(),
+                msg,
+            )
+            .then(
+                store_self::ext(env::current_account_id())
+                    .with_static_gas(resolve_gas)
+                    .nft_resolve_batch_transfer(
+                        pred,
+                        receiver_id,
+                        token_ids,
+                        approved_account_ids,
+                        split_owners,
+                    ),
+            )
+    }
+
+    /// Like `nft_transfer`, but marks the transfer as a gift. The emitted
+    /// `nft_transfer` event has no `authorized_id`, and its `memo` is a JSON
+    /// object `{"gift": true, "memo": <memo>}`, allowing indexers to tell
+    /// gifts apart from sales. Only the token owner can call this, and no
+    /// payout is involved.
+    #[payable]
+    pub fn nft_gift(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        memo: Option<String>,
+    ) {
+        self.pay_transfer_fee(&[token_id.as_str()]);
+        self.assert_not_burn_sink(&receiver_id);
+        let token_id_tuple = parse_token_id(&token_id);
+        let mut token = self.nft_token_internal(token_id_tuple);
+        let old_owner = token.owner_id.to_string();
+        assert_token_unloaned!(token);
+        assert_token_owned_by!(token, &env::predecessor_account_id());
+        self.assert_transfer_cooldown_elapsed(&token);
+        self.assert_not_soulbound(&token);
+
+        self.transfer_internal(&mut token, receiver_id.clone(), true);
+        let memo = near_sdk::serde_json::json!({ "gift": true, "memo": memo });
+        log_nft_transfer(
+            &receiver_id,
+            token_id_tuple,
+            &Some(memo.to_string()),
+            old_owner,
+            None,
+        );
+    }
+
     // -------------------------- view methods -----------------------------
 
+    /// Number of seconds until the token may be transferred. Returns zero if
+    /// the token is not subject to a transfer cooldown, or the cooldown has
+    /// already elapsed.
+    pub fn get_transfer_cooldown_remaining(&self, token_id: String) -> u64 {
+        self.transfer_cooldown_remaining(parse_token_id(&token_id))
+    }
+
+    /// Whether a token exists, has been burned, or has never been minted.
+    /// Unlike `nft_token`, this allows telling apart burned tokens.
+    pub fn nft_token_status(&self, token_id: String) -> TokenStatus {
+        let (metadata_id, id) = parse_token_id(&token_id);
+        match self
+            .tokens
+            .get(&metadata_id)
+            .and_then(|metadata_tokens| metadata_tokens.get(&id))
+        {
+            Some(Some(_)) => TokenStatus::Exists,
+            Some(None) => TokenStatus::Burned,
+            None => TokenStatus::Never,
+        }
+    }
+
     // -------------------------- private methods --------------------------
 
+    /// Call back of `nft_batch_transfer_call`. Returns the token IDs that
+    /// have been successfully transferred to `receiver_id`.
+    #[private]
+    pub fn nft_resolve_batch_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_ids: Vec<String>,
+        approved_account_ids: Vec<HashMap<AccountId, u64>>,
+        split_owners: Vec<Option<SplitOwners>>,
+    ) -> Vec<String> {
+        near_assert!(
+            env::promise_results_count() == 1,
+            "Wtf? Had more than one DataReceipt to process"
+        );
+        // Get the tokens that should be returned
+        let rejected: HashSet<String> = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<Vec<String>>(&value)
+                    .unwrap_or_else(|_| token_ids.clone())
+            }
+            PromiseResult::Failed => token_ids.clone(),
+        }
+        .into_iter()
+        .collect();
+
+        let mut transferred = Vec::with_capacity(token_ids.len());
+        for ((token_id, approvals), splits) in token_ids
+            .into_iter()
+            .zip(approved_account_ids)
+            .zip(split_owners)
+        {
+            let mut token = self.nft_token_internal(parse_token_id(&token_id));
+            self.unlock_token(&mut token);
+            if rejected.contains(&token_id) {
+                self.revert_transfer_internal(
+                    &mut token,
+                    previous_owner_id.clone(),
+                    receiver_id.clone(),
+                    approvals,
+                    splits,
+                );
+            } else {
+                self.remove_approval_expiries(approvals.values());
+                transferred.push(token_id);
+            }
+        }
+        transferred
+    }
+
     // -------------------------- internal methods -------------------------
 
+    /// Return a token to its previous owner after a transfer-and-call chain
+    /// has been rejected, restoring approvals and split owners.
+    fn revert_transfer_internal(
+        &mut self,
+        token: &mut Token,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        approved_account_ids: HashMap<AccountId, u64>,
+        split_owners: Option<SplitOwners>,
+    ) {
+        self.transfer_internal(token, previous_owner_id.clone(), true);
+        log_nft_transfer(
+            &previous_owner_id,
+            token.id_tuple(),
+            &None,
+            receiver_id.to_string(),
+            None,
+        );
+        // restore approvals
+        token.approvals = approved_account_ids;
+        for (account_id, &approval_id) in token.approvals.iter() {
+            crate::approvals::log_approve(
+                token.id_tuple(),
+                approval_id,
+                account_id,
+            );
+        }
+        // restore split owners
+        token.split_owners = split_owners;
+        if let Some(split_owners) = token.split_owners.as_ref() {
+            crate::payout::log_set_split_owners(
+                vec![token.fmt_id()],
+                split_owners,
+            );
+        }
+        self.save_token(token);
+    }
+
+    /// Remaining transfer cooldown of a token in seconds, rounded up.
+    fn transfer_cooldown_remaining(&self, token_id: (u64, u64)) -> u64 {
+        let cooldown = match self.transfer_cooldowns.get(&token_id.0) {
+            None => return 0,
+            Some(secs) => secs.saturating_mul(1_000_000_000),
+        };
+        match self.tokens_minted_at.get(&token_id) {
+            None => 0,
+            Some(minted_at) => minted_at
+                .saturating_add(cooldown)
+                .saturating_sub(env::block_timestamp())
+                .div_ceil(1_000_000_000),
+        }
+    }
+
+    /// Transfers a single token without handling the attached deposit, which
+    /// is up to the caller (`nft_transfer` or `nft_transfer_payout`).
+    pub(crate) fn nft_transfer_internal(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        let token_id_tuple = parse_token_id(&token_id);
+        let mut token = self.nft_token_internal(token_id_tuple);
+        let old_owner = token.owner_id.to_string();
+        assert_token_unloaned!(token);
+        let authorized_id = self.assert_token_owned_or_approved(
+            &token,
+            &env::predecessor_account_id(),
+            approval_id,
+        );
+
+        // transfers to the burn sink are burns, which are not subject to the
+        // transfer cooldown
+        if self.burn_sink_account.as_ref() == Some(&receiver_id) {
+            self.burn_to_sink(token, authorized_id, memo);
+            return;
+        }
+        self.assert_transfer_cooldown_elapsed(&token);
+        self.assert_not_soulbound(&token);
+
+        self.transfer_internal(&mut token, receiver_id.clone(), true);
+        log_nft_transfer(
+            &receiver_id,
+            token_id_tuple,
+            &memo,
+            old_owner,
+            authorized_id,
+        );
+    }
+
+    /// Requires one yoctoNEAR to be attached, plus the transfer fee for each
+    /// of `token_ids` if one has been set. The fees are forwarded to their
+    /// recipient, and any surplus deposit is refunded to the caller.
+    fn pay_transfer_fee(&self, token_ids: &[&str]) {
+        let (fee_recipient, fee) = match self.transfer_fee.as_ref() {
+            None => return assert_one_yocto(),
+            Some((fee_recipient, fee)) => (fee_recipient.clone(), *fee),
+        };
+        let amount = fee
+            .checked_mul(token_ids.len() as u128)
+            .unwrap_or_else(|| env::panic_str("Transfer fee overflow"));
+        let attached_deposit = env::attached_deposit();
+        near_assert!(
+            attached_deposit > amount,
+            "Requires attached deposit of at least {} yoctoNEAR to cover the transfer fee",
+            amount + 1
+        );
+
+        let surplus = attached_deposit - amount - 1;
+        if surplus > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(surplus);
+        }
+        if amount == 0 {
+            return;
+        }
+        Promise::new(fee_recipient.clone()).transfer(amount);
+        env::log_str(
+            &TransferFeeData {
+                token_ids: token_ids.iter().map(|id| id.to_string()).collect(),
+                payer_id: env::predecessor_account_id(),
+                fee_recipient,
+                amount: amount.into(),
+            }
+            .serialize_event(),
+        );
+    }
+
+    /// Panics if the token was minted on metadata with a transfer cooldown
+    /// and that cooldown has not yet elapsed.
+    pub(crate) fn assert_transfer_cooldown_elapsed(&self, token: &Token) {
+        let remaining = self.transfer_cooldown_remaining(token.id_tuple());
+        near_assert!(
+            remaining == 0,
+            "Token {} cannot be transferred during its cooldown ({} seconds remaining)",
+            token.fmt_id(),
+            remaining
+        );
+    }
+
+    /// Panics if the token was minted on soulbound metadata.
+    pub(crate) fn assert_not_soulbound(&self, token: &Token) {
+        near_assert!(
+            !self.soulbound_metadata.contains(&token.metadata_id),
+            "Token {} is soulbound and cannot be transferred",
+            token.fmt_id()
+        );
+    }
+
     /// Set the owner of `token` to `to` and clear the approvals on the
     /// token. Update the `tokens_per_owner` sets. `remove_prior` is an
     /// optimization on batch removal, in particular useful for batch sending
@@ -299,6 +658,7 @@ impl MintbaseStore {
             Some(to.clone()),
         );
         token.owner_id = Owner::Account(to);
+        self.remove_approval_expiries(token.approvals.values());
         token.approvals.clear();
         self.save_token(token);
     }
@@ -343,40 +703,61 @@ impl MintbaseStore {
                 }
             })
     }
-}
 
-/// Checks if `account_id` is allowed to transfer the token and returns the
-/// `authorized_id` to log. Explicitly, returns `None` if token is owned by
-/// `account_id`, returns `Some(account_id)` if `account_id` was approved
-/// with the correct `approval_id`, panics otherwise.
-fn assert_token_owned_or_approved(
-    token: &Token,
-    account_id: &AccountId,
-    approval_id: Option<u64>,
-) -> Option<String> {
-    if token.is_owned_by(account_id) {
-        return None;
-    }
+    /// Checks if `account_id` is allowed to transfer the token and returns the
+    /// `authorized_id` to log. Explicitly, returns `None` if token is owned by
+    /// `account_id`, returns `Some(account_id)` if `account_id` was approved
+    /// with the correct `approval_id`, panics otherwise.
+    fn assert_token_owned_or_approved(
+        &self,
+        token: &Token,
+        account_id: &AccountId,
+        approval_id: Option<u64>,
+    ) -> Option<String> {
+        if token.is_owned_by(account_id) {
+            return None;
+        }
 
-    match (token.approvals.get(account_id), approval_id) {
-        // approval ID needs to exist
-        (_, None) => near_panic!("Disallowing approvals without approval ID"),
-        // account_id needs to be approved
-        (None, _) => {
-            near_panic!(
-                "{} has no approval for token {}:{}",
+        match (token.approvals.get(account_id), approval_id) {
+            // approval ID needs to exist
+            (_, None) => {
+                near_panic!("Disallowing approvals without approval ID")
+            }
+            // account_id needs to be approved
+            (None, _) => {
+                near_panic!(
+                    "{} has no approval for token {}:{}",
+                    account_id,
+                    token.metadata_id,
+                    token.id
+                )
+            }
+            // approval IDs need to match
+            (Some(a), Some(b)) if *a != b => near_panic!(
+                "The current approval ID is {}, but {} has been provided",
+                a,
+                b
+            ),
+            // approval must not have expired
+            (Some(a), _) if self.approval_expired(*a) => near_panic!(
+                "Approval of {} for token {}:{} has expired",
                 account_id,
                 token.metadata_id,
                 token.id
-            )
+            ),
+            _ => Some(account_id.to_string()),
         }
-        // approval IDs need to match
-        (Some(a), Some(b)) if *a != b => near_panic!(
-            "The current approval ID is {}, but {} has been provided",
-            a,
-            b
-        ),
-        _ => Some(account_id.to_string()),
+    }
+
+    /// Tokens are only burned when sent to the burn sink via `nft_transfer`.
+    /// All other transfer methods refuse the burn sink as receiver, as the
+    /// tokens would otherwise be stranded there.
+    fn assert_not_burn_sink(&self, receiver_id: &AccountId) {
+        near_assert!(
+            self.burn_sink_account.as_ref() != Some(receiver_id),
+            "{} is the burn sink, use nft_transfer to burn tokens",
+            receiver_id
+        );
     }
 }
 
@@ -398,6 +779,22 @@ fn log_nft_transfer(
     env::log_str(data.serialize_event().as_str());
 }
 
+fn log_nft_send_all(
+    token_ids: Vec<String>,
+    receiver_id: &AccountId,
+    old_owner_id: String,
+) {
+    let data = NftTransferData(vec![NftTransferLog {
+        authorized_id: None,
+        old_owner_id,
+        new_owner_id: receiver_id.to_string(),
+        token_ids,
+        memo: None,
+    }]);
+
+    env::log_str(data.serialize_event().as_str());
+}
+
 fn log_nft_batch_transfer(
     token_ids: Vec<String>,
     accounts: &[AccountId],
@@ -1,5 +1,12 @@
 use mb_sdk::{
-    data::store::TokenCompliant,
+    constants::{
+        MAX_LIMIT_TOKENS_BY_EXTRA,
+        MAX_LIMIT_TOKENS_WITH_APPROVALS,
+    },
+    data::store::{
+        TokenCompliant,
+        TokenWithApprovalsJson,
+    },
     near_sdk::{
         self,
         json_types::U64,
@@ -44,6 +51,56 @@ impl MintbaseStore {
             .collect()
     }
 
+    /// List NFTs whose metadata `extra` field contains `substring`, e.g. to
+    /// browse a collection by trait. This is best-effort and intended for
+    /// small collections: it scans all metadata on the contract until `limit`
+    /// matching tokens have been found, so a low `limit` caps the work done
+    /// per call. At most `MAX_LIMIT_TOKENS_BY_EXTRA` tokens are returned.
+    pub fn nft_tokens_by_extra(
+        &self,
+        substring: String,
+        from_index: Option<U128>, // default: "0"
+        limit: Option<u32>,       // default: MAX_LIMIT_TOKENS_BY_EXTRA
+    ) -> Vec<TokenCompliant> {
+        let mut skip = from_index.unwrap_or(U128(0)).0;
+        let limit = limit
+            .unwrap_or(MAX_LIMIT_TOKENS_BY_EXTRA)
+            .min(MAX_LIMIT_TOKENS_BY_EXTRA) as usize;
+        let mut tokens = Vec::with_capacity(limit);
+        if limit == 0 {
+            return tokens;
+        }
+
+        'metadata: for (metadata_id, metadata_tokens) in self.tokens.iter() {
+            let matches = self
+                .token_metadata
+                .get(&metadata_id)
+                .and_then(|minting_metadata| minting_metadata.metadata.extra)
+                .map(|extra| extra.contains(&substring))
+                .unwrap_or(false);
+            if !matches {
+                continue;
+            }
+            for (token_id, _) in metadata_tokens.iter() {
+                let token = match self
+                    .nft_token_compliant_internal(&(metadata_id, token_id))
+                {
+                    None => continue,
+                    Some(token) => token,
+                };
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                tokens.push(token);
+                if tokens.len() == limit {
+                    break 'metadata;
+                }
+            }
+        }
+        tokens
+    }
+
     /// Total number of available NFTs for specified owner according to
     /// [NEP-181](https://nomicon.io/Standards/Tokens/NonFungibleToken/Enumeration)
     pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U64 {
@@ -82,4 +139,26 @@ impl MintbaseStore {
             .flat_map(|id| self.nft_token_compliant_internal(&id))
             .collect::<Vec<_>>()
     }
+
+    /// List NFTs for specified owner like `nft_tokens_for_owner`, each paired
+    /// with the accounts approved to transfer it. This saves clients from
+    /// calling `nft_approvals` for every token. `limit` defaults to and is
+    /// capped at 50.
+    pub fn nft_tokens_for_owner_with_approvals(
+        &self,
+        account_id: AccountId,
+        from_index: Option<String>,
+        limit: Option<u32>,
+    ) -> Vec<TokenWithApprovalsJson> {
+        let limit = limit
+            .unwrap_or(MAX_LIMIT_TOKENS_WITH_APPROVALS)
+            .min(MAX_LIMIT_TOKENS_WITH_APPROVALS);
+        self.nft_tokens_for_owner(account_id, from_index, Some(limit))
+            .into_iter()
+            .map(|token| TokenWithApprovalsJson {
+                approvals: token.approved_account_ids.clone(),
+                token,
+            })
+            .collect()
+    }
 }
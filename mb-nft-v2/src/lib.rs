@@ -11,6 +11,7 @@ use mb_sdk::{
         SplitOwners,
         Token,
         TokenMetadataCompliant,
+        TokenMetadataOverride,
     },
     near_assert,
     near_panic,
@@ -23,7 +24,9 @@ use mb_sdk::{
         },
         collections::{
             LookupMap,
+            LookupSet,
             TreeMap,
+            UnorderedMap,
             UnorderedSet,
         },
         env,
@@ -36,6 +39,7 @@ use mb_sdk::{
         AccountId,
         StorageUsage,
     },
+    utils::ContractVersionJson,
 };
 
 /// Implementing approval management as [described in the Nomicon](https://nomicon.io/Standards/NonFungibleToken/ApprovalManagement).
@@ -54,6 +58,11 @@ mod minting;
 mod ownership;
 /// Implementing payouts as [described in the Nomicon](https://nomicon.io/Standards/NonFungibleToken/Payout).
 mod payout;
+/// Implementing storage management for minters as [described in the Nomicon](https://nomicon.io/Standards/StorageManagement).
+mod storage;
+/// Methods to seed state in integration tests
+#[cfg(feature = "testing")]
+mod testing;
 /// Implementing NFT updates as [described in the Nomicon](https://nomicon.io/Standards/NonFungibleToken/Payout).
 mod update;
 
@@ -122,6 +131,66 @@ pub struct MintbaseStore {
     pub storage_deposit_by_account: LookupMap<AccountId, near_sdk::Balance>,
     /// Pre-purchased storage for minting on a per-metadata basis
     pub storage_deposit_by_metadata: LookupMap<u64, near_sdk::Balance>,
+    /// Number of seconds that freshly minted tokens of a given metadata need
+    /// to wait before they can be transferred
+    pub transfer_cooldowns: LookupMap<u64, u64>,
+    /// Mint timestamps (in nanoseconds) for tokens on metadata that has a
+    /// transfer cooldown. Entries are removed once the token is burned.
+    pub tokens_minted_at: LookupMap<(u64, u64), u64>,
+    /// If true, creators may repair the `reference` of locked metadata. Can be
+    /// irreversibly disabled by the store owner.
+    pub allow_reference_repairs: bool,
+    /// Receives `MINTING_FEE` instead of the parent account if set. Required
+    /// for stores deployed to top-level accounts to pay a minting fee.
+    pub fee_recipient: Option<AccountId>,
+    /// Account that has been proposed as new owner, together with the
+    /// `keep_old_creators` setting to apply once the proposal is accepted.
+    pub pending_owner: Option<(AccountId, bool)>,
+    /// Named royalties that can be referenced when creating metadata instead
+    /// of passing the same `royalty_args` repeatedly.
+    pub royalty_templates: UnorderedMap<String, Royalty>,
+    /// Fee in yoctoNEAR per token that has to be attached to transfers (in
+    /// addition to the one yoctoNEAR), and the account that receives it.
+    /// Sales via `nft_transfer_payout` are exempt.
+    pub transfer_fee: Option<(AccountId, near_sdk::Balance)>,
+    /// Maximum number of tokens that may be minted across all metadata within
+    /// a sliding window, as `(count, window_seconds)`.
+    pub global_mint_rate_limit: Option<(u32, u64)>,
+    /// Number of tokens minted per time bucket (start of the bucket in
+    /// seconds, count) while `global_mint_rate_limit` is set. Buckets that
+    /// have left the window are dropped on each mint, such that this never
+    /// holds more than `MINT_RATE_LIMIT_BUCKETS + 1` entries.
+    pub recent_mints: Vec<(u64, u32)>,
+    /// Per-token `reference`/`media` overrides specified on mint. Entries are
+    /// removed once the token is burned.
+    pub token_overrides: LookupMap<(u64, u64), TokenMetadataOverride>,
+    /// Splits of the primary sale proceeds (the minting price after
+    /// royalties) per metadata. Without an entry, the creator receives
+    /// everything.
+    pub primary_splits: LookupMap<u64, SplitOwners>,
+    /// FT contract in which the creator of a metadata would prefer to receive
+    /// royalties. Purely advisory, markets may use it to pick the currency
+    /// of listings.
+    pub royalty_currency_hints: LookupMap<u64, AccountId>,
+    /// Mint window as `(starts_at, expires_at)` in nanoseconds that is used
+    /// for newly created metadata that doesn't specify its own.
+    pub default_mint_window: Option<(u64, u64)>,
+    /// Tokens sent to this account via `nft_transfer` are burned instead of
+    /// transferred.
+    pub burn_sink_account: Option<AccountId>,
+    /// Expiry timestamps in nanoseconds by approval ID. As approval IDs are
+    /// never reused, entries become irrelevant once an approval is revoked
+    /// or replaced.
+    pub approval_expiries: LookupMap<u64, u64>,
+    /// Maximum number of tokens that a single account may mint on a given
+    /// metadata. Without an entry, minting is not limited per account.
+    pub per_minter_caps: LookupMap<u64, u32>,
+    /// Number of tokens minted per metadata and minter, only tracked for
+    /// metadata that has an entry in `per_minter_caps`.
+    pub minted_per_minter: LookupMap<(u64, AccountId), u32>,
+    /// Metadata IDs whose tokens cannot be transferred or approved after
+    /// minting, only burned.
+    pub soulbound_metadata: LookupSet<u64>,
 }
 
 impl Default for MintbaseStore {
@@ -159,6 +228,24 @@ impl MintbaseStore {
             minting_cap: None,
             storage_deposit_by_account: LookupMap::new(b"h".to_vec()),
             storage_deposit_by_metadata: LookupMap::new(b"j".to_vec()),
+            transfer_cooldowns: LookupMap::new(b"k".to_vec()),
+            tokens_minted_at: LookupMap::new(b"l".to_vec()),
+            allow_reference_repairs: true,
+            fee_recipient: None,
+            pending_owner: None,
+            royalty_templates: UnorderedMap::new(b"m".to_vec()),
+            transfer_fee: None,
+            global_mint_rate_limit: None,
+            recent_mints: Vec::new(),
+            token_overrides: LookupMap::new(b"n".to_vec()),
+            primary_splits: LookupMap::new(b"o".to_vec()),
+            royalty_currency_hints: LookupMap::new(b"p".to_vec()),
+            default_mint_window: None,
+            burn_sink_account: None,
+            approval_expiries: LookupMap::new(b"q".to_vec()),
+            per_minter_caps: LookupMap::new(b"r".to_vec()),
+            minted_per_minter: LookupMap::new(b"s".to_vec()),
+            soulbound_metadata: LookupSet::new(b"t".to_vec()),
         }
     }
 
@@ -202,11 +289,28 @@ impl MintbaseStore {
         self.num_approved
     }
 
+    /// Show name, version, and commit hash of the deployed contract binary
+    pub fn get_version(&self) -> ContractVersionJson {
+        mb_sdk::contract_version!()
+    }
+
     /// Get maximum number of minted tokens on this contract
     pub fn get_minting_cap(&self) -> Option<u64> {
         self.minting_cap
     }
 
+    /// Check if the contract-wide minting cap has been reached
+    pub fn is_minted_out(&self) -> bool {
+        self.minting_cap
+            .map(|cap| self.tokens_minted >= cap)
+            .unwrap_or(false)
+    }
+
+    /// Get status of reference repairs on locked metadata
+    pub fn get_allow_reference_repairs(&self) -> bool {
+        self.allow_reference_repairs
+    }
+
     /// Get status of open minting enablement
     pub fn get_open_creating(&self) -> bool {
         self.creators.is_empty()
@@ -218,11 +322,60 @@ impl MintbaseStore {
     /// elements of the state should be copied over. This method may only be
     /// called by the holder of the Store public key, in this case the
     /// Factory.
+    ///
+    /// Stores that still have the state layout of `MintbaseStoreV1` are
+    /// migrated to the current layout, with all newer settings disabled.
     #[private]
     #[init(ignore_state)]
     pub fn migrate(metadata: NFTContractMetadata) -> Self {
-        let old = env::state_read().expect("ohno ohno state");
-        Self { metadata, ..old }
+        let state = env::storage_read(b"STATE").expect("ohno ohno state");
+        if let Ok(current) = Self::try_from_slice(&state) {
+            return Self {
+                metadata,
+                ..current
+            };
+        }
+
+        let old = MintbaseStoreV1::try_from_slice(&state)
+            .unwrap_or_else(|_| env::panic_str("Cannot deserialize state"));
+        Self {
+            creators: old.creators,
+            metadata,
+            token_metadata: old.token_metadata,
+            metadata_id: old.metadata_id,
+            token_royalty: old.token_royalty,
+            tokens: old.tokens,
+            tokens_per_owner: old.tokens_per_owner,
+            composables: old.composables,
+            next_token_id: old.next_token_id,
+            tokens_minted: old.tokens_minted,
+            tokens_burned: old.tokens_burned,
+            num_approved: old.num_approved,
+            owner_id: old.owner_id,
+            storage_costs: old.storage_costs,
+            allow_moves: old.allow_moves,
+            minting_cap: old.minting_cap,
+            storage_deposit_by_account: old.storage_deposit_by_account,
+            storage_deposit_by_metadata: old.storage_deposit_by_metadata,
+            transfer_cooldowns: LookupMap::new(b"k".to_vec()),
+            tokens_minted_at: LookupMap::new(b"l".to_vec()),
+            allow_reference_repairs: true,
+            fee_recipient: None,
+            pending_owner: None,
+            royalty_templates: UnorderedMap::new(b"m".to_vec()),
+            transfer_fee: None,
+            global_mint_rate_limit: None,
+            recent_mints: Vec::new(),
+            token_overrides: LookupMap::new(b"n".to_vec()),
+            primary_splits: LookupMap::new(b"o".to_vec()),
+            royalty_currency_hints: LookupMap::new(b"p".to_vec()),
+            default_mint_window: None,
+            burn_sink_account: None,
+            approval_expiries: LookupMap::new(b"q".to_vec()),
+            per_minter_caps: LookupMap::new(b"r".to_vec()),
+            minted_per_minter: LookupMap::new(b"s".to_vec()),
+            soulbound_metadata: LookupSet::new(b"t".to_vec()),
+        }
     }
 
     // -------------------------- internal methods -------------------------
@@ -327,6 +480,20 @@ pub trait NonFungibleResolveTransfer {
         approved_account_ids: std::collections::HashMap<AccountId, u64>,
         split_owners: Option<SplitOwners>,
     );
+
+    /// Finalize an `nft_batch_transfer_call` chain of cross-contract calls.
+    /// Works like `nft_resolve_transfer`, but the receiver returns the list of
+    /// token IDs that it rejects, and only those are returned to
+    /// `previous_owner_id`.
+    #[private]
+    fn nft_resolve_batch_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_ids: Vec<String>,
+        approved_account_ids: Vec<std::collections::HashMap<AccountId, u64>>,
+        split_owners: Vec<Option<SplitOwners>>,
+    );
 }
 
 pub(crate) fn parse_token_id(s: &str) -> (u64, u64) {
@@ -351,3 +518,28 @@ pub(crate) fn parse_token_id(s: &str) -> (u64, u64) {
 pub(crate) fn fmt_token_id(tuple: (u64, u64)) -> String {
     format!("{}:{}", tuple.0, tuple.1)
 }
+
+// State layout before transfer cooldowns and the subsequent settings were
+// added, required to migrate stores that have been deployed with it
+#[derive(BorshDeserialize)]
+struct MintbaseStoreV1 {
+    pub creators: UnorderedSet<AccountId>,
+    #[allow(dead_code)]
+    pub metadata: NFTContractMetadata,
+    pub token_metadata: LookupMap<u64, MintingMetadata>,
+    pub metadata_id: u64,
+    pub token_royalty: LookupMap<u64, Royalty>,
+    pub tokens: TreeMap<u64, TreeMap<u64, Option<Token>>>,
+    pub tokens_per_owner: LookupMap<AccountId, UnorderedSet<(u64, u64)>>,
+    pub composables: LookupMap<String, UnorderedSet<String>>,
+    pub next_token_id: LookupMap<u64, u64>,
+    pub tokens_minted: u64,
+    pub tokens_burned: u64,
+    pub num_approved: u64,
+    pub owner_id: AccountId,
+    pub storage_costs: StorageCosts,
+    pub allow_moves: bool,
+    pub minting_cap: Option<u64>,
+    pub storage_deposit_by_account: LookupMap<AccountId, near_sdk::Balance>,
+    pub storage_deposit_by_metadata: LookupMap<u64, near_sdk::Balance>,
+}
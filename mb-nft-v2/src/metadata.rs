@@ -59,6 +59,9 @@ impl MintbaseStore {
             .get(&self.nft_token_internal(token_id).metadata_id)
             .expect("bad metadata_id");
         let mut metadata = minting_metadata.metadata;
+        if let Some(token_override) = self.token_overrides.get(&token_id) {
+            token_override.apply(&mut metadata);
+        }
         // If copies would overflow, just use `None` instead. Need to keep the
         // u16 for backwards compatibility.
         metadata.copies = {
@@ -72,6 +75,31 @@ impl MintbaseStore {
         metadata
     }
 
+    /// Get the ID of the metadata a token has been minted on, without loading
+    /// the metadata itself. Returns `None` if the token does not exist.
+    pub fn nft_token_metadata_id(&self, token_id: String) -> Option<U64> {
+        let (metadata_id, id) = parse_token_id(&token_id);
+        self.tokens
+            .get(&metadata_id)
+            .and_then(|metadata_tokens| metadata_tokens.get(&id))
+            .flatten()
+            .map(|token| token.metadata_id.into())
+    }
+
+    /// The FT contract in which the creator of this token would prefer to
+    /// receive royalties, see `set_royalty_currency_hint`. Markets may use
+    /// this to decide on the currency of a sale. Returns `None` if there is
+    /// no preference or the token does not exist.
+    pub fn get_preferred_sale_currency(
+        &self,
+        token_id: String,
+    ) -> Option<AccountId> {
+        self.nft_token_metadata_id(token_id)
+            .and_then(|metadata_id| {
+                self.royalty_currency_hints.get(&metadata_id.0)
+            })
+    }
+
     /// The Token URI is generated to index the token on whatever distributed
     /// storage platform this `Store` uses. Mintbase publishes token data on
     /// Arweave. `Store` owners may opt to use their own storage platform.
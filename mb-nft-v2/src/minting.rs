@@ -1,24 +1,31 @@
-use std::convert::TryInto;
+use std::collections::HashMap;
 
 use mb_sdk::{
     constants::{
         DYNAMIC_METADATA_MAX_TOKENS,
+        MAX_LEN_METADATA_STATES_QUERY,
+        MAX_LEN_MINTERS_PAGE,
         MAX_LEN_ROYALTIES,
         MAX_LEN_SPLITS,
+        MAX_LEN_TOKEN_OVERRIDE,
         MINIMUM_FREE_STORAGE_STAKE,
         MINTING_FEE,
+        MINT_RATE_LIMIT_BUCKETS,
     },
     data::store::{
         ComposableStats,
+        MetadataStateJson,
         MintingPayment,
         Royalty,
         RoyaltyArgs,
         SplitBetweenUnparsed,
         TokenMetadata,
+        TokenMetadataOverride,
     },
     events::store::{
         CreateMetadataData,
         MbStoreChangeSettingDataV020,
+        MintingCapReachedData,
         NftMintLog,
         NftMintLogMemo,
     },
@@ -35,6 +42,7 @@ use mb_sdk::{
         Promise,
     },
     serde::Deserialize,
+    utils::parent_account_id,
 };
 
 use crate::*;
@@ -56,12 +64,18 @@ impl MintbaseStore {
         is_dynamic: Option<bool>,
         price: U128,
         ft_contract_id: Option<AccountId>,
+        transfer_cooldown_seconds: Option<u64>,
+        royalty_template: Option<String>,
+        primary_split: Option<SplitBetweenUnparsed>,
+        per_minter_cap: Option<u32>,
+        soulbound: Option<bool>,
     ) -> String {
         // metadata ID: either predefined (must not conflict with existing), or
         // increasing the counter for it
         let metadata_id = self.get_metadata_id(metadata_id);
 
         let is_locked = !is_dynamic.unwrap_or(false);
+        let soulbound = soulbound.unwrap_or(false);
 
         // creator needs to be allowed to create metadata on this smart contract
         let creator = env::predecessor_account_id();
@@ -75,20 +89,41 @@ impl MintbaseStore {
         validate_metadata(&metadata);
 
         // validate royalties
-        let roy_len = royalty_args
+        let checked_royalty = match (royalty_args, royalty_template) {
+            (Some(_), Some(_)) => near_panic!(
+                "Cannot use both `royalty_args` and `royalty_template`"
+            ),
+            (Some(royalty_args), None) => Some(Royalty::new(royalty_args)),
+            (None, Some(name)) => Some(self.get_royalty_template(&name)),
+            (None, None) => None,
+        };
+        let roy_len = checked_royalty
             .as_ref()
-            .map(|pre_roy| {
-                let len = pre_roy.split_between.len();
-                len as u32
-            })
+            .map(|roy| roy.split_between.len() as u32)
             .unwrap_or(0);
-        let checked_royalty = royalty_args.map(Royalty::new);
         near_assert!(
             roy_len <= MAX_LEN_ROYALTIES,
             "Number of royalty holders may not exceed {}",
             MAX_LEN_ROYALTIES
         );
 
+        // validate primary sale splits
+        let primary_split_len = primary_split
+            .as_ref()
+            .map(|split| split.len() as u32)
+            .unwrap_or(0);
+        near_assert!(
+            primary_split_len <= MAX_LEN_SPLITS,
+            "Number of primary split holders may not exceed {}",
+            MAX_LEN_SPLITS
+        );
+        let primary_split = primary_split.map(SplitOwners::new);
+
+        near_assert!(
+            per_minter_cap != Some(0),
+            "`per_minter_cap` needs to be greater than zero"
+        );
+
         // makes sure storage is covered
         let metadata_size = borsh::to_vec(&metadata).unwrap().len() as u64;
         let expected_storage_consumption: Balance = self
@@ -96,6 +131,10 @@ impl MintbaseStore {
                 metadata_size,
                 roy_len,
                 minters_allowlist.as_ref().map(|l| l.len()).unwrap_or(0) as u64,
+                transfer_cooldown_seconds.is_some(),
+                primary_split_len,
+                per_minter_cap.is_some(),
+                soulbound,
             );
         let covered_storage = env::attached_deposit();
         near_assert!(
@@ -105,6 +144,21 @@ impl MintbaseStore {
             expected_storage_consumption + MINTING_FEE
         );
 
+        // the default mint window only fills in what has been omitted, the
+        // resulting window still needs to be valid
+        let starts_at = starts_at
+            .map(|t| t.0)
+            .or(self.default_mint_window.map(|(start, _)| start));
+        let expires_at = expires_at
+            .map(|t| t.0)
+            .or(self.default_mint_window.map(|(_, expiry)| expiry));
+        if let (Some(start), Some(expiry)) = (starts_at, expires_at) {
+            near_assert!(
+                start < expiry,
+                "Mint window must start before it expires"
+            );
+        }
+
         if let Some(true) = unique_minters {
             near_assert!(minters_allowlist.is_some(), "`unique_minters` may only be used along with `minters_allowlist`")
         }
@@ -123,8 +177,8 @@ impl MintbaseStore {
                 accounts.into_iter().map(|acc| (acc, false)).collect()
             }),
             unique_minters: unique_minters.unwrap_or(false),
-            starts_at: starts_at.map(|t| t.0),
-            expires_at: expires_at.map(|t| t.0),
+            starts_at,
+            expires_at,
             creator: creator.clone(),
             is_locked,
             metadata,
@@ -133,6 +187,16 @@ impl MintbaseStore {
         checked_royalty
             .as_ref()
             .map(|r| self.token_royalty.insert(&metadata_id, r));
+        transfer_cooldown_seconds
+            .map(|secs| self.transfer_cooldowns.insert(&metadata_id, &secs));
+        primary_split
+            .as_ref()
+            .map(|split| self.primary_splits.insert(&metadata_id, split));
+        per_minter_cap
+            .map(|cap| self.per_minter_caps.insert(&metadata_id, &cap));
+        if soulbound {
+            self.soulbound_metadata.insert(&metadata_id);
+        }
         self.next_token_id.insert(&metadata_id, &0);
         self.tokens.insert(
             &metadata_id,
@@ -151,11 +215,108 @@ impl MintbaseStore {
             free_storage_stake
         );
 
-        log_create_metadata(metadata_id, minting_metadata, checked_royalty);
+        log_create_metadata(
+            metadata_id,
+            minting_metadata,
+            checked_royalty,
+            per_minter_cap,
+            soulbound,
+        );
 
         metadata_id.to_string()
     }
 
+    /// Like `create_metadata`, but the metadata ID is derived from a hash of
+    /// `metadata` instead of a counter, such that it can be predicted via
+    /// `derive_metadata_id` without coordinating with other creators.
+    /// Creating the same metadata twice (or a hash collision with an existing
+    /// metadata ID) panics, in which case the metadata needs to be modified,
+    /// e.g. by changing `extra`.
+    #[payable]
+    pub fn create_metadata_deterministic(
+        &mut self,
+        metadata: TokenMetadata,
+        royalty_args: Option<RoyaltyArgs>,
+        minters_allowlist: Option<Vec<AccountId>>,
+        unique_minters: Option<bool>,
+        max_supply: Option<u32>,
+        starts_at: Option<U64>,
+        expires_at: Option<U64>,
+        is_dynamic: Option<bool>,
+        price: U128,
+        ft_contract_id: Option<AccountId>,
+        transfer_cooldown_seconds: Option<u64>,
+        royalty_template: Option<String>,
+        primary_split: Option<SplitBetweenUnparsed>,
+        per_minter_cap: Option<u32>,
+        soulbound: Option<bool>,
+    ) -> String {
+        let metadata_id = self.derive_metadata_id(metadata.clone());
+        self.create_metadata(
+            metadata,
+            Some(metadata_id),
+            royalty_args,
+            minters_allowlist,
+            unique_minters,
+            max_supply,
+            starts_at,
+            expires_at,
+            is_dynamic,
+            price,
+            ft_contract_id,
+            transfer_cooldown_seconds,
+            royalty_template,
+            primary_split,
+            per_minter_cap,
+            soulbound,
+        )
+    }
+
+    /// Stores a royalty under `name`, such that it can be used via the
+    /// `royalty_template` argument of `create_metadata`. Templates cannot be
+    /// overwritten. Only accounts that are allowed to create metadata can
+    /// call this, and the storage for the template must be covered by the
+    /// attached deposit.
+    #[payable]
+    pub fn register_royalty_template(
+        &mut self,
+        name: String,
+        royalty_args: RoyaltyArgs,
+    ) {
+        let creator = env::predecessor_account_id();
+        near_assert!(
+            self.creators.is_empty() || self.creators.contains(&creator),
+            "{} is not allowed to create metadata",
+            creator
+        );
+        near_assert!(
+            royalty_args.split_between.len() as u32 <= MAX_LEN_ROYALTIES,
+            "Number of royalty holders may not exceed {}",
+            MAX_LEN_ROYALTIES
+        );
+        near_assert!(
+            self.royalty_templates.get(&name).is_none(),
+            "Royalty template {} already exists",
+            name
+        );
+
+        let initial_storage_usage = env::storage_usage();
+        self.royalty_templates
+            .insert(&name, &Royalty::new(royalty_args));
+        let storage_cost = (env::storage_usage() - initial_storage_usage)
+            as u128
+            * self.storage_costs.storage_price_per_byte;
+        near_assert!(
+            env::attached_deposit() >= storage_cost,
+            "Storing this royalty template requires a deposit of {} yoctoNEAR",
+            storage_cost
+        );
+    }
+
+    /// Mints tokens on existing metadata. Each token can optionally carry its
+    /// own `reference` and `media` via `per_token_overrides`, which needs to
+    /// have one entry per minted token. Overrides require additional storage
+    /// deposit.
     #[payable]
     pub fn mint_on_metadata(
         &mut self,
@@ -164,6 +325,7 @@ impl MintbaseStore {
         num_to_mint: Option<u16>,
         token_ids: Option<Vec<U64>>,
         split_owners: Option<SplitBetweenUnparsed>,
+        per_token_overrides: Option<Vec<TokenMetadataOverride>>,
     ) {
         let args = self.preprocess_mint(
             env::predecessor_account_id(),
@@ -173,6 +335,7 @@ impl MintbaseStore {
                 num_to_mint,
                 token_ids,
                 split_owners,
+                per_token_overrides,
             },
         );
 
@@ -187,8 +350,7 @@ impl MintbaseStore {
         );
 
         // is the storage deposited?
-        let storage_usage =
-            self.storage_cost_to_mint(args.num_to_mint, args.num_splits);
+        let storage_usage = self.storage_cost_to_mint(&args);
         if let Some(deposit) = self.subtract_storage_deposit(
             &args.minter_id,
             args.metadata_id,
@@ -245,25 +407,47 @@ impl MintbaseStore {
 
         let amount = env::attached_deposit();
         if let Some(U64(metadata_id)) = metadata_id {
-            let new_deposit =
-                match self.storage_deposit_by_metadata.get(&metadata_id) {
-                    // subtract common for entry creation
-                    None => amount - mb_sdk::constants::storage_stake::COMMON,
-                    Some(old_deposit) => old_deposit + amount,
-                };
-            self.storage_deposit_by_metadata
-                .insert(&metadata_id, &new_deposit);
+            self.deposit_storage_for_metadata(metadata_id, amount);
         } else {
             let account_id =
                 account_id.unwrap_or(env::predecessor_account_id());
-            let new_deposit =
-                match self.storage_deposit_by_account.get(&account_id) {
-                    // subtract common for entry creation
-                    None => amount - mb_sdk::constants::storage_stake::COMMON,
-                    Some(old_deposit) => old_deposit + amount,
-                };
-            self.storage_deposit_by_account
-                .insert(&account_id, &new_deposit);
+            self.deposit_storage_for_account(&account_id, amount);
+        }
+    }
+
+    /// Like `deposit_storage` with a `metadata_id`, but sponsors mints for
+    /// multiple metadata at once. The attached deposit is split according to
+    /// `amounts`, which must have the same length as `metadata_ids`. Any
+    /// deposit exceeding the sum of `amounts` is refunded.
+    #[payable]
+    pub fn batch_deposit_storage_for_metadata(
+        &mut self,
+        metadata_ids: Vec<U64>,
+        amounts: Vec<U128>,
+    ) {
+        near_assert!(!metadata_ids.is_empty(), "Metadata IDs cannot be empty");
+        near_assert!(
+            metadata_ids.len() == amounts.len(),
+            "Number of metadata IDs and amounts must be equal"
+        );
+        let total: Balance = amounts.iter().map(|amount| amount.0).sum();
+        let attached = env::attached_deposit();
+        near_assert!(
+            total <= attached,
+            "Attached deposit of {} yoctoNEAR does not cover the total of {} yoctoNEAR",
+            attached,
+            total
+        );
+
+        for (U64(metadata_id), U128(amount)) in
+            metadata_ids.into_iter().zip(amounts)
+        {
+            self.deposit_storage_for_metadata(metadata_id, amount);
+        }
+
+        if attached > total {
+            Promise::new(env::predecessor_account_id())
+                .transfer(attached - total);
         }
     }
 
@@ -361,8 +545,7 @@ impl MintbaseStore {
         );
 
         // is the storage deposited?
-        let storage_usage =
-            self.storage_cost_to_mint(args.num_to_mint, args.num_splits);
+        let storage_usage = self.storage_cost_to_mint(&args);
         self.subtract_storage_deposit(
             &args.minter_id,
             args.metadata_id,
@@ -386,6 +569,14 @@ impl MintbaseStore {
 
     // -------------------------- view methods -----------------------------
 
+    /// Metadata ID that `create_metadata_deterministic` would use for
+    /// `metadata`, which are the first 8 bytes of the sha256 hash of its
+    /// borsh serialization.
+    pub fn derive_metadata_id(&self, metadata: TokenMetadata) -> U64 {
+        let hash = env::sha256(&borsh::to_vec(&metadata).unwrap());
+        u64::from_be_bytes(hash[..8].try_into().unwrap()).into()
+    }
+
     /// Check if `account_id` is a minter.
     pub fn check_is_creator(&self, account_id: AccountId) -> bool {
         self.creators.contains(&account_id)
@@ -393,10 +584,44 @@ impl MintbaseStore {
 
     /// Lists all account IDs that are currently allowed to mint on this
     /// contract.
+    ///
+    /// Deprecated: This might exceed gas limits for large sets of creators, use
+    /// `list_creators_paginated` instead.
     pub fn list_creators(&self) -> Vec<AccountId> {
         self.creators.iter().collect()
     }
 
+    /// Lists a page of account IDs that are currently allowed to mint on this
+    /// contract. At most `MAX_LEN_MINTERS_PAGE` (100) accounts are returned
+    /// per call.
+    pub fn list_creators_paginated(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        let limit = limit
+            .unwrap_or(MAX_LEN_MINTERS_PAGE)
+            .min(MAX_LEN_MINTERS_PAGE);
+        self.creators
+            .as_vector()
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Number of account IDs that are currently allowed to mint on this
+    /// contract.
+    pub fn creators_count(&self) -> u64 {
+        self.creators.len()
+    }
+
+    /// Metadata ID that `create_metadata` will assign if no `metadata_id` is
+    /// specified, skipping IDs that have already been taken explicitly.
+    pub fn get_next_metadata_id(&self) -> U64 {
+        self.next_free_metadata_id().into()
+    }
+
     /// Retrieves metadata
     pub fn get_metadata(
         &self,
@@ -407,9 +632,190 @@ impl MintbaseStore {
             .map(|minting_metadata| minting_metadata.metadata.into())
     }
 
+    /// Shows the minting state for up to 100 metadata at once, in the order of
+    /// `metadata_ids`, with `None` for metadata that don't exist.
+    pub fn get_metadata_states(
+        &self,
+        metadata_ids: Vec<U64>,
+    ) -> Vec<Option<MetadataStateJson>> {
+        near_assert!(
+            metadata_ids.len() <= MAX_LEN_METADATA_STATES_QUERY,
+            "Cannot query more than {} metadata at once",
+            MAX_LEN_METADATA_STATES_QUERY
+        );
+        let now = env::block_timestamp();
+        metadata_ids
+            .iter()
+            .map(|metadata_id| {
+                self.token_metadata.get(&metadata_id.0).map(
+                    |minting_metadata| {
+                        let not_started = matches!(
+                            minting_metadata.starts_at,
+                            Some(start) if now < start
+                        );
+                        let expired = matches!(
+                            minting_metadata.expires_at,
+                            Some(expiry) if now > expiry
+                        );
+                        let sold_out = matches!(
+                            minting_metadata.max_supply,
+                            Some(max) if minting_metadata.minted >= max
+                        );
+                        MetadataStateJson {
+                            is_locked: minting_metadata.is_locked,
+                            minted: minting_metadata.minted,
+                            max_supply: minting_metadata.max_supply,
+                            price: minting_metadata.price.into(),
+                            mintable_now: !not_started && !expired && !sold_out,
+                        }
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Whether `account_id` has already used its mint on a metadata with
+    /// `unique_minters`. Returns `false` if the account is not on the
+    /// allowlist, has not minted yet, or the metadata does not exist.
+    pub fn has_minted(&self, metadata_id: U64, account_id: AccountId) -> bool {
+        self.token_metadata
+            .get(&metadata_id.0)
+            .and_then(|minting_metadata| minting_metadata.allowlist)
+            .map(|allowlist| allowlist.contains(&(account_id, true)))
+            .unwrap_or(false)
+    }
+
+    /// Shows how the primary sale proceeds of a metadata are split, in basis
+    /// points. `None` if the creator receives all proceeds.
+    pub fn get_primary_split(
+        &self,
+        metadata_id: U64,
+    ) -> Option<HashMap<AccountId, u16>> {
+        self.primary_splits
+            .get(&metadata_id.0)
+            .map(|split| split.to_unparsed())
+    }
+
+    /// Maximum number of tokens a single account may mint on this metadata,
+    /// `None` if not limited.
+    pub fn get_per_minter_cap(&self, metadata_id: U64) -> Option<u32> {
+        self.per_minter_caps.get(&metadata_id.0)
+    }
+
+    /// Number of tokens that `account_id` has minted on this metadata. Only
+    /// tracked if the metadata has a `per_minter_cap`.
+    pub fn get_minted_by_minter(
+        &self,
+        metadata_id: U64,
+        account_id: AccountId,
+    ) -> u32 {
+        self.minted_per_minter
+            .get(&(metadata_id.0, account_id))
+            .unwrap_or(0)
+    }
+
+    /// Whether tokens minted on this metadata are soulbound, i.e. cannot be
+    /// transferred or approved.
+    pub fn is_metadata_soulbound(&self, metadata_id: U64) -> bool {
+        self.soulbound_metadata.contains(&metadata_id.0)
+    }
+
+    /// Lists all royalty templates that can be used in `create_metadata`.
+    pub fn get_royalty_templates(&self) -> HashMap<String, Royalty> {
+        self.royalty_templates.iter().collect()
+    }
+
+    /// Show the global mint rate limit as `(count, window_seconds)`, if any.
+    pub fn get_global_mint_rate_limit(&self) -> Option<(u32, u64)> {
+        self.global_mint_rate_limit
+    }
+
+    /// Number of tokens minted within the current window of the global mint
+    /// rate limit. Always zero if no limit is set.
+    pub fn get_tokens_minted_in_range(&self) -> u32 {
+        self.recent_mints_in_window().iter().map(|(_, n)| n).sum()
+    }
+
     // -------------------------- private methods --------------------------
     // -------------------------- internal methods -------------------------
 
+    /// Get a royalty template, panicking if it doesn't exist.
+    fn get_royalty_template(&self, name: &String) -> Royalty {
+        match self.royalty_templates.get(name) {
+            Some(royalty) => royalty,
+            None => near_panic!("Royalty template {} does not exist", name),
+        }
+    }
+
+    /// The account that receives `MINTING_FEE`: the explicitly configured
+    /// `fee_recipient` if set, otherwise the parent account (factory).
+    pub(crate) fn minting_fee_recipient(&self) -> Option<AccountId> {
+        self.fee_recipient
+            .clone()
+            .or_else(|| parent_account_id(&env::current_account_id()))
+    }
+
+    /// Buckets of `recent_mints` that still overlap with the window of the
+    /// global mint rate limit, oldest first.
+    fn recent_mints_in_window(&self) -> Vec<(u64, u32)> {
+        let window_seconds = match self.global_mint_rate_limit {
+            None => return vec![],
+            Some((_, window_seconds)) => window_seconds,
+        };
+        let bucket_seconds = mint_rate_bucket_seconds(window_seconds);
+        let now = env::block_timestamp() / 1_000_000_000;
+        self.recent_mints
+            .iter()
+            .filter(|(start, _)| start + bucket_seconds + window_seconds > now)
+            .cloned()
+            .collect()
+    }
+
+    /// Panics if minting `num_to_mint` tokens would exceed the global mint
+    /// rate limit, otherwise records the mint in the current bucket.
+    fn record_rate_limited_mint(&mut self, num_to_mint: u32) {
+        let (max_mints, window_seconds) = match self.global_mint_rate_limit {
+            None => return,
+            Some(limit) => limit,
+        };
+        near_assert!(
+            num_to_mint <= max_mints,
+            "Cannot mint more than {} tokens per {} seconds",
+            max_mints,
+            window_seconds
+        );
+
+        let bucket_seconds = mint_rate_bucket_seconds(window_seconds);
+        let now = env::block_timestamp() / 1_000_000_000;
+        let mut recent_mints = self.recent_mints_in_window();
+        let minted: u32 = recent_mints.iter().map(|(_, n)| n).sum();
+        if minted + num_to_mint > max_mints {
+            // find the bucket after whose expiry enough mints are available
+            let mut to_free = minted + num_to_mint - max_mints;
+            let mut retry_at = now;
+            for (start, n) in recent_mints.iter() {
+                retry_at = start + bucket_seconds + window_seconds;
+                if *n >= to_free {
+                    break;
+                }
+                to_free -= n;
+            }
+            near_panic!(
+                "Global mint rate limit of {} tokens per {} seconds reached, retry in {} seconds",
+                max_mints,
+                window_seconds,
+                retry_at.saturating_sub(now).max(1)
+            );
+        }
+
+        let bucket_start = now / bucket_seconds * bucket_seconds;
+        match recent_mints.last_mut() {
+            Some((start, n)) if *start == bucket_start => *n += num_to_mint,
+            _ => recent_mints.push((bucket_start, num_to_mint)),
+        }
+        self.recent_mints = recent_mints;
+    }
+
     /// Ensure the mint is valid, parse data structures, fill in defaults
     fn preprocess_mint(
         &self,
@@ -465,6 +871,20 @@ impl MintbaseStore {
             );
         }
 
+        // check per-minter cap
+        if let Some(cap) = self.per_minter_caps.get(&metadata_id) {
+            let minted = self
+                .minted_per_minter
+                .get(&(metadata_id, minter_id.clone()))
+                .unwrap_or(0);
+            near_assert!(
+                minted + num_to_mint as u32 <= cap,
+                "{} may not mint more than {} tokens on this metadata",
+                minter_id,
+                cap
+            );
+        }
+
         // Cannot mint more than NFTs than the threshold for dynamic metadata,
         // as that would exceed the log limit when emitting the event
         near_assert!(
@@ -490,6 +910,24 @@ impl MintbaseStore {
 
         let split_owners = args.split_owners.map(SplitOwners::new);
 
+        // overrides need to match the minted tokens and have bounded size
+        if let Some(ref overrides) = args.per_token_overrides {
+            near_assert!(
+                overrides.len() == num_to_mint as usize,
+                "Expected {} per-token overrides, got {}",
+                num_to_mint,
+                overrides.len()
+            );
+            near_assert!(
+                overrides.iter().all(|o| {
+                    option_string_fits_override(&o.reference)
+                        && option_string_fits_override(&o.media)
+                }),
+                "Per-token overrides may not exceed {} characters",
+                MAX_LEN_TOKEN_OVERRIDE
+            );
+        }
+
         ProcessedMintingArgs {
             metadata_id,
             minting_metadata,
@@ -499,6 +937,7 @@ impl MintbaseStore {
             token_ids,
             num_splits,
             split_owners,
+            per_token_overrides: args.per_token_overrides,
         }
     }
 
@@ -509,6 +948,8 @@ impl MintbaseStore {
         mut args: ProcessedMintingArgs,
         amount: Balance,
     ) {
+        self.record_rate_limited_mint(args.num_to_mint as u32);
+
         // mint the tokens, store splits
         let royalty_id =
             match self.token_royalty.contains_key(&args.metadata_id) {
@@ -516,8 +957,10 @@ impl MintbaseStore {
                 false => None,
             };
         let mut owned_set = self.get_or_make_new_owner_set(&args.owner_id);
+        let has_cooldown =
+            self.transfer_cooldowns.contains_key(&args.metadata_id);
         self.tokens_minted += args.num_to_mint as u64;
-        for &id in args.token_ids.iter() {
+        for (i, &id) in args.token_ids.iter().enumerate() {
             let token = Token {
                 id,
                 owner_id: mb_sdk::data::store::Owner::Account(
@@ -538,9 +981,28 @@ impl MintbaseStore {
                 origin_key: None,
             };
             self.save_token(&token);
+            if has_cooldown {
+                self.tokens_minted_at
+                    .insert(&(args.metadata_id, id), &env::block_timestamp());
+            }
+            if let Some(token_override) = args
+                .per_token_overrides
+                .as_ref()
+                .map(|overrides| &overrides[i])
+                .filter(|o| !o.is_empty())
+            {
+                self.token_overrides
+                    .insert(&(args.metadata_id, id), token_override);
+            }
             owned_set.insert(&(args.metadata_id, id));
         }
         args.minting_metadata.minted += args.num_to_mint as u32;
+        if self.per_minter_caps.contains_key(&args.metadata_id) {
+            let key = (args.metadata_id, args.minter_id.clone());
+            let minted = self.minted_per_minter.get(&key).unwrap_or(0);
+            self.minted_per_minter
+                .insert(&key, &(minted + args.num_to_mint as u32));
+        }
         if args.minting_metadata.unique_minters {
             let mut allowlist: Vec<_> = args
                 .minting_metadata
@@ -570,6 +1032,13 @@ impl MintbaseStore {
             &args.minting_metadata.metadata.extra,
         );
 
+        // signal that the collection is sold out
+        if let Some(minting_cap) = self.minting_cap {
+            if self.tokens_minted == minting_cap {
+                log_minting_cap_reached(minting_cap);
+            }
+        }
+
         // payout for creator(s) and minting fee
         self.minting_payout(
             args.metadata_id,
@@ -579,6 +1048,42 @@ impl MintbaseStore {
         );
     }
 
+    /// Credits `amount` to the storage deposit of `account_id`. Creating the
+    /// entry costs `storage_stake::COMMON`, which is subtracted from the first
+    /// deposit.
+    pub(crate) fn deposit_storage_for_account(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+    ) {
+        let new_deposit = match self.storage_deposit_by_account.get(account_id)
+        {
+            // subtract common for entry creation
+            None => amount - mb_sdk::constants::storage_stake::COMMON,
+            Some(old_deposit) => old_deposit + amount,
+        };
+        self.storage_deposit_by_account
+            .insert(account_id, &new_deposit);
+    }
+
+    /// Credits `amount` to the sponsored mints of `metadata_id`. Creating the
+    /// entry costs `storage_stake::COMMON`, which is subtracted from the first
+    /// deposit.
+    fn deposit_storage_for_metadata(
+        &mut self,
+        metadata_id: u64,
+        amount: Balance,
+    ) {
+        let new_deposit =
+            match self.storage_deposit_by_metadata.get(&metadata_id) {
+                // subtract common for entry creation
+                None => amount - mb_sdk::constants::storage_stake::COMMON,
+                Some(old_deposit) => old_deposit + amount,
+            };
+        self.storage_deposit_by_metadata
+            .insert(&metadata_id, &new_deposit);
+    }
+
     /// Subtract the storage deposit from either sponsored mints per metadata
     /// (preferential) or predeposited storage by the user.
     fn subtract_storage_deposit(
@@ -617,11 +1122,16 @@ impl MintbaseStore {
     /// Get the storage in bytes to create metadata each with
     /// `metadata_storage` and `len_map` royalty receivers.
     /// Internal
+    #[allow(clippy::too_many_arguments)]
     fn storage_cost_to_create_metadata(
         &self,
         metadata_storage: StorageUsage,
         num_royalties: u32,
         num_minters: u64,
+        has_cooldown: bool,
+        num_primary_splits: u32,
+        has_per_minter_cap: bool,
+        is_soulbound: bool,
     ) -> near_sdk::Balance {
         // - metadata_storage
         // - minters allowlist: account_id * length
@@ -642,6 +1152,14 @@ impl MintbaseStore {
             + self.storage_costs.common
             // price, burned, minted, max_supply, expiry, is_locked
             + self.storage_costs.common
+            // store the transfer cooldown
+            + has_cooldown as u128 * self.storage_costs.common
+            // store the primary sale splits
+            + num_primary_splits as u128 * self.storage_costs.common
+            // store the per-minter cap
+            + has_per_minter_cap as u128 * self.storage_costs.common
+            // store the soulbound flag
+            + is_soulbound as u128 * self.storage_costs.common
     }
 
     /// Get the storage in bytes to mint `num_tokens` each with
@@ -649,18 +1167,41 @@ impl MintbaseStore {
     /// Internal
     fn storage_cost_to_mint(
         &self,
-        num_tokens: u16,
-        num_splits: u32,
+        args: &ProcessedMintingArgs,
     ) -> near_sdk::Balance {
-        num_tokens as u128
+        let has_cooldown =
+            self.transfer_cooldowns.contains_key(&args.metadata_id);
+        // first mint of an account on capped metadata creates its counter
+        let creates_minter_counter =
+            self.per_minter_caps.contains_key(&args.metadata_id)
+                && !self
+                    .minted_per_minter
+                    .contains_key(&(args.metadata_id, args.minter_id.clone()));
+        let overrides_cost: u128 = args
+            .per_token_overrides
+            .iter()
+            .flatten()
+            .filter(|o| !o.is_empty())
+            .map(|o| {
+                // entry in token_overrides plus the override strings
+                self.storage_costs.common
+                    + o.try_to_vec().unwrap().len() as u128
+                        * self.storage_costs.storage_price_per_byte
+            })
+            .sum();
+        args.num_to_mint as u128
             * (
                 // token base storage
                 self.storage_costs.token
                 // dynamic split storage
-                + num_splits as u128 * self.storage_costs.common
+                + args.num_splits as u128 * self.storage_costs.common
                 // create an entry in tokens_per_owner
                 + self.storage_costs.common
+                // store the mint timestamp for the transfer cooldown
+                + has_cooldown as u128 * self.storage_costs.common
             )
+            + overrides_cost
+            + creates_minter_counter as u128 * self.storage_costs.common
     }
 
     fn get_metadata_id(&mut self, metadata_id: Option<U64>) -> u64 {
@@ -672,14 +1213,20 @@ impl MintbaseStore {
                 metadata_id
             }
             None => {
-                while self.token_metadata.contains_key(&self.metadata_id) {
-                    self.metadata_id += 1;
-                }
+                self.metadata_id = self.next_free_metadata_id();
                 self.metadata_id
             }
         }
     }
 
+    fn next_free_metadata_id(&self) -> u64 {
+        let mut metadata_id = self.metadata_id;
+        while self.token_metadata.contains_key(&metadata_id) {
+            metadata_id += 1;
+        }
+        metadata_id
+    }
+
     fn get_token_ids(
         &self,
         metadata_id: u64,
@@ -755,9 +1302,9 @@ impl MintbaseStore {
         mut balance: u128,
         creator: AccountId,
     ) {
-        // pay minting fee to parent account
-        if let Some(factory) = parent_account_id(&env::current_account_id()) {
-            Promise::new(factory).transfer(MINTING_FEE);
+        // pay minting fee to configured recipient or parent account
+        if let Some(recipient) = self.minting_fee_recipient() {
+            Promise::new(recipient).transfer(MINTING_FEE);
         }
 
         // pay out royalty holders
@@ -773,6 +1320,21 @@ impl MintbaseStore {
             balance -= royalties_total;
         }
 
+        // pay out primary sale splits, rounding remainder goes to the creator
+        if let Some(primary_split) = self.primary_splits.get(&metadata_id) {
+            let mut remainder = balance;
+            for (account_id, percentage) in primary_split.split_between.iter() {
+                let amount = percentage.multiply_balance(balance);
+                payment_method
+                    .create_payment_promise(account_id.to_owned(), amount);
+                remainder -= amount;
+            }
+            balance = remainder;
+            if balance == 0 {
+                return;
+            }
+        }
+
         // rest goes to the creator
         payment_method.create_payment_promise(creator, balance);
     }
@@ -798,6 +1360,7 @@ pub struct MintingArgs {
     num_to_mint: Option<u16>,
     token_ids: Option<Vec<U64>>,
     split_owners: Option<SplitBetweenUnparsed>,
+    per_token_overrides: Option<Vec<TokenMetadataOverride>>,
 }
 
 struct ProcessedMintingArgs {
@@ -809,6 +1372,19 @@ struct ProcessedMintingArgs {
     token_ids: Vec<u64>,
     num_splits: u32,
     split_owners: Option<SplitOwners>,
+    per_token_overrides: Option<Vec<TokenMetadataOverride>>,
+}
+
+/// Length of a bucket of the global mint rate limit in seconds.
+fn mint_rate_bucket_seconds(window_seconds: u64) -> u64 {
+    (window_seconds / MINT_RATE_LIMIT_BUCKETS).max(1)
+}
+
+fn option_string_fits_override(opt_s: &Option<String>) -> bool {
+    opt_s
+        .as_ref()
+        .map(|s| s.len() <= MAX_LEN_TOKEN_OVERRIDE)
+        .unwrap_or(true)
 }
 
 fn option_string_is_u64(opt_s: &Option<String>) -> bool {
@@ -822,6 +1398,8 @@ fn log_create_metadata(
     metadata_id: u64,
     minting_metadata: MintingMetadata,
     royalty: Option<Royalty>,
+    per_minter_cap: Option<u32>,
+    soulbound: bool,
 ) {
     env::log_str(
         CreateMetadataData {
@@ -841,6 +1419,8 @@ fn log_create_metadata(
             starts_at: minting_metadata.starts_at.map(Into::into),
             expires_at: minting_metadata.expires_at.map(Into::into),
             is_locked: minting_metadata.is_locked,
+            per_minter_cap,
+            soulbound,
         }
         .serialize_event()
         .as_str(),
@@ -873,6 +1453,10 @@ fn log_nft_batch_mint(
     env::log_str(log.serialize_event().as_str());
 }
 
+pub(crate) fn log_minting_cap_reached(cap: u64) {
+    env::log_str(&MintingCapReachedData { cap: cap.into() }.serialize_event());
+}
+
 pub(crate) fn log_grant_creator(account_id: &AccountId) {
     env::log_str(
         &MbStoreChangeSettingDataV020 {
@@ -893,17 +1477,6 @@ pub(crate) fn log_revoke_creator(account_id: &AccountId) {
     );
 }
 
-fn parent_account_id(child: &AccountId) -> Option<AccountId> {
-    child
-        .as_str()
-        .split_once('.')
-        .unwrap()
-        .1
-        .to_string()
-        .try_into()
-        .ok()
-}
-
 pub(crate) fn validate_metadata(metadata: &TokenMetadata) {
     near_assert!(
         option_string_is_u64(&metadata.starts_at),
@@ -1,7 +1,12 @@
 use mb_sdk::{
     constants::StorageCostsJson,
-    events::store::MbStoreChangeSettingDataV020,
+    events::store::{
+        MbStoreChangeSettingDataV020,
+        OwnershipProposalCancelledData,
+        OwnershipProposedData,
+    },
     near_assert,
+    near_panic,
     near_sdk::{
         self,
         assert_one_yocto,
@@ -14,6 +19,7 @@ use mb_sdk::{
 use crate::{
     minting::{
         log_grant_creator,
+        log_minting_cap_reached,
         log_revoke_creator,
     },
     *,
@@ -42,17 +48,63 @@ impl MintbaseStore {
             "{} already owns this store",
             new_owner
         );
-        if !keep_old_creators {
-            for creator in self.creators.iter() {
-                log_revoke_creator(&creator);
+        self.pending_owner = None;
+        self.transfer_store_ownership_internal(new_owner, keep_old_creators);
+    }
+
+    /// Propose a new owner for this `Store`. Ownership is only transferred
+    /// once the proposed account calls `accept_ownership`, which prevents
+    /// handing the store to an account that nobody controls. The
+    /// `keep_old_creators` setting is applied on acceptance, see
+    /// `transfer_store_ownership`.
+    ///
+    /// Only the store owner may call this function.
+    #[payable]
+    pub fn propose_owner(
+        &mut self,
+        new_owner: AccountId,
+        keep_old_creators: bool,
+    ) {
+        self.assert_store_owner();
+        near_assert!(
+            new_owner != self.owner_id,
+            "{} already owns this store",
+            new_owner
+        );
+        log_ownership_proposed(&self.owner_id, &new_owner);
+        self.pending_owner = Some((new_owner, keep_old_creators));
+    }
+
+    /// Accept a pending ownership proposal.
+    ///
+    /// Only the proposed owner may call this function.
+    #[payable]
+    pub fn accept_ownership(&mut self) {
+        assert_one_yocto();
+        let (new_owner, keep_old_creators) = match self.pending_owner.take() {
+            Some((pending_owner, keep_old_creators))
+                if pending_owner == env::predecessor_account_id() =>
+            {
+                (pending_owner, keep_old_creators)
             }
-            self.creators.clear();
-        }
-        log_grant_creator(&new_owner);
-        // add the new_owner to the creator set (insert does nothing if they already are a minter).
-        self.creators.insert(&new_owner);
-        log_transfer_store(&new_owner);
-        self.owner_id = new_owner;
+            _ => near_panic!(
+                "This method can only be called by the pending store owner"
+            ),
+        };
+        self.transfer_store_ownership_internal(new_owner, keep_old_creators);
+    }
+
+    /// Cancel a pending ownership proposal.
+    ///
+    /// Only the store owner may call this function.
+    #[payable]
+    pub fn cancel_ownership_proposal(&mut self) {
+        self.assert_store_owner();
+        let (pending_owner, _) = match self.pending_owner.take() {
+            None => near_panic!("There is no pending ownership proposal"),
+            Some(pending) => pending,
+        };
+        log_ownership_proposal_cancelled(&self.owner_id, &pending_owner);
     }
 
     /// Owner of this `Store` may call to withdraw Near deposited onto
@@ -86,7 +138,8 @@ impl MintbaseStore {
     #[payable]
     pub fn set_storage_price_per_byte(&mut self, new_price: U128) {
         self.assert_store_owner();
-        self.storage_costs = StorageCosts::new(new_price.into())
+        self.storage_costs = StorageCosts::new(new_price.into());
+        log_storage_price_per_byte(new_price);
     }
 
     /// Remove a key from the smart contract.
@@ -96,6 +149,7 @@ impl MintbaseStore {
     pub fn del_key(&mut self, key: String) -> Promise {
         self.assert_store_owner();
         let key: near_sdk::PublicKey = key.parse().expect("Cannot parse key");
+        log_deleted_access_key(&key);
         Promise::new(env::current_account_id()).delete_key(key)
     }
 
@@ -106,6 +160,7 @@ impl MintbaseStore {
     pub fn add_key(&mut self, key: String) -> Promise {
         self.assert_store_owner();
         let key: near_sdk::PublicKey = key.parse().expect("Cannot parse key");
+        log_added_access_key(&key);
         Promise::new(env::current_account_id()).add_full_access_key(key)
     }
 
@@ -125,6 +180,49 @@ impl MintbaseStore {
         log_minting_cap(minting_cap);
     }
 
+    /// Limit the number of tokens that can be minted across all metadata to
+    /// `count` within any `window_seconds`, passed as a tuple. Setting this to
+    /// `None` lifts the limit.
+    #[payable]
+    pub fn set_global_mint_rate_limit(
+        &mut self,
+        global_mint_rate_limit: Option<(u32, u64)>,
+    ) {
+        self.assert_store_owner();
+        if let Some((count, window_seconds)) = global_mint_rate_limit {
+            near_assert!(
+                count > 0 && window_seconds > 0,
+                "Mint rate limit count and window must be positive"
+            );
+        } else {
+            self.recent_mints.clear();
+        }
+        self.global_mint_rate_limit = global_mint_rate_limit;
+        log_global_mint_rate_limit(global_mint_rate_limit);
+    }
+
+    /// Lower the maximum number of minted tokens on this contract, e.g. to end
+    /// a drop early. The new cap may not be lower than the number of already
+    /// minted tokens, and raising the cap is not allowed.
+    #[payable]
+    pub fn reduce_minting_cap(&mut self, new_cap: u64) {
+        self.assert_store_owner();
+        let minting_cap = match self.minting_cap {
+            None => near_panic!("Minting cap has not been set"),
+            Some(cap) => cap,
+        };
+        near_assert!(new_cap < minting_cap, "Minting cap can only be lowered");
+        near_assert!(
+            self.tokens_minted <= new_cap,
+            "Cannot set minting cap lower than already minted tokens"
+        );
+        self.minting_cap = Some(new_cap);
+        log_minting_cap(new_cap);
+        if self.tokens_minted == new_cap {
+            log_minting_cap_reached(new_cap);
+        }
+    }
+
     /// Set maximum number of minted tokens on this contract
     #[payable]
     pub fn set_open_creating(&mut self, allow: bool) {
@@ -139,12 +237,135 @@ impl MintbaseStore {
         log_open_creating(allow);
     }
 
+    /// Irreversibly disable `repair_reference` for all metadata on this
+    /// contract, for collections that require absolute immutability.
+    #[payable]
+    pub fn disable_reference_repairs(&mut self) {
+        self.assert_store_owner();
+        near_assert!(
+            self.allow_reference_repairs,
+            "Reference repairs are already disabled"
+        );
+        self.allow_reference_repairs = false;
+        log_allow_reference_repairs(false);
+    }
+
+    /// Set the account that receives `MINTING_FEE`, overriding the parent
+    /// account. Only the current fee recipient may redirect the fee. If there
+    /// is none, e.g. for stores deployed to top-level accounts, the store
+    /// owner may set it.
+    #[payable]
+    pub fn set_fee_recipient(&mut self, fee_recipient: AccountId) {
+        assert_one_yocto();
+        let caller = env::predecessor_account_id();
+        match self.minting_fee_recipient() {
+            Some(current) => near_assert!(
+                caller == current,
+                "This method can only be called by the current fee recipient"
+            ),
+            None => near_assert!(
+                caller == self.owner_id,
+                "This method can only be called by the store owner"
+            ),
+        }
+        log_fee_recipient(&fee_recipient);
+        self.fee_recipient = Some(fee_recipient);
+    }
+
+    /// Require a fee of `transfer_fee.1` yoctoNEAR per token on every
+    /// transfer except for sales via `nft_transfer_payout`, which is
+    /// forwarded to `transfer_fee.0`. Setting this to `None` removes the fee.
+    ///
+    /// Only the store owner may call this function.
+    #[payable]
+    pub fn set_transfer_fee(
+        &mut self,
+        transfer_fee: Option<(AccountId, U128)>,
+    ) {
+        self.assert_store_owner();
+        log_transfer_fee(&transfer_fee);
+        self.transfer_fee = transfer_fee
+            .map(|(fee_recipient, amount)| (fee_recipient, amount.0));
+    }
+
+    /// Set a mint window as `(starts_at, expires_at)` in nanoseconds, which
+    /// is applied to newly created metadata that omits `starts_at` or
+    /// `expires_at`. Metadata that only sets one of them must still end up
+    /// with a valid window. Existing metadata is not affected. Setting this
+    /// to `None` removes the default.
+    ///
+    /// Only the store owner may call this function.
+    #[payable]
+    pub fn set_default_mint_window(
+        &mut self,
+        default_mint_window: Option<(U64, U64)>,
+    ) {
+        self.assert_store_owner();
+        if let Some((starts_at, expires_at)) = default_mint_window {
+            near_assert!(
+                starts_at.0 < expires_at.0,
+                "Mint window must start before it expires"
+            );
+        }
+        self.default_mint_window = default_mint_window
+            .map(|(starts_at, expires_at)| (starts_at.0, expires_at.0));
+        log_default_mint_window(default_mint_window);
+    }
+
+    /// Set an account that acts as burn sink. Tokens that are sent to this
+    /// account via `nft_transfer` are burned instead of being transferred,
+    /// emitting an `nft_burn` event. This prevents tokens from being stranded
+    /// by integrations that burn by transferring to an account that nobody
+    /// controls. All other transfer methods refuse the burn sink as receiver.
+    /// Setting this to `None` disables the sink.
+    ///
+    /// Only the store owner may call this function.
+    #[payable]
+    pub fn set_burn_sink_account(&mut self, account_id: Option<AccountId>) {
+        self.assert_store_owner();
+        log_burn_sink_account(&account_id);
+        self.burn_sink_account = account_id;
+    }
+
     // -------------------------- view methods -----------------------------
     /// Show the current owner of this NFT contract
     pub fn get_owner_id(&self) -> AccountId {
         self.owner_id.clone()
     }
 
+    /// Show the account that has been proposed as new owner, if any
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner
+            .as_ref()
+            .map(|(account_id, _)| account_id.clone())
+    }
+
+    /// Show the account that receives the minting fee, if any
+    pub fn get_fee_recipient(&self) -> Option<AccountId> {
+        self.minting_fee_recipient()
+    }
+
+    /// Show the fee that needs to be attached to transfers and the account
+    /// receiving it, if any
+    pub fn get_transfer_fee(&self) -> Option<(AccountId, U128)> {
+        self.transfer_fee.as_ref().map(|(fee_recipient, amount)| {
+            (fee_recipient.clone(), (*amount).into())
+        })
+    }
+
+    /// Show the default mint window for new metadata as
+    /// `(starts_at, expires_at)` in nanoseconds, if any
+    pub fn get_default_mint_window(&self) -> Option<(U64, U64)> {
+        self.default_mint_window.map(|(starts_at, expires_at)| {
+            (starts_at.into(), expires_at.into())
+        })
+    }
+
+    /// Show the account to which transferred tokens are burned, if any
+    pub fn get_burn_sink_account(&self) -> Option<AccountId> {
+        self.burn_sink_account.clone()
+    }
+
     /// Show the current owner of this NFT contract
     pub fn get_storage_costs(&self) -> StorageCostsJson {
         (&self.storage_costs).into()
@@ -153,6 +374,26 @@ impl MintbaseStore {
     // -------------------------- private methods --------------------------
     // -------------------------- internal methods -------------------------
 
+    /// Hand the store over to `new_owner`, optionally revoking all existing
+    /// creators.
+    fn transfer_store_ownership_internal(
+        &mut self,
+        new_owner: AccountId,
+        keep_old_creators: bool,
+    ) {
+        if !keep_old_creators {
+            for creator in self.creators.iter() {
+                log_revoke_creator(&creator);
+            }
+            self.creators.clear();
+        }
+        log_grant_creator(&new_owner);
+        // add the new_owner to the creator set (insert does nothing if they already are a minter).
+        self.creators.insert(&new_owner);
+        log_transfer_store(&new_owner);
+        self.owner_id = new_owner;
+    }
+
     /// Validate the caller of this method matches the owner of this `Store`.
     pub(crate) fn assert_store_owner(&self) {
         assert_one_yocto();
@@ -192,3 +433,120 @@ fn log_minting_cap(cap: u64) {
         .serialize_event(),
     );
 }
+
+fn log_storage_price_per_byte(price: U128) {
+    env::log_str(
+        &MbStoreChangeSettingDataV020 {
+            new_storage_price_per_byte: Some(price),
+            ..MbStoreChangeSettingDataV020::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_added_access_key(key: &near_sdk::PublicKey) {
+    env::log_str(
+        &MbStoreChangeSettingDataV020 {
+            added_access_key: Some(key.into()),
+            ..MbStoreChangeSettingDataV020::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_deleted_access_key(key: &near_sdk::PublicKey) {
+    env::log_str(
+        &MbStoreChangeSettingDataV020 {
+            deleted_access_key: Some(key.into()),
+            ..MbStoreChangeSettingDataV020::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_fee_recipient(account_id: &AccountId) {
+    env::log_str(
+        &MbStoreChangeSettingDataV020 {
+            new_fee_recipient: Some(account_id.to_string()),
+            ..MbStoreChangeSettingDataV020::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_allow_reference_repairs(allow: bool) {
+    env::log_str(
+        &MbStoreChangeSettingDataV020 {
+            allow_reference_repairs: Some(allow),
+            ..MbStoreChangeSettingDataV020::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_global_mint_rate_limit(limit: Option<(u32, u64)>) {
+    env::log_str(
+        &MbStoreChangeSettingDataV020 {
+            new_global_mint_rate_limit: Some(limit),
+            ..MbStoreChangeSettingDataV020::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_transfer_fee(transfer_fee: &Option<(AccountId, U128)>) {
+    env::log_str(
+        &MbStoreChangeSettingDataV020 {
+            new_transfer_fee: Some(transfer_fee.as_ref().map(
+                |(fee_recipient, amount)| (fee_recipient.to_string(), *amount),
+            )),
+            ..MbStoreChangeSettingDataV020::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_default_mint_window(window: Option<(U64, U64)>) {
+    env::log_str(
+        &MbStoreChangeSettingDataV020 {
+            new_default_mint_window: Some(window),
+            ..MbStoreChangeSettingDataV020::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_burn_sink_account(account_id: &Option<AccountId>) {
+    env::log_str(
+        &MbStoreChangeSettingDataV020 {
+            new_burn_sink_account: Some(
+                account_id.as_ref().map(|a| a.to_string()),
+            ),
+            ..MbStoreChangeSettingDataV020::empty()
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_ownership_proposed(owner_id: &AccountId, pending_owner_id: &AccountId) {
+    env::log_str(
+        &OwnershipProposedData {
+            owner_id: owner_id.clone(),
+            pending_owner_id: pending_owner_id.clone(),
+        }
+        .serialize_event(),
+    );
+}
+
+fn log_ownership_proposal_cancelled(
+    owner_id: &AccountId,
+    pending_owner_id: &AccountId,
+) {
+    env::log_str(
+        &OwnershipProposalCancelledData {
+            owner_id: owner_id.clone(),
+            pending_owner_id: pending_owner_id.clone(),
+        }
+        .serialize_event(),
+    );
+}
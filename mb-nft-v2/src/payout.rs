@@ -46,7 +46,9 @@ impl MintbaseStore {
     ) -> Payout {
         assert_one_yocto();
         let payout = self.nft_payout(token_id.clone(), balance, max_len_payout);
-        self.nft_transfer(receiver_id, token_id, approval_id, memo);
+        // sales are exempt from the transfer fee, as markets only attach one
+        // yoctoNEAR
+        self.nft_transfer_internal(receiver_id, token_id, approval_id, memo);
         payout
     }
 
@@ -95,10 +97,6 @@ impl MintbaseStore {
         split_between: SplitBetweenUnparsed,
     ) {
         near_assert!(!token_ids.is_empty(), "Requires token IDs");
-        // near_assert!(
-        //     split_between.len() >= 2,
-        //     "Requires at least two accounts to split between"
-        // );
         assert_storage_deposit!(
             (self.storage_costs.common * split_between.len() as u128)
                 * token_ids.len() as u128
@@ -131,7 +129,7 @@ impl MintbaseStore {
             token.split_owners = Some(splits.clone());
             self.save_token(&token);
         });
-        log_set_split_owners(token_ids, splits);
+        log_set_split_owners(token_ids, &splits);
     }
 
     // -------------------------- view methods -----------------------------
@@ -140,13 +138,15 @@ impl MintbaseStore {
     /// token, as this would lead to duplication of `Royalty`s across tokens.
     /// Instead, the `Royalty` is stored in a Contract `LookupMap`.
     pub fn get_token_royalty(&self, token_id: String) -> Option<Royalty> {
+        self.nft_token_royalty(token_id)
+    }
+
+    /// Get the Royalty for a Token, as stored for the metadata that the token
+    /// has been minted on. Returns `None` if the metadata has no royalties.
+    pub fn nft_token_royalty(&self, token_id: String) -> Option<Royalty> {
         let token_id = parse_token_id(&token_id);
-        // FIXME: needs to be metadata_id, but this function will have to change
-        let royalty_id = self.nft_token_internal(token_id).royalty_id;
-        match royalty_id {
-            Some(id) => self.token_royalty.get(&id),
-            None => None,
-        }
+        let metadata_id = self.nft_token_internal(token_id).metadata_id;
+        self.token_royalty.get(&metadata_id)
     }
 
     // -------------------------- private methods --------------------------
@@ -250,16 +250,12 @@ impl OwnershipFractions {
 
 pub(crate) fn log_set_split_owners(
     token_ids: Vec<String>,
-    mut split_owners: SplitOwners,
+    split_owners: &SplitOwners,
 ) {
     env::log_str(
         &mb_sdk::events::store::NftSetSplitOwnerData {
             token_ids,
-            split_owners: split_owners
-                .split_between
-                .drain()
-                .map(|(acc, fraction)| (acc, fraction.numerator as u16))
-                .collect(),
+            split_owners: split_owners.to_unparsed(),
         }
         .serialize_event(),
     );
@@ -0,0 +1,131 @@
+use mb_sdk::{
+    constants::{
+        storage_stake,
+        MINTING_FEE,
+    },
+    data::store::{
+        StorageBalance,
+        StorageBalanceBounds,
+    },
+    near_assert,
+    near_sdk::{
+        self,
+        assert_one_yocto,
+        env,
+        json_types::U128,
+        near_bindgen,
+        AccountId,
+        Balance,
+        Promise,
+    },
+};
+
+use crate::*;
+
+// ------------------- standardized storage management ---------------------- //
+// The storage deposits managed here are the same that `deposit_storage` uses
+// for minting. Sponsored deposits for a metadata are not covered, as NEP-145
+// only knows accounts.
+#[near_bindgen]
+impl MintbaseStore {
+    // -------------------------- change methods ---------------------------
+    /// Deposit storage for minting as specified by
+    /// [NEP-145](https://nomicon.io/Standards/StorageManagement). With
+    /// `registration_only`, only the minimum deposit is kept and any excess
+    /// (or the full deposit for registered accounts) is refunded.
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let account_id = account_id.unwrap_or(env::predecessor_account_id());
+        let mut amount = env::attached_deposit();
+        let is_registered =
+            self.storage_deposit_by_account.contains_key(&account_id);
+        let min = self.storage_balance_min();
+        near_assert!(
+            is_registered || amount >= min,
+            "The attached deposit is less than the minimum storage balance of {} yoctoNEAR",
+            min
+        );
+
+        if registration_only.unwrap_or(false) {
+            let refund = if is_registered { amount } else { amount - min };
+            if refund > 0 {
+                Promise::new(env::predecessor_account_id()).transfer(refund);
+            }
+            amount -= refund;
+        }
+        if amount > 0 {
+            self.deposit_storage_for_account(&account_id, amount);
+        }
+
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    /// Withdraw unused storage deposit as specified by
+    /// [NEP-145](https://nomicon.io/Standards/StorageManagement). Without
+    /// `amount`, the whole available balance is withdrawn.
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let available = match self.storage_deposit_by_account.get(&account_id) {
+            None => near_panic!("{} has no storage deposit", account_id),
+            Some(deposit) => deposit,
+        };
+        let amount = amount.map(|a| a.0).unwrap_or(available);
+        near_assert!(
+            amount <= available,
+            "Cannot withdraw more than the available {} yoctoNEAR",
+            available
+        );
+
+        if amount > 0 {
+            self.storage_deposit_by_account
+                .insert(&account_id, &(available - amount));
+            Promise::new(account_id.clone()).transfer(amount);
+        }
+
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    // -------------------------- view methods -----------------------------
+    /// Storage balance of `account_id` as specified by
+    /// [NEP-145](https://nomicon.io/Standards/StorageManagement). All of the
+    /// deposit is available, as minting subtracts its storage costs directly.
+    pub fn storage_balance_of(
+        &self,
+        account_id: AccountId,
+    ) -> Option<StorageBalance> {
+        self.storage_deposit_by_account
+            .get(&account_id)
+            .map(|deposit| StorageBalance {
+                total: deposit.into(),
+                available: deposit.into(),
+            })
+    }
+
+    /// Storage balance bounds as specified by
+    /// [NEP-145](https://nomicon.io/Standards/StorageManagement). The minimum
+    /// covers the registration and minting a single token without splits.
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: self.storage_balance_min().into(),
+            max: None,
+        }
+    }
+
+    // -------------------------- internal methods -------------------------
+    fn storage_balance_min(&self) -> Balance {
+        // entry in `storage_deposit_by_account`
+        storage_stake::COMMON
+            // token base storage and entry in tokens_per_owner
+            + self.storage_costs.token
+            + self.storage_costs.common
+            + MINTING_FEE
+            // minting requires the deposit to exceed its costs
+            + 1
+    }
+}
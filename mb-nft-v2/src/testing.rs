@@ -0,0 +1,38 @@
+use mb_sdk::{
+    data::store::{
+        Owner,
+        Token,
+    },
+    near_assert,
+    near_sdk::{
+        self,
+        near_bindgen,
+    },
+};
+
+use crate::*;
+
+#[near_bindgen]
+impl MintbaseStore {
+    /// Inserts a token without going through the minting flow, e.g. to test
+    /// edge cases of payouts. The metadata referenced by the token must exist,
+    /// but no other checks are performed, no payments are required, and no
+    /// events are emitted. Only the store itself can call this.
+    #[private]
+    pub fn debug_seed_token(&mut self, token: Token) {
+        near_assert!(
+            self.tokens.contains_key(&token.metadata_id),
+            "Metadata {} does not exist",
+            token.metadata_id
+        );
+        if let Owner::Account(owner_id) = &token.owner_id {
+            self.update_tokens_per_owner(
+                token.id_tuple(),
+                None,
+                Some(owner_id.clone()),
+            );
+        }
+        self.tokens_minted += 1;
+        self.save_token(&token);
+    }
+}
@@ -1,12 +1,25 @@
 use mb_sdk::{
-    data::store::TokenMetadata,
+    assert_storage_deposit,
+    constants::MAX_LEN_REPAIRED_REFERENCE,
+    data::store::{
+        MintingPayment,
+        TokenMetadata,
+    },
     events::store::{
         MintingMetadataUpdateData,
         NftMetadataUpdateLog,
+        RepairReferenceData,
     },
     near_sdk::{
         self,
+        json_types::{
+            Base64VecU8,
+            U128,
+        },
         near_bindgen,
+        Balance,
+        Promise,
+        PromiseOrValue,
     },
 };
 
@@ -78,12 +91,173 @@ impl MintbaseStore {
         // Emit event
         log_token_lock(metadata_id.0);
     }
+
+    /// Allows the creator to fix a broken `reference` (e.g. after an IPFS
+    /// gateway migration), even if the metadata is locked. All other fields
+    /// of the metadata remain immutable. This may be disabled by the store
+    /// owner via `disable_reference_repairs`.
+    ///
+    /// As no storage deposit is required, `new_reference` is limited to
+    /// `MAX_LEN_REPAIRED_REFERENCE` bytes and `new_reference_hash` must be a
+    /// sha256 hash. Emits a single `repair_reference` event for the metadata
+    /// rather than `nft_metadata_update` for all of its tokens.
+    #[payable]
+    pub fn repair_reference(
+        &mut self,
+        metadata_id: U64,
+        new_reference: String,
+        new_reference_hash: Base64VecU8,
+    ) {
+        // Get metadata: needs to exist
+        let mut minting_metadata = self.get_minting_metadata(metadata_id.0);
+
+        // Only creator of metadata is allowed to repair it (require yoctoNEAR deposit)
+        near_sdk::assert_one_yocto();
+        near_assert!(
+            minting_metadata.creator == env::predecessor_account_id(),
+            "This method can only be called by the metadata creator"
+        );
+
+        // Store must allow repairs
+        near_assert!(
+            self.allow_reference_repairs,
+            "Reference repairs are disabled on this contract"
+        );
+
+        // Storage growth must remain bounded
+        near_assert!(
+            new_reference.len() <= MAX_LEN_REPAIRED_REFERENCE,
+            "Reference cannot exceed {} bytes",
+            MAX_LEN_REPAIRED_REFERENCE
+        );
+        near_assert!(
+            new_reference_hash.0.len() == 32,
+            "Reference hash must be a sha256 hash"
+        );
+
+        // Update the reference
+        minting_metadata.metadata.reference = Some(new_reference.clone());
+        minting_metadata.metadata.reference_hash =
+            Some(new_reference_hash.clone());
+        self.token_metadata
+            .insert(&metadata_id.0, &minting_metadata);
+
+        log_repair_reference(metadata_id, new_reference, new_reference_hash);
+    }
+
+    /// Allows the creator to set the FT contract in which they would prefer to
+    /// receive royalties for tokens of this metadata, or to remove the
+    /// preference by passing `None`. This is a hint for markets and not
+    /// enforced by the contract. Setting a new hint requires a storage
+    /// deposit, which is refunded to the creator once the hint is removed.
+    #[payable]
+    pub fn set_royalty_currency_hint(
+        &mut self,
+        metadata_id: U64,
+        ft_contract_id: Option<AccountId>,
+    ) -> PromiseOrValue<()> {
+        // Get metadata: needs to exist
+        let minting_metadata = self.get_minting_metadata(metadata_id.0);
+
+        // Only creator of metadata is allowed to set it
+        near_assert!(
+            minting_metadata.creator == env::predecessor_account_id(),
+            "This method can only be called by the metadata creator"
+        );
+
+        match ft_contract_id {
+            Some(ft_contract_id) => {
+                // replacing an existing hint requires no additional storage
+                match self
+                    .royalty_currency_hints
+                    .insert(&metadata_id.0, &ft_contract_id)
+                {
+                    None => {
+                        assert_storage_deposit!(self.storage_costs.common)
+                    }
+                    Some(_) => near_sdk::assert_one_yocto(),
+                }
+                PromiseOrValue::Value(())
+            }
+            None => {
+                near_sdk::assert_one_yocto();
+                match self.royalty_currency_hints.remove(&metadata_id.0) {
+                    Some(_) => PromiseOrValue::Promise(
+                        Promise::new(env::predecessor_account_id())
+                            .transfer(self.storage_costs.common),
+                    ),
+                    None => PromiseOrValue::Value(()),
+                }
+            }
+        }
+    }
+
+    /// Allows the creator to switch the currency in which the minting price is
+    /// paid (NEAR if `ft_contract_id` is `None`), and optionally to change the
+    /// price along with it. This is only possible before the first token has
+    /// been minted on this metadata.
+    #[payable]
+    pub fn set_metadata_payment(
+        &mut self,
+        metadata_id: U64,
+        ft_contract_id: Option<AccountId>,
+        price: Option<U128>,
+    ) {
+        // Get metadata: needs to exist
+        let mut minting_metadata = self.get_minting_metadata(metadata_id.0);
+
+        // Only creator of metadata is allowed to change it (require yoctoNEAR deposit)
+        near_sdk::assert_one_yocto();
+        near_assert!(
+            minting_metadata.creator == env::predecessor_account_id(),
+            "This method can only be called by the metadata creator"
+        );
+
+        // Payment is frozen once the first token has been minted
+        near_assert!(
+            minting_metadata.minted == 0,
+            "Payment cannot be changed after tokens have been minted"
+        );
+
+        // Update payment
+        minting_metadata.payment_method = match ft_contract_id {
+            Some(id) => MintingPayment::Ft(id),
+            None => MintingPayment::Near,
+        };
+        if let Some(price) = price {
+            minting_metadata.price = price.0;
+        }
+        self.token_metadata
+            .insert(&metadata_id.0, &minting_metadata);
+
+        // Emit event
+        log_payment_update(
+            metadata_id.0,
+            minting_metadata.price,
+            &minting_metadata.payment_method,
+        );
+    }
 }
 
 fn log_nft_metadata_update(token_ids: Vec<String>) {
     env::log_str(&NftMetadataUpdateLog { token_ids }.serialize_event())
 }
 
+fn log_repair_reference(
+    metadata_id: U64,
+    reference: String,
+    reference_hash: Base64VecU8,
+) {
+    env::log_str(
+        &RepairReferenceData {
+            metadata_id,
+            reference,
+            reference_hash,
+        }
+        .serialize_event(),
+    )
+}
+
 fn log_token_lock(metadata_id: u64) {
     env::log_str(
         &MintingMetadataUpdateData {
@@ -91,6 +265,28 @@ fn log_token_lock(metadata_id: u64) {
             minters_allowlist: None,
             price: None,
             is_dynamic: Some(false),
+            currency: None,
+        }
+        .serialize_event(),
+    )
+}
+
+fn log_payment_update(
+    metadata_id: u64,
+    price: Balance,
+    payment_method: &MintingPayment,
+) {
+    let currency = match payment_method.get_ft_contract_id() {
+        None => "near".to_string(),
+        Some(ft_contract_id) => format!("ft::{}", ft_contract_id),
+    };
+    env::log_str(
+        &MintingMetadataUpdateData {
+            metadata_id: metadata_id.into(),
+            minters_allowlist: None,
+            price: Some(price.into()),
+            is_dynamic: None,
+            currency: Some(currency),
         }
         .serialize_event(),
     )
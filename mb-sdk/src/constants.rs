@@ -55,6 +55,13 @@ pub mod gas {
     /// Gas requirements for `nft_transfer_call`
     pub const NFT_TRANSFER_CALL: Gas = tgas(35);
 
+    /// Gas requirements for `nft_batch_transfer_call`
+    pub const NFT_BATCH_TRANSFER_CALL: Gas = tgas(50);
+
+    /// Additional gas requirements per token for resolving a
+    /// `nft_batch_transfer_call` XCC
+    pub const RESOLVE_BATCH_TRANSFER_PER_TOKEN: Gas = tgas(5);
+
     /// Gas requirements for `nft_transfer_call`
     pub const NFT_ON_APPROVE: Gas = tgas(25);
 
@@ -120,10 +127,48 @@ pub const MAX_LEN_ROYALTIES: u32 = 25;
 /// Maximum splits participants to process (NFT v2)
 pub const MAX_LEN_SPLITS: u32 = 25;
 
+/// Maximum length of per-token `reference` and `media` overrides (NFT v2)
+pub const MAX_LEN_TOKEN_OVERRIDE: usize = 256;
+
+/// Maximum length of a `reference` that is set via `repair_reference`, as the
+/// repair does not require a storage deposit (NFT v2)
+pub const MAX_LEN_REPAIRED_REFERENCE: usize = 256;
+
 /// Maximum allowed approvals per token to prevent panics on revoking all, most
 /// notably during transfers.
 pub const MAX_APPROVALS_PER_TOKEN: u64 = 100;
 
+/// Maximum number of tokens that can be transferred at once via
+/// `nft_batch_transfer_call`, such that all of them can be returned within the
+/// gas of the resolving callback (NFT v2)
+pub const MAX_LEN_BATCH_TRANSFER_CALL: usize = 20;
+
+/// Maximum number of accounts returned per page of the banlist and allowlist
+/// (legacy market)
+pub const MAX_LEN_ACCOUNTS_PAGE: u64 = 100;
+
+/// Maximum number of accounts returned by `list_minters_paginated` (NFT v1)
+/// and `list_creators_paginated` (NFT v2)
+pub const MAX_LEN_MINTERS_PAGE: u64 = 100;
+
+/// Maximum number of tokens that can be queried at once via `nft_approval_ids`
+pub const MAX_LEN_APPROVAL_IDS_QUERY: usize = 100;
+
+/// Maximum number of metadata that can be queried at once via
+/// `get_metadata_states` (NFT v2)
+pub const MAX_LEN_METADATA_STATES_QUERY: usize = 100;
+
+/// Maximum number of tokens returned by `nft_tokens_for_owner_with_approvals`
+/// (NFT v2)
+pub const MAX_LIMIT_TOKENS_WITH_APPROVALS: u32 = 50;
+
+/// Maximum number of tokens returned by `nft_tokens_by_extra` (NFT v2)
+pub const MAX_LIMIT_TOKENS_BY_EXTRA: u32 = 50;
+
+/// Number of buckets that the window of the global mint rate limit is split
+/// into, bounding the storage used to track recent mints (NFT v2)
+pub const MINT_RATE_LIMIT_BUCKETS: u64 = 60;
+
 /// Minimum storage stake required to allow updates
 pub const MINIMUM_FREE_STORAGE_STAKE: near_sdk::Balance = 50 * YOCTO_PER_BYTE;
 
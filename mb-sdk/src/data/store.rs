@@ -160,6 +160,53 @@ pub struct TokenCompliant {
     pub origin_key: Option<TokenKey>,
 }
 
+/// A token together with the accounts approved to transfer it, as returned by
+/// `nft_tokens_for_owner_with_approvals`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TokenWithApprovalsJson {
+    pub token: TokenCompliant,
+    /// Approved accounts and their approval IDs, as returned by
+    /// `nft_approvals`.
+    pub approvals: HashMap<AccountId, u64>,
+}
+
+/// Distinguishes burned tokens from tokens that have never been minted, as
+/// returned by `nft_token_status`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TokenStatus {
+    Exists,
+    Burned,
+    Never,
+}
+
+/// Minting state of a metadata, as returned by `get_metadata_states`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MetadataStateJson {
+    pub is_locked: bool,
+    pub minted: u32,
+    pub max_supply: Option<u32>,
+    pub price: U128,
+    /// Whether the current block lies within the minting window of the
+    /// metadata and its supply has not been exhausted.
+    pub mintable_now: bool,
+}
+
+/// Storage balance of an account as specified by
+/// [NEP-145](https://nomicon.io/Standards/StorageManagement).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StorageBalance {
+    pub total: U128,
+    pub available: U128,
+}
+
+/// Storage balance bounds as specified by
+/// [NEP-145](https://nomicon.io/Standards/StorageManagement).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>,
+}
+
 // -------- token metadata
 // NON-COMPLIANT https://github.com/near/NEPs/blob/master/specs/Standards/NonFungibleToken/Metadata.md
 /// ref:
@@ -246,6 +293,35 @@ impl From<TokenMetadata> for TokenMetadataCompliant {
     }
 }
 
+/// Per-token replacement of `reference` and `media` for tokens minted on store
+/// v2, e.g. for generative 1/1s that otherwise share their metadata.
+#[derive(
+    Clone, Debug, Deserialize, Serialize, BorshDeserialize, BorshSerialize,
+)]
+pub struct TokenMetadataOverride {
+    pub reference: Option<String>,
+    pub media: Option<String>,
+}
+
+impl TokenMetadataOverride {
+    pub fn is_empty(&self) -> bool {
+        self.reference.is_none() && self.media.is_none()
+    }
+
+    /// Replaces the overridden fields on `metadata`. Hashes of the replaced
+    /// fields are dropped, as they refer to the shared content.
+    pub fn apply(&self, metadata: &mut TokenMetadata) {
+        if let Some(ref reference) = self.reference {
+            metadata.reference = Some(reference.clone());
+            metadata.reference_hash = None;
+        }
+        if let Some(ref media) = self.media {
+            metadata.media = Some(media.clone());
+            metadata.media_hash = None;
+        }
+    }
+}
+
 /// Metadata and meta-metadata for tokens minted on store v2
 #[derive(Clone, BorshDeserialize, BorshSerialize)]
 pub struct MintingMetadata {
@@ -473,10 +549,12 @@ pub struct SplitOwners {
 }
 
 impl SplitOwners {
+    /// A single account receiving all of the revenue is allowed, e.g. to
+    /// delegate all proceeds of a sale to a treasury account.
     pub fn new(split_between: HashMap<near_sdk::AccountId, u32>) -> Self {
         crate::near_assert!(
-            split_between.len() >= 2,
-            "Requires at least two accounts to split revenue"
+            !split_between.is_empty(),
+            "Requires at least one account to split revenue"
         );
         // validate args
         let mut sum: u32 = 0;
@@ -500,6 +578,17 @@ impl SplitOwners {
 
         Self { split_between }
     }
+
+    /// Reverse of `SplitOwners::new`, returning the share of each account in
+    /// basis points (summing up to 10_000), e.g. for views and events.
+    pub fn to_unparsed(&self) -> HashMap<AccountId, u16> {
+        self.split_between
+            .iter()
+            .map(|(account, fraction)| {
+                (account.clone(), fraction.numerator as u16)
+            })
+            .collect()
+    }
 }
 
 /// A representation of permanent partial ownership of a Token's revenues.
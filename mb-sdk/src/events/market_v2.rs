@@ -6,7 +6,10 @@ use near_events::near_event_data;
 // #[cfg(feature = "ser")]
 // use near_sdk::serde::Serialize;
 use near_sdk::{
-    json_types::U128,
+    json_types::{
+        U128,
+        U64,
+    },
     AccountId,
 };
 
@@ -24,6 +27,9 @@ pub struct NftListData {
     pub nft_owner_id: AccountId,
     pub currency: String,
     pub price: U128,
+    /// Opaque reference specified by the lister, omitted if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_ref: Option<String>,
 }
 
 // This could be more efficient by vectorizing token IDs and approval IDs, but
@@ -40,6 +46,40 @@ pub struct NftUnlistData {
     pub nft_approval_id: u64,
 }
 
+/// Unlisting that has not been triggered by the token owner, with `reason`
+/// explaining why the listing has been removed (e.g. `"stale"`). `forced` is
+/// set if the market owner removed a listing that was still valid.
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "nft_unlist"
+)]
+pub struct NftUnlistWithReasonData {
+    pub nft_contract_id: AccountId,
+    pub nft_token_id: String,
+    pub nft_approval_id: u64,
+    pub reason: String,
+    pub forced: bool,
+}
+
+/// In-place modification of a listing, with `field` naming the modified
+/// listing field and the values serialized as strings. Creation and removal
+/// of listings are covered by `nft_list` and `nft_unlist`.
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "nft_listing_update"
+)]
+pub struct NftListingUpdateData {
+    pub nft_contract_id: AccountId,
+    pub nft_token_id: String,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
 #[cfg_attr(feature = "all", derive(Clone, Debug))]
 #[near_event_data(
     standard = "mb_market",
@@ -80,6 +120,9 @@ pub struct NftSaleData {
     // this field should always be populated, `Option` for backwards
     // compatibility of generated JSON
     pub mintbase_amount: U128,
+    /// Opaque reference specified by the lister, omitted if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_ref: Option<String>,
 }
 
 #[cfg_attr(feature = "all", derive(Clone, Debug))]
@@ -118,6 +161,56 @@ pub struct NftMakeOfferData {
     pub affiliate_amount: Option<U128>,
 }
 
+/// Escrowed offer on a token that is not listed on the market, or that is
+/// listed in a different currency.
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "nft_unlisted_offer"
+)]
+pub struct NftUnlistedOfferData {
+    pub nft_contract_id: AccountId,
+    pub nft_token_id: String,
+    pub offerer_id: AccountId,
+    pub currency: String,
+    pub amount: U128,
+    pub expires_at: U64,
+}
+
+/// Removal of an unlisted offer without a sale, with `reason` being either
+/// `"withdrawn"` or `"expired"`.
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "nft_remove_unlisted_offer"
+)]
+pub struct NftRemoveUnlistedOfferData {
+    pub nft_contract_id: AccountId,
+    pub nft_token_id: String,
+    pub offerer_id: AccountId,
+    pub reason: String,
+}
+
+/// Offer that has been refunded without being inserted into a listing, with
+/// `reason` explaining why (e.g. `"listing_not_found"` when another buyer was
+/// faster).
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "nft_refund_offer"
+)]
+pub struct NftRefundOfferData {
+    pub nft_contract_id: AccountId,
+    pub nft_token_id: String,
+    pub offerer_id: AccountId,
+    pub currency: String,
+    pub amount: U128,
+    pub reason: String,
+}
+
 #[cfg_attr(feature = "all", derive(Clone, Debug))]
 #[near_event_data(
     standard = "mb_market",
@@ -130,6 +223,7 @@ pub struct NftWithdrawOfferData {
     pub offer_id: u64,
 }
 
+/// Deprecated, the market now emits `NftFailedSaleDataV030` instead.
 #[cfg_attr(feature = "all", derive(Clone, Debug))]
 #[near_event_data(
     standard = "mb_market",
@@ -142,3 +236,101 @@ pub struct NftFailedSaleData {
     pub nft_approval_id: u64,
     pub offer_id: u64,
 }
+
+/// Sale that has been aborted after the offer was accepted, e.g. because the
+/// NFT transfer failed or the payout was ill-formatted. The listing has been
+/// removed and the `amount` refunded to `offerer_id`.
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "nft_failed_listing"
+)]
+pub struct NftFailedSaleDataV030 {
+    pub nft_contract_id: AccountId,
+    pub nft_token_id: String,
+    pub nft_approval_id: u64,
+    pub offerer_id: AccountId,
+    pub amount: U128,
+}
+
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "nft_rescue"
+)]
+pub struct NftRescueData {
+    pub nft_contract_id: AccountId,
+    pub nft_token_id: String,
+    pub receiver_id: AccountId,
+}
+
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "ft_rescue"
+)]
+pub struct FtRescueData {
+    pub ft_contract_id: AccountId,
+    pub amount: U128,
+    pub receiver_id: AccountId,
+}
+
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "ownership_transferred"
+)]
+pub struct MarketOwnershipTransferredData {
+    pub old_owner: AccountId,
+    pub new_owner: AccountId,
+}
+
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "update_cuts"
+)]
+pub struct MarketCutsUpdateData {
+    pub mintbase_cut: Option<u16>,
+    pub fallback_cut: Option<u16>,
+}
+
+/// Accounts that have been added to (`banned = true`) or removed from the
+/// banlist in a single batch.
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "update_banlist"
+)]
+pub struct MarketBanlistUpdateData {
+    pub accounts: Vec<AccountId>,
+    pub banned: bool,
+}
+
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "ownership_proposed"
+)]
+pub struct MarketOwnershipProposedData {
+    pub owner: AccountId,
+    pub pending_owner: AccountId,
+}
+
+#[cfg_attr(feature = "all", derive(Clone, Debug))]
+#[near_event_data(
+    standard = "mb_market",
+    version = "0.3.0",
+    event = "ownership_proposal_cancelled"
+)]
+pub struct MarketOwnershipProposalCancelledData {
+    pub owner: AccountId,
+    pub pending_owner: AccountId,
+}
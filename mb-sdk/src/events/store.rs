@@ -7,7 +7,10 @@ use near_sdk::serde::Deserialize;
 #[cfg(feature = "ser")]
 use near_sdk::serde::Serialize;
 use near_sdk::{
-    json_types::U64,
+    json_types::{
+        U128,
+        U64,
+    },
     AccountId,
 };
 
@@ -108,6 +111,12 @@ pub struct CreateMetadataData {
     pub starts_at: Option<U64>,
     pub expires_at: Option<U64>,
     pub is_locked: bool,
+    /// Maximum number of tokens per minter, omitted if unlimited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_minter_cap: Option<u32>,
+    /// Whether tokens are non-transferable, omitted if they are not.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub soulbound: bool,
 }
 
 #[cfg_attr(feature = "all", derive(Debug, Clone))]
@@ -123,6 +132,35 @@ pub struct MintingMetadataUpdateData {
     // TODO: method
     pub price: Option<near_sdk::json_types::U128>,
     pub is_dynamic: Option<bool>,
+    /// Payment currency for minting, either `"near"` or
+    /// `"ft::<ft_contract_id>"`, omitted if unchanged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+/// Emitted instead of `nft_metadata_update` when the reference of a metadata
+/// has been repaired, as listing all affected token IDs is unbounded for
+/// locked metadata. Applies to all tokens minted on `metadata_id`.
+#[cfg_attr(feature = "all", derive(Debug, Clone))]
+#[near_event_data(
+    standard = "mb_store",
+    version = "2.0.0",
+    event = "repair_reference"
+)]
+pub struct RepairReferenceData {
+    pub metadata_id: U64,
+    pub reference: String,
+    pub reference_hash: near_sdk::json_types::Base64VecU8,
+}
+
+#[cfg_attr(feature = "all", derive(Debug, Clone))]
+#[near_event_data(
+    standard = "mb_store",
+    version = "2.0.0",
+    event = "minting_cap_reached"
+)]
+pub struct MintingCapReachedData {
+    pub cap: U64,
 }
 
 // ------------------------------- Approvals -------------------------------- //
@@ -177,6 +215,8 @@ pub struct NftSetSplitOwnerData {
 }
 
 // ----------------------------- Store settings ----------------------------- //
+// Settings that have been added after the event versions were published are
+// omitted unless set, to keep existing events compatible with their schema
 #[near_event_data(
     standard = "mb_store",
     version = "0.1.0",
@@ -188,6 +228,14 @@ pub struct MbStoreChangeSettingDataV010 {
     pub new_owner: Option<String>,
     pub new_icon_base64: Option<String>, // deprecated in favor of metadata update
     pub new_base_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_storage_price_per_byte: Option<U128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_access_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_access_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_fee_recipient: Option<String>,
 }
 
 impl MbStoreChangeSettingDataV010 {
@@ -198,6 +246,10 @@ impl MbStoreChangeSettingDataV010 {
             new_owner: None,
             new_icon_base64: None,
             new_base_uri: None,
+            new_storage_price_per_byte: None,
+            added_access_key: None,
+            deleted_access_key: None,
+            new_fee_recipient: None,
         }
     }
 }
@@ -215,6 +267,28 @@ pub struct MbStoreChangeSettingDataV020 {
     pub new_base_uri: Option<String>,
     pub set_minting_cap: Option<U64>,
     pub allow_open_minting: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_storage_price_per_byte: Option<U128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_access_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_access_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_fee_recipient: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_reference_repairs: Option<bool>,
+    /// `Some(None)` is serialized as `null` and means the sink was disabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_burn_sink_account: Option<Option<String>>,
+    /// `Some(None)` is serialized as `null` and means the default was removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_default_mint_window: Option<Option<(U64, U64)>>,
+    /// `Some(None)` is serialized as `null` and means the limit was lifted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_global_mint_rate_limit: Option<Option<(u32, u64)>>,
+    /// `Some(None)` is serialized as `null` and means the fee was removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_transfer_fee: Option<Option<(String, U128)>>,
 }
 
 impl MbStoreChangeSettingDataV020 {
@@ -227,6 +301,52 @@ impl MbStoreChangeSettingDataV020 {
             new_base_uri: None,
             set_minting_cap: None,
             allow_open_minting: None,
+            new_storage_price_per_byte: None,
+            added_access_key: None,
+            deleted_access_key: None,
+            new_fee_recipient: None,
+            allow_reference_repairs: None,
+            new_burn_sink_account: None,
+            new_default_mint_window: None,
+            new_global_mint_rate_limit: None,
+            new_transfer_fee: None,
         }
     }
 }
+
+#[cfg_attr(feature = "all", derive(Debug, Clone))]
+#[near_event_data(
+    standard = "mb_store",
+    version = "2.0.0",
+    event = "ownership_proposed"
+)]
+pub struct OwnershipProposedData {
+    pub owner_id: AccountId,
+    pub pending_owner_id: AccountId,
+}
+
+#[cfg_attr(feature = "all", derive(Debug, Clone))]
+#[near_event_data(
+    standard = "mb_store",
+    version = "2.0.0",
+    event = "ownership_proposal_cancelled"
+)]
+pub struct OwnershipProposalCancelledData {
+    pub owner_id: AccountId,
+    pub pending_owner_id: AccountId,
+}
+
+/// Emitted when transfer fees have been paid for `token_ids`, with `amount`
+/// being the total of the fees.
+#[cfg_attr(feature = "all", derive(Debug, Clone))]
+#[near_event_data(
+    standard = "mb_store",
+    version = "2.0.0",
+    event = "transfer_fee"
+)]
+pub struct TransferFeeData {
+    pub token_ids: Vec<String>,
+    pub payer_id: AccountId,
+    pub fee_recipient: AccountId,
+    pub amount: U128,
+}
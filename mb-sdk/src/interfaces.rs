@@ -22,6 +22,16 @@ pub trait ExtOldMarket {
 
 #[ext_contract(ext_nft)]
 pub trait ExtNft {
+    /// Transfer the token without requesting payout data.
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    );
+    /// Query a token according to NEP-171.
+    fn nft_token(&self, token_id: String);
     /// Transfer the token and get the payout data.
     fn nft_transfer_payout(
         &mut self,
@@ -94,6 +104,46 @@ pub trait ExtNftOnTransfer {
     ) -> Promise;
 }
 
+#[ext_contract(ext_nft_on_purchase)]
+pub trait ExtNftOnPurchase {
+    /// Take some action after a token has been bought on a market and
+    /// transferred to the buyer.
+    ///
+    /// Arguments:
+    /// * `buyer_id`: the account that bought and now owns the token.
+    /// * `nft_contract_id`: the NFT contract on which the token lives.
+    /// * `token_id`: the ID of the bought token.
+    /// * `msg`: the message that the buyer passed to the market.
+    ///
+    /// The sale has been completed when this is called, and it will not be
+    /// reverted if this call fails.
+    fn nft_on_purchase(
+        &mut self,
+        buyer_id: AccountId,
+        nft_contract_id: AccountId,
+        token_id: String,
+        msg: String,
+    );
+}
+
+#[ext_contract(ext_nft_on_batch_transfer)]
+pub trait ExtNftOnBatchTransfer {
+    /// Batched version of `nft_on_transfer`, not standardized! Take some
+    /// action after receiving multiple non-fungible tokens in a single call.
+    ///
+    /// Arguments are the same as for `nft_on_transfer`, except that
+    /// `token_ids` contains all transferred tokens.
+    ///
+    /// Returns the token IDs that should be returned to `previous_owner_id`.
+    fn nft_on_batch_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_ids: Vec<String>,
+        msg: String,
+    ) -> Promise;
+}
+
 #[ext_contract(ext_factory)]
 pub trait ExtFactory {
     fn on_create(
@@ -109,6 +159,8 @@ pub trait ExtFactory {
 #[near_sdk::ext_contract(ext_ft)]
 pub trait ExtFt {
     fn ft_transfer(receiver_id: AccountId, amount: U128, memo: Option<String>);
+    fn ft_balance_of(account_id: AccountId) -> U128;
+    fn ft_metadata();
     fn ft_resolve_transfer(
         sender_id: AccountId,
         receiver_id: AccountId,
@@ -130,5 +182,37 @@ pub trait ExtFt {
 #[near_sdk::ext_contract(ext_new_market)]
 pub trait ExtNewMarket {
     fn nft_resolve_payout_near(token_key: String);
-    fn nft_resolve_payout_ft(token_key: String);
+    fn nft_resolve_payout_ft(
+        token_key: String,
+        surplus: U128,
+        max_len_payout: u32,
+    );
+    fn ft_resolve_rescue(
+        ft_contract_id: AccountId,
+        amount: U128,
+        receiver_id: AccountId,
+    );
+    fn resolve_stale_listing(
+        nft_contract_id: AccountId,
+        token_id: String,
+        nft_approval_id: u64,
+    );
+    fn resolve_release_stuck_offer(
+        nft_contract_id: AccountId,
+        token_id: String,
+        nft_approval_id: u64,
+        offer_started_at: u64,
+    );
+    fn ft_resolve_claim_accruals(
+        ft_contract_id: AccountId,
+        account_id: AccountId,
+        amount: U128,
+    );
+    fn ft_resolve_refresh(ft_contract_id: AccountId) -> bool;
+    fn resolve_accept_unlisted_offer(
+        token_key: String,
+        approval_id: u64,
+        offer_created_at: u64,
+        offer_expires_at: u64,
+    );
 }
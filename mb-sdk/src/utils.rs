@@ -36,6 +36,39 @@ pub fn ft_transfer(
         .ft_transfer(receiver_id, amount.into(), None)
 }
 
+/// Get the parent of an account ID, e.g. the factory that deployed a store.
+/// Returns `None` for top-level and implicit accounts.
+pub fn parent_account_id(child: &AccountId) -> Option<AccountId> {
+    match child.as_str().split_once('.') {
+        Some((_, parent)) => parent.parse().ok(),
+        None => None,
+    }
+}
+
+// ------------------------- ContractVersion type --------------------------- //
+/// Identifies the contract binary that is deployed to an account. Construct
+/// this with the `contract_version!` macro to use the package metadata of the
+/// calling crate.
+#[derive(Serialize)]
+pub struct ContractVersionJson {
+    pub name: String,
+    pub version: String,
+    /// Commit hash that was passed via the `GIT_SHA` environment variable
+    /// during compilation, if any
+    pub git_sha: Option<String>,
+}
+
+#[macro_export]
+macro_rules! contract_version {
+    () => {
+        $crate::utils::ContractVersionJson {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: option_env!("GIT_SHA").map(|sha| sha.to_string()),
+        }
+    };
+}
+
 // --------------------------- SafeFraction type ---------------------------- //
 /// A provisional safe fraction type, borrowed and modified from:
 /// https://github.com/near/core-contracts/blob/master/staking-pool/src/lib.rs#L127
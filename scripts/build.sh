@@ -12,6 +12,12 @@ cargo clippy -p mb-factory-v1 -- -D warnings || exit 1
 cargo clippy -p mb-factory-v2 -- -D warnings || exit 1
 cargo clippy -p mb-legacy-market -- -D warnings || exit 1
 cargo clippy -p mb-interop-market -- -D warnings || exit 1
+cargo clippy -p mb-nft-v2 --features testing -- -D warnings -A clippy::too_many_arguments || exit 1
+cargo clippy -p mb-interop-market --features testing -- -D warnings || exit 1
+
+# Embedded into the contracts and exposed via `get_version`
+GIT_SHA="$(git rev-parse --short HEAD)"
+export GIT_SHA
 
 build() {
   cargo "$1" || return 1
@@ -19,9 +25,30 @@ build() {
   wasm-opt "wasm/$1-raw.wasm" -Oz -o "wasm/$1.wasm"
 }
 
+# Store v2 with the state layout prior to `migrate`, required by the v2 upgrade
+# test. v2 stores are not yet deployed, so `PRE_MIGRATION_REV` needs to be set
+# to the upstream tag or commit of the release to migrate from, otherwise the
+# test is skipped. The other contracts are tested against their deployed code.
+build_pre_migration() {
+  [[ -z "$PRE_MIGRATION_REV" ]] && return 0
+  [[ -e wasm/mb-nft-v2-pre-migration.wasm ]] && return 0
+  local dir
+  dir="$(mktemp -d)"
+  git worktree add --detach "$dir" "$PRE_MIGRATION_REV" || return 1
+  (cd "$dir" && mkdir -p wasm && cargo mb-nft-v2) || {
+    git worktree remove --force "$dir"
+    return 1
+  }
+  cp "$dir/wasm/mb-nft-v2.wasm" wasm/mb-nft-v2-pre-migration.wasm
+  git worktree remove --force "$dir"
+}
+
 build mb-nft-v1 || exit 1
 build mb-nft-v2 || exit 1
 build factory-v1 || exit 1
 build factory-v2 || exit 1
 build legacy-market || exit 1
 build interop-market || exit 1
+build mb-nft-v2-testing || exit 1
+build interop-market-testing || exit 1
+build_pre_migration || exit 1
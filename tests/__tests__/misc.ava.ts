@@ -1,4 +1,4 @@
-import { TransactionResult } from "near-workspaces";
+import { KeyPair, NearAccount, TransactionResult } from "near-workspaces";
 import avaTest from "ava";
 import {
   NEAR,
@@ -10,6 +10,9 @@ import {
   assertMinters,
   assertContractPanics,
   changeSettingsData,
+  getBalance,
+  assertBalanceChange,
+  mintingDeposit,
 } from "./utils/index.js";
 import { CHANGE_SETTING_VERSION, MB_VERSION, setup } from "./setup.js";
 
@@ -241,3 +244,198 @@ test("ownership::transfer-store", async (test) => {
     "transferring store ownership (keep minters)"
   );
 });
+
+test("ownership::propose-accept", async (test) => {
+  const { alice, bob, carol, store } = test.context.accounts;
+  const keepMinters =
+    MB_VERSION === "v1"
+      ? { keep_old_minters: true }
+      : { keep_old_creators: true };
+
+  await alice.call(
+    store,
+    "propose_owner",
+    { new_owner: bob.accountId, ...keepMinters },
+    { attachedDeposit: "1" }
+  );
+  test.is(await store.view("get_pending_owner"), bob.accountId);
+  test.is(await store.view("get_owner_id"), alice.accountId);
+
+  await assertContractPanics(test, [
+    [
+      async () => {
+        await carol.call(
+          store,
+          "accept_ownership",
+          {},
+          { attachedDeposit: "1" }
+        );
+      },
+      "This method can only be called by the pending store owner",
+      "Non-proposed account tried to accept store ownership",
+    ],
+  ]);
+
+  await bob.call(store, "accept_ownership", {}, { attachedDeposit: "1" });
+  test.is(await store.view("get_owner_id"), bob.accountId);
+  test.is(await store.view("get_pending_owner"), null);
+
+  // cancelled proposals cannot be accepted
+  await bob.call(
+    store,
+    "propose_owner",
+    { new_owner: carol.accountId, ...keepMinters },
+    { attachedDeposit: "1" }
+  );
+  await bob.call(
+    store,
+    "cancel_ownership_proposal",
+    {},
+    { attachedDeposit: "1" }
+  );
+  test.is(await store.view("get_pending_owner"), null);
+  await assertContractPanics(test, [
+    [
+      async () => {
+        await carol.call(
+          store,
+          "accept_ownership",
+          {},
+          { attachedDeposit: "1" }
+        );
+      },
+      "This method can only be called by the pending store owner",
+      "Tried to accept a cancelled ownership proposal",
+    ],
+  ]);
+  test.is(await store.view("get_owner_id"), bob.accountId);
+});
+
+const initStore = async (store: NearAccount, owner: NearAccount) => {
+  await store.deploy(`../wasm/mb-nft-${MB_VERSION}.wasm`);
+  await store.call(store, "new", {
+    owner_id: owner.accountId,
+    metadata: { spec: "nft-1.0.0", name: "store", symbol: "STORE" },
+  });
+};
+
+const mintOne = async (store: NearAccount, minter: NearAccount) => {
+  if (MB_VERSION === "v1") {
+    await minter.call(
+      store,
+      "nft_batch_mint",
+      { owner_id: minter.accountId, metadata: {}, num_to_mint: 1 },
+      { attachedDeposit: mintingDeposit({ n_tokens: 1 }) }
+    );
+    return;
+  }
+
+  await minter.call(
+    store,
+    "create_metadata",
+    { metadata: {}, price: NEAR(0.01) },
+    { attachedDeposit: NEAR(0.1) }
+  );
+  await minter.call(
+    store,
+    "deposit_storage",
+    {},
+    { attachedDeposit: NEAR(0.05) }
+  );
+  await minter.call(
+    store,
+    "mint_on_metadata",
+    { metadata_id: "0", num_to_mint: 1, owner_id: minter.accountId },
+    { attachedDeposit: NEAR(0.02) }
+  );
+};
+
+test("ownership::fee-recipient", async (test) => {
+  const { root, alice, bob, carol, factory, store } = test.context.accounts;
+
+  // factory stores pay the minting fee to the factory by default, and only the
+  // factory may redirect it
+  test.is(await store.view("get_fee_recipient"), factory.accountId);
+  await assertContractPanics(test, [
+    [
+      async () => {
+        await alice.call(
+          store,
+          "set_fee_recipient",
+          { fee_recipient: alice.accountId },
+          { attachedDeposit: "1" }
+        );
+      },
+      "This method can only be called by the current fee recipient",
+      "Store owner tried to redirect the factory minting fee",
+    ],
+  ]);
+
+  // stores on top-level accounts have no fee recipient unless configured
+  const topLevelStore = await root.createAccount("toplevel".repeat(5), {
+    initialBalance: NEAR(10).toString(),
+  });
+  await initStore(topLevelStore, alice);
+  test.is(await topLevelStore.view("get_fee_recipient"), null);
+
+  await assertContractPanics(test, [
+    [
+      async () => {
+        await bob.call(
+          topLevelStore,
+          "set_fee_recipient",
+          { fee_recipient: bob.accountId },
+          { attachedDeposit: "1" }
+        );
+      },
+      "This method can only be called by the store owner",
+      "Non-owner tried to set the fee recipient",
+    ],
+  ]);
+  const setFeeRecipientCall = await alice.callRaw(
+    topLevelStore,
+    "set_fee_recipient",
+    { fee_recipient: carol.accountId },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    setFeeRecipientCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: CHANGE_SETTING_VERSION,
+        event: "change_setting",
+        data: changeSettingsData({ new_fee_recipient: carol.accountId }),
+      },
+    ],
+    "setting fee recipient"
+  );
+  test.is(await topLevelStore.view("get_fee_recipient"), carol.accountId);
+
+  // minting pays the fee to the configured recipient
+  const carolBalance = await getBalance(carol);
+  await mintOne(topLevelStore, alice);
+  await assertBalanceChange(
+    test,
+    { account: carol, ref: carolBalance, diff: mNEAR(1) },
+    "Minting on top-level store"
+  );
+});
+
+test("deployment::implicit-account", async (test) => {
+  const { root, alice } = test.context.accounts;
+
+  // implicit accounts can only be created by transferring to them
+  const keyPair = KeyPair.fromRandom("ed25519");
+  const implicitId = Buffer.from(keyPair.getPublicKey().data).toString("hex");
+  await root.transfer(implicitId, NEAR(10));
+  const implicitStore = root.getAccount(implicitId);
+  await implicitStore.setKey(keyPair);
+  await initStore(implicitStore, alice);
+
+  // minting must not panic when trying to find the parent account
+  test.is(await implicitStore.view("get_fee_recipient"), null);
+  await mintOne(implicitStore, alice);
+  test.is(await implicitStore.view("get_tokens_minted"), "1");
+});
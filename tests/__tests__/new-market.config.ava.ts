@@ -1,11 +1,19 @@
 import avaTest from "ava";
-import { NearAccount } from "near-workspaces";
+import { Gas, NearAccount } from "near-workspaces";
 import {
   nearToYocto,
   nearToBn,
   getBalance,
   diffCheck,
 } from "./utils/balances.js";
+import {
+  assertContractPanic,
+  assertEventLogs,
+  batchMint,
+  getEvent,
+  getPanic,
+  getTokenIds,
+} from "./utils/index.js";
 import setup from "./setup.js";
 
 const test = setup(avaTest);
@@ -19,6 +27,10 @@ test("interop-market::init", async (t) => {
   t.is(await market.view("get_listing_lock_seconds"), "0");
   t.is(await market.view("get_listing_storage_deposit"), nearToYocto("0.01"));
   t.deepEqual(await market.view("banned_accounts"), []);
+  t.like(await market.view("get_version"), {
+    name: "mb-interop-market",
+    version: "0.1.0",
+  });
 });
 
 test("interop-market::config", async (test) => {
@@ -40,6 +52,62 @@ test("interop-market::config", async (test) => {
   );
   test.is(await market.view("get_mintbase_cut"), 500);
 
+  const setCutsCall = await root.callRaw(
+    market,
+    "set_cuts",
+    { mintbase_cut: 1000, fallback_cut: 250 },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    setCutsCall.logs,
+    [
+      {
+        standard: "mb_market",
+        version: "0.3.0",
+        event: "update_cuts",
+        data: { mintbase_cut: 1000, fallback_cut: 250 },
+      },
+    ],
+    "setting both cuts"
+  );
+  test.is(await market.view("get_mintbase_cut"), 1000);
+  test.is(await market.view("get_fallback_cut"), 250);
+  await root.call(
+    market,
+    "set_cuts",
+    { mintbase_cut: 500, fallback_cut: 500 },
+    { attachedDeposit: "1" }
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await root.call(
+        market,
+        "set_cuts",
+        { mintbase_cut: 10_001, fallback_cut: 250 },
+        { attachedDeposit: "1" }
+      );
+    },
+    "Cuts cannot exceed 10000 basis points",
+    "Setting cuts above 100%"
+  );
+  for (const method of ["set_mintbase_cut", "set_fallback_cut"]) {
+    await assertContractPanic(
+      test,
+      async () => {
+        await root.call(
+          market,
+          method,
+          { new_cut: 10_001 },
+          { attachedDeposit: "1" }
+        );
+      },
+      "Cuts cannot exceed 10000 basis points",
+      `Setting cut above 100% via ${method}`
+    );
+  }
+
   await root.call(
     market,
     "set_listing_lock_seconds",
@@ -48,6 +116,29 @@ test("interop-market::config", async (test) => {
   );
   test.is(await market.view("get_listing_lock_seconds"), "60");
 
+  test.is(await market.view("get_offer_timeout_seconds"), "3600");
+  await root.call(
+    market,
+    "set_offer_timeout_seconds",
+    { secs: "600" },
+    { attachedDeposit: "1" }
+  );
+  test.is(await market.view("get_offer_timeout_seconds"), "600");
+  // anyone may release stuck offers, but only those of existing listings
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(market, "release_stuck_offer", {
+        nft_contract_id: alice.accountId,
+        token_id: "0",
+      });
+    },
+    "Listing not found",
+    "Releasing the offer of a missing listing"
+  );
+
+  test.is(await market.view("get_min_ft_payout"), "0");
+
   await root.call(
     market,
     "set_listing_storage_deposit",
@@ -55,6 +146,19 @@ test("interop-market::config", async (test) => {
     { attachedDeposit: "1" }
   );
   test.is(await market.view("get_listing_storage_deposit"), nearToYocto("1"));
+  await assertContractPanic(
+    test,
+    async () => {
+      await root.call(
+        market,
+        "set_listing_storage_deposit",
+        { deposit: nearToYocto("0.008") },
+        { attachedDeposit: "1" }
+      );
+    },
+    `Listing storage deposit cannot be lower than ${nearToYocto("0.00851")}`,
+    "Setting listing storage deposit below minimum"
+  );
 
   await root.call(
     market,
@@ -63,6 +167,26 @@ test("interop-market::config", async (test) => {
     { attachedDeposit: "1" }
   );
   test.deepEqual(await market.view("banned_accounts"), ["evil.near"]);
+  await root.call(
+    market,
+    "ban",
+    { account_id: "evil2.near" },
+    { attachedDeposit: "1" }
+  );
+  test.deepEqual(
+    await market.view("banned_accounts_paginated", {
+      from_index: 1,
+      limit: 1,
+    }),
+    ["evil2.near"]
+  );
+  test.true(await market.view("is_banned", { account_id: "evil.near" }));
+  await root.call(
+    market,
+    "unban",
+    { account_id: "evil2.near" },
+    { attachedDeposit: "1" }
+  );
   await root.call(
     market,
     "unban",
@@ -70,6 +194,54 @@ test("interop-market::config", async (test) => {
     { attachedDeposit: "1" }
   );
   test.deepEqual(await market.view("banned_accounts"), []);
+  test.false(await market.view("is_banned", { account_id: "evil.near" }));
+
+  // batched banning
+  const batchBanCall = await root.callRaw(
+    market,
+    "batch_ban",
+    { accounts: ["evil.near", "evil2.near"] },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    batchBanCall.logs,
+    [
+      {
+        standard: "mb_market",
+        version: "0.3.0",
+        event: "update_banlist",
+        data: { accounts: ["evil.near", "evil2.near"], banned: true },
+      },
+    ],
+    "batch banning"
+  );
+  test.deepEqual(await market.view("banned_accounts"), [
+    "evil.near",
+    "evil2.near",
+  ]);
+  await root.call(
+    market,
+    "batch_unban",
+    { accounts: ["evil.near", "evil2.near"] },
+    { attachedDeposit: "1" }
+  );
+  test.deepEqual(await market.view("banned_accounts"), []);
+  await assertContractPanic(
+    test,
+    async () => {
+      await root.call(
+        market,
+        "batch_ban",
+        {
+          accounts: [...Array(101).keys()].map((i) => `evil${i}.near`),
+        },
+        { attachedDeposit: "1" }
+      );
+    },
+    "Cannot change more than 100 banned accounts at once",
+    "Batch banning too many accounts"
+  );
 
   await root.call(
     market,
@@ -78,6 +250,39 @@ test("interop-market::config", async (test) => {
     { attachedDeposit: "1" }
   );
   test.deepEqual(await market.view("affiliates"), [[alice.accountId, 200]]);
+  test.deepEqual(
+    await market.view("preview_affiliate_earning", {
+      price: nearToYocto("1"),
+      referrer_id: alice.accountId,
+    }),
+    {
+      affiliate_amount: nearToYocto("0.019"),
+      mintbase_amount: nearToYocto("0.001"),
+      seller_payout_before_royalty: nearToYocto("0.98"),
+    }
+  );
+  // cut computations must not overflow for huge (FT) offers
+  test.deepEqual(
+    await market.view("preview_affiliate_earning", {
+      price: "340282366920938463463374607431768211455", // u128::MAX
+      referrer_id: alice.accountId,
+    }),
+    {
+      affiliate_amount: "6465364971497830805804117541203596018",
+      mintbase_amount: "340282366920938463463374607431768211",
+      seller_payout_before_royalty: "333476719582519694194107115283132847225",
+    }
+  );
+  test.deepEqual(
+    await market.view("preview_affiliate_earning", {
+      price: nearToYocto("1"),
+    }),
+    {
+      affiliate_amount: "0",
+      mintbase_amount: nearToYocto("0.05"),
+      seller_payout_before_royalty: nearToYocto("0.95"),
+    }
+  );
   await root.call(
     market,
     "del_affiliate",
@@ -86,13 +291,39 @@ test("interop-market::config", async (test) => {
   );
   test.deepEqual(await market.view("affiliates"), []);
 
-  await root.call(
+  const setOwnerCall = await root.callRaw(
     market,
     "set_owner",
     { new_owner: alice.accountId },
     { attachedDeposit: "1" }
   );
+  assertEventLogs(
+    test,
+    setOwnerCall.logs,
+    [
+      {
+        standard: "mb_market",
+        version: "0.3.0",
+        event: "ownership_transferred",
+        data: { old_owner: root.accountId, new_owner: alice.accountId },
+      },
+    ],
+    "transferring market ownership"
+  );
+  test.is(await market.view("get_owner"), alice.accountId);
+
+  // two-step ownership transfer
+  await alice.call(
+    market,
+    "propose_owner",
+    { new_owner: root.accountId },
+    { attachedDeposit: "1" }
+  );
+  test.is(await market.view("get_pending_owner"), root.accountId);
   test.is(await market.view("get_owner"), alice.accountId);
+  await root.call(market, "accept_ownership", {}, { attachedDeposit: "1" });
+  test.is(await market.view("get_owner"), root.accountId);
+  test.is(await market.view("get_pending_owner"), null);
 });
 
 test("interop-market::deposits", async (test) => {
@@ -114,6 +345,18 @@ test("interop-market::deposits", async (test) => {
     { attachedDeposit: nearToYocto("1") as string }
   );
   await assertDeposit(alice, nearToYocto("1") as string);
+  test.is(
+    await market.view("get_free_storage_deposit", {
+      account_id: alice.accountId,
+    }),
+    nearToYocto("1")
+  );
+  test.is(
+    await market.view("get_affordable_listings", {
+      account_id: alice.accountId,
+    }),
+    100
+  );
   const postDepositBalance = await getBalance(alice);
   test.true(
     diffCheck(
@@ -142,4 +385,114 @@ test("interop-market::deposits", async (test) => {
     ),
     "Claimed deposit was not refunded"
   );
+
+  // funding a known number of listings, remainder is kept as well
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        market,
+        "deposit_and_prepare",
+        { num_listings: 3 },
+        { attachedDeposit: nearToYocto("0.02") as string }
+      );
+    },
+    `Deposit of ${nearToYocto("0.03")} yoctoNEAR is required to cover 3 listings`,
+    "Depositing too little storage for the requested listings"
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        market,
+        "deposit_and_prepare",
+        { num_listings: 1e17 },
+        { attachedDeposit: nearToYocto("0.02") as string }
+      );
+    },
+    "Storage deposit for 100000000000000000 listings overflows",
+    "Preparing an excessive number of listings"
+  );
+  test.is(
+    await alice.call(
+      market,
+      "deposit_and_prepare",
+      { num_listings: 3 },
+      { attachedDeposit: nearToYocto("0.045") as string }
+    ),
+    4
+  );
+  await assertDeposit(alice, nearToYocto("0.045") as string);
+});
+
+test("interop-market::rescue-nft", async (test) => {
+  const { root, alice, newMarket: market, store } = test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 2 });
+  const [strandedId, listedId] = getTokenIds(mintCall);
+  const rescue = (account: NearAccount, token_id: string) =>
+    account.callRaw(
+      market,
+      "rescue_nft",
+      {
+        nft_contract_id: store.accountId,
+        token_id,
+        receiver_id: alice.accountId,
+      },
+      { attachedDeposit: "1", gas: Gas.parse("100 Tgas") }
+    );
+
+  // alice transfers a token to the market instead of listing it
+  await alice.call(
+    store,
+    "nft_transfer",
+    { receiver_id: market.accountId, token_id: strandedId },
+    { attachedDeposit: "1" }
+  );
+
+  // only the owner can rescue it
+  test.is(
+    getPanic(await rescue(alice, strandedId)),
+    "Smart contract panicked: Method is restricted to market owner"
+  );
+  const rescueCall = await rescue(root, strandedId);
+  test.false(rescueCall.failed);
+  test.deepEqual(getEvent(rescueCall.logs[0]), {
+    standard: "mb_market",
+    version: "0.3.0",
+    event: "nft_rescue",
+    data: {
+      nft_contract_id: store.accountId,
+      nft_token_id: strandedId,
+      receiver_id: alice.accountId,
+    },
+  });
+  test.like(await store.view("nft_token", { token_id: strandedId }), {
+    owner_id: alice.accountId,
+  });
+
+  // listed tokens cannot be rescued
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.01") as string }
+  );
+  await alice.call(
+    store,
+    "nft_approve",
+    {
+      token_id: listedId,
+      account_id: market.accountId,
+      msg: JSON.stringify({ price: nearToYocto("1") }),
+    },
+    {
+      attachedDeposit: nearToYocto("0.008") as string,
+      gas: Gas.parse("50 Tgas"),
+    }
+  );
+  test.is(
+    getPanic(await rescue(root, listedId)),
+    "Smart contract panicked: Cannot rescue a token that is listed on the market"
+  );
 });
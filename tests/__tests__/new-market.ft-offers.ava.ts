@@ -6,7 +6,7 @@ import {
   diffCheck,
   nearToBn,
 } from "./utils/balances.js";
-import { getPanic } from "./utils/panics.js";
+import { assertContractPanic, getPanic } from "./utils/panics.js";
 import { getEvent } from "./utils/events.js";
 import { createPayouts } from "./utils/payouts.js";
 import setup, { createAndDeploy } from "./setup.js";
@@ -22,12 +22,10 @@ const deployWnear = async (root: NearAccount): Promise<NearAccount> =>
     initArgs: {},
   });
 
-const mintAndList = async ({
-  alice,
-  market,
-  store,
-  wnear,
-}: Record<string, NearAccount>) => {
+const mintAndList = async (
+  { alice, market, store, wnear }: Record<string, NearAccount>,
+  price = "1"
+) => {
   const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
   const tokenId = getTokenIds(mintCall)[0];
 
@@ -45,7 +43,7 @@ const mintAndList = async ({
       token_id: tokenId,
       account_id: market.accountId,
       msg: JSON.stringify({
-        price: nearToYocto("1"),
+        price: nearToYocto(price),
         ft_contract: wnear.accountId,
       }),
     },
@@ -250,7 +248,7 @@ test("interop-market::ft-offer-below-ask", async (test) => {
   test.true(postBobBalance.eq(preBobBalance));
 });
 
-test("interop-market::ft-ffers", async (test) => {
+test("interop-market::ft-offer-invalid-msg", async (test) => {
   const { root, alice, bob, newMarket: market, store } = test.context.accounts;
   const wnear = await deployWnear(root);
 
@@ -266,6 +264,45 @@ test("interop-market::ft-ffers", async (test) => {
   const preMarketBalance = await getWnearBalance({ account: market, wnear });
   const preBobBalance = await getWnearBalance({ account: bob, wnear });
 
+  const buyCall = await bob.callRaw(
+    wnear,
+    "ft_transfer_call",
+    {
+      receiver_id: market.accountId,
+      amount: nearToYocto("1"),
+      msg: "garbage",
+    },
+    { attachedDeposit: "1", gas: Gas.parse("299 Tgas") }
+  );
+  test.is(buyCall.logs.length, 3);
+  test.is(buyCall.logs[1], "Invalid arguments to buy using FT, refunding.");
+  test.is(await getOwner({ token_id: tokenId }), alice.accountId);
+
+  const postAliceBalance = await getWnearBalance({ account: alice, wnear });
+  const postMarketBalance = await getWnearBalance({ account: market, wnear });
+  const postBobBalance = await getWnearBalance({ account: bob, wnear });
+
+  test.true(postAliceBalance.eq(preAliceBalance));
+  test.true(postMarketBalance.eq(preMarketBalance));
+  test.true(postBobBalance.eq(preBobBalance));
+});
+
+test("interop-market::ft-ffers", async (test) => {
+  const { root, alice, bob, newMarket: market, store } = test.context.accounts;
+  const wnear = await deployWnear(root);
+
+  const getOwner = async ({ token_id }: { token_id: string }) =>
+    ((await store.view("nft_token", { token_id })) as { owner_id: string })
+      .owner_id;
+  const tokenId = await mintAndList({ alice, market, store, wnear }, "2");
+  await wrapNear({ account: alice, wnear, amount: "0.5" });
+  await wrapNear({ account: market, wnear, amount: "0.5" });
+  await wrapNear({ account: bob, wnear, amount: "3" });
+
+  const preAliceBalance = await getWnearBalance({ account: alice, wnear });
+  const preMarketBalance = await getWnearBalance({ account: market, wnear });
+  const preBobBalance = await getWnearBalance({ account: bob, wnear });
+
   test.is(await getOwner({ token_id: tokenId }), alice.accountId);
   const buyCall = await bob.callRaw(
     wnear,
@@ -332,7 +369,138 @@ test("interop-market::ft-ffers", async (test) => {
   test.true(postBobBalance.eq(preBobBalance.sub(nearToBn("2"))));
 });
 
+test("interop-market::ft-offer-above-ask", async (test) => {
+  const { root, alice, bob, newMarket: market, store } = test.context.accounts;
+  const wnear = await deployWnear(root);
+
+  const tokenId = await mintAndList({ alice, market, store, wnear });
+  await wrapNear({ account: alice, wnear, amount: "0.5" });
+  await wrapNear({ account: market, wnear, amount: "0.5" });
+  await wrapNear({ account: bob, wnear, amount: "3" });
+
+  const preAliceBalance = await getWnearBalance({ account: alice, wnear });
+  const preMarketBalance = await getWnearBalance({ account: market, wnear });
+  const preBobBalance = await getWnearBalance({ account: bob, wnear });
+
+  await bob.call(
+    wnear,
+    "ft_transfer_call",
+    {
+      receiver_id: market.accountId,
+      amount: nearToYocto("2"),
+      msg: JSON.stringify({
+        nft_contract_id: store.accountId,
+        token_id: tokenId,
+      }),
+    },
+    { attachedDeposit: "1", gas: Gas.parse("299 Tgas") }
+  );
+  test.is(
+    (
+      (await store.view("nft_token", { token_id: tokenId })) as {
+        owner_id: string;
+      }
+    ).owner_id,
+    bob.accountId
+  );
+
+  const postAliceBalance = await getWnearBalance({ account: alice, wnear });
+  const postMarketBalance = await getWnearBalance({ account: market, wnear });
+  const postBobBalance = await getWnearBalance({ account: bob, wnear });
+
+  // fees only apply to the listing price, surplus has been refunded
+  test.true(postAliceBalance.eq(preAliceBalance.add(nearToBn("0.975"))));
+  test.true(postMarketBalance.eq(preMarketBalance.add(nearToBn("0.025"))));
+  test.true(postBobBalance.eq(preBobBalance.sub(nearToBn("1"))));
+});
+
 // ----------------------- checking referral support ------------------------ //
+test("interop-market::ft-accruals", async (test) => {
+  const { root, alice, bob, newMarket: market, store } = test.context.accounts;
+  const wnear = await deployWnear(root);
+
+  const tokenId = await mintAndList({ alice, market, store, wnear });
+  await wrapNear({ account: alice, wnear, amount: "0.5" });
+  await wrapNear({ account: market, wnear, amount: "0.5" });
+  await wrapNear({ account: bob, wnear, amount: "3" });
+
+  // payouts below the minimum are accrued instead of being transferred
+  await root.call(
+    market,
+    "set_min_ft_payout",
+    { amount: nearToYocto("2") },
+    { attachedDeposit: "1" }
+  );
+  test.is(await market.view("get_min_ft_payout"), nearToYocto("2"));
+
+  const preAliceBalance = await getWnearBalance({ account: alice, wnear });
+  await bob.call(
+    wnear,
+    "ft_transfer_call",
+    {
+      receiver_id: market.accountId,
+      amount: nearToYocto("1"),
+      msg: JSON.stringify({
+        nft_contract_id: store.accountId,
+        token_id: tokenId,
+      }),
+    },
+    { attachedDeposit: "1", gas: Gas.parse("299 Tgas") }
+  );
+  test.true(
+    (await getWnearBalance({ account: alice, wnear })).eq(preAliceBalance)
+  );
+  test.is(
+    await market.view("get_ft_accruals", {
+      ft_contract_id: wnear.accountId,
+      account_id: alice.accountId,
+    }),
+    nearToYocto("0.975")
+  );
+
+  // accrued amounts can only be claimed once they reach the minimum
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        market,
+        "claim_ft_accruals",
+        { ft_contract_id: wnear.accountId },
+        { attachedDeposit: "1", gas: Gas.parse("50 Tgas") }
+      );
+    },
+    `Accrued amount of ${nearToYocto(
+      "0.975"
+    )} is below the minimum payout of ${nearToYocto("2")}`,
+    "Claiming accruals below minimum payout"
+  );
+
+  await root.call(
+    market,
+    "set_min_ft_payout",
+    { amount: nearToYocto("0.5") },
+    { attachedDeposit: "1" }
+  );
+  await alice.call(
+    market,
+    "claim_ft_accruals",
+    { ft_contract_id: wnear.accountId },
+    { attachedDeposit: "1", gas: Gas.parse("50 Tgas") }
+  );
+  test.true(
+    (await getWnearBalance({ account: alice, wnear })).eq(
+      preAliceBalance.add(nearToBn("0.975"))
+    )
+  );
+  test.is(
+    await market.view("get_ft_accruals", {
+      ft_contract_id: wnear.accountId,
+      account_id: alice.accountId,
+    }),
+    "0"
+  );
+});
+
 test("interop-market::ft-affiliate", async (test) => {
   const {
     root,
@@ -344,7 +512,7 @@ test("interop-market::ft-affiliate", async (test) => {
   } = test.context.accounts;
   const wnear = await deployWnear(root);
 
-  const tokenId = await mintAndList({ alice, market, store, wnear });
+  const tokenId = await mintAndList({ alice, market, store, wnear }, "2");
   await root.call(
     market,
     "add_affiliate",
@@ -404,7 +572,7 @@ test("interop-market::ft-payout", async (test) => {
   } = test.context.accounts;
   const wnear = await deployWnear(root);
 
-  const tokenId = await mintAndList({ alice, market, store, wnear });
+  const tokenId = await mintAndList({ alice, market, store, wnear }, "2");
   await alice.call(
     store,
     "set_split_owners",
@@ -456,6 +624,73 @@ test("interop-market::ft-payout", async (test) => {
   test.true(postCarolBalance.eq(preCarolBalance.sub(nearToBn("2"))));
 });
 
+test("interop-market::ft-payout-affiliate-overlap", async (test) => {
+  const {
+    root,
+    alice,
+    bob,
+    carol,
+    newMarket: market,
+    store,
+  } = test.context.accounts;
+  const wnear = await deployWnear(root);
+
+  const tokenId = await mintAndList({ alice, market, store, wnear }, "2");
+  await alice.call(
+    store,
+    "set_split_owners",
+    {
+      token_ids: [tokenId],
+      split_between: createPayouts([
+        [alice, 6000],
+        [bob, 4000],
+      ]),
+    },
+    { attachedDeposit: nearToYocto("0.0016") as string }
+  );
+  await root.call(
+    market,
+    "add_affiliate",
+    { account_id: bob.accountId, cut: 200 },
+    { attachedDeposit: "1" }
+  );
+  await wrapNear({ account: alice, wnear, amount: "0.5" });
+  await wrapNear({ account: market, wnear, amount: "0.5" });
+  await wrapNear({ account: bob, wnear, amount: "0.5" });
+  await wrapNear({ account: carol, wnear, amount: "3" });
+
+  const preAliceBalance = await getWnearBalance({ account: alice, wnear });
+  const preMarketBalance = await getWnearBalance({ account: market, wnear });
+  const preBobBalance = await getWnearBalance({ account: bob, wnear });
+  const preCarolBalance = await getWnearBalance({ account: carol, wnear });
+
+  // bob is both split owner and affiliate
+  await carol.call(
+    wnear,
+    "ft_transfer_call",
+    {
+      receiver_id: market.accountId,
+      amount: nearToYocto("2"),
+      msg: JSON.stringify({
+        nft_contract_id: store.accountId,
+        token_id: tokenId,
+        affiliate_id: bob.accountId,
+      }),
+    },
+    { attachedDeposit: "1", gas: Gas.parse("299 Tgas") }
+  );
+
+  const postAliceBalance = await getWnearBalance({ account: alice, wnear });
+  const postMarketBalance = await getWnearBalance({ account: market, wnear });
+  const postBobBalance = await getWnearBalance({ account: bob, wnear });
+  const postCarolBalance = await getWnearBalance({ account: carol, wnear });
+
+  test.true(postAliceBalance.eq(preAliceBalance.add(nearToBn("1.176"))));
+  test.true(postMarketBalance.eq(preMarketBalance.add(nearToBn("0.02"))));
+  test.true(postBobBalance.eq(preBobBalance.add(nearToBn("0.804"))));
+  test.true(postCarolBalance.eq(preCarolBalance.sub(nearToBn("2"))));
+});
+
 // -------------------------- checking edge cases --------------------------- //
 // TODO: move some of the edge cases down here
 // TODO: check logs for refund reasoning
@@ -569,3 +804,361 @@ test("interop-market::ft-updated-approval", async (test) => {
 
   await checkFailedBuy(test, { alice, bob, market, store, wnear }, token_id);
 });
+
+test("interop-market::ft-info", async (test) => {
+  const { root, alice, newMarket: market } = test.context.accounts;
+  const wnear = await deployWnear(root);
+
+  test.is(
+    await market.view("get_ft_info", { ft_contract_id: wnear.accountId }),
+    null
+  );
+
+  // only the owner may register FTs
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        market,
+        "register_ft",
+        { ft_contract_id: wnear.accountId, decimals: 24, symbol: "wNEAR" },
+        { attachedDeposit: "1" }
+      );
+    },
+    "Method is restricted to market owner",
+    "Registering FT as non-owner"
+  );
+
+  await root.call(
+    market,
+    "register_ft",
+    { ft_contract_id: wnear.accountId, decimals: 18, symbol: "WRONG" },
+    { attachedDeposit: "1" }
+  );
+  test.deepEqual(
+    await market.view("get_ft_info", { ft_contract_id: wnear.accountId }),
+    { decimals: 18, symbol: "WRONG" }
+  );
+
+  // refreshing fetches the FT metadata from the FT contract
+  test.true(
+    await root.call(
+      market,
+      "refresh_ft",
+      { ft_contract_id: wnear.accountId },
+      { attachedDeposit: "1", gas: new Gas("50000000000000") }
+    )
+  );
+  test.deepEqual(
+    await market.view("get_ft_info", { ft_contract_id: wnear.accountId }),
+    { decimals: 24, symbol: "wNEAR" }
+  );
+});
+
+test("interop-market::ft-counter-currency-offer", async (test) => {
+  const { root, alice, bob, carol, newMarket: market, store } =
+    test.context.accounts;
+  const wnear = await deployWnear(root);
+
+  // alice lists for NEAR
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
+  const tokenId = getTokenIds(mintCall)[0];
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.01") as string }
+  );
+  await alice.call(
+    store,
+    "nft_approve",
+    {
+      token_id: tokenId,
+      account_id: market.accountId,
+      msg: JSON.stringify({ price: nearToYocto("2") }),
+    },
+    {
+      attachedDeposit: nearToYocto("0.008") as string,
+      gas: Gas.parse("50 Tgas"),
+    }
+  );
+  await wrapNear({ account: alice, wnear, amount: "0.5" });
+  await wrapNear({ account: market, wnear, amount: "0.5" });
+  await wrapNear({ account: bob, wnear, amount: "2" });
+  const expiresAt = ((Date.now() + 3600_000) * 1_000_000).toString();
+  const makeFtOffer = () =>
+    bob.callRaw(
+      wnear,
+      "ft_transfer_call",
+      {
+        receiver_id: market.accountId,
+        amount: nearToYocto("1"),
+        msg: JSON.stringify({
+          nft_contract_id: store.accountId,
+          token_id: tokenId,
+          expires_at: expiresAt,
+        }),
+      },
+      { attachedDeposit: "1", gas: Gas.parse("100 Tgas") }
+    );
+
+  // NEAR offers on a NEAR listing need to use `buy`
+  await assertContractPanic(
+    test,
+    async () => {
+      await carol.call(
+        market,
+        "make_unlisted_offer",
+        {
+          nft_contract_id: store.accountId,
+          token_id: tokenId,
+          expires_at: expiresAt,
+        },
+        { attachedDeposit: nearToYocto("1.01") as string }
+      );
+    },
+    "This token is listed, use `buy` instead",
+    "Making a NEAR offer on a NEAR listing"
+  );
+
+  // offers are refunded for unregistered FTs and without storage deposit
+  const preBobBalance = await getWnearBalance({ account: bob, wnear });
+  const unregisteredCall = await makeFtOffer();
+  test.true(
+    unregisteredCall.logs.includes(
+      `Offers in FTs from ${wnear.accountId} are not accepted, refunding.`
+    )
+  );
+  await root.call(
+    market,
+    "register_ft",
+    { ft_contract_id: wnear.accountId, decimals: 24, symbol: "wNEAR" },
+    { attachedDeposit: "1" }
+  );
+  const noStorageCall = await makeFtOffer();
+  test.true(
+    noStorageCall.logs.includes("Storage for offer not covered, refunding.")
+  );
+  test.true(
+    (await getWnearBalance({ account: bob, wnear })).eq(preBobBalance)
+  );
+
+  // offers by banned accounts or on banned NFT contracts are refunded
+  await bob.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.01") as string }
+  );
+  for (const account of [bob, store]) {
+    await root.call(
+      market,
+      "ban",
+      { account_id: account.accountId },
+      { attachedDeposit: "1" }
+    );
+    const bannedCall = await makeFtOffer();
+    test.true(
+      bannedCall.logs.includes(
+        `${account.accountId} is banned from the market, refunding.`
+      )
+    );
+    await root.call(
+      market,
+      "unban",
+      { account_id: account.accountId },
+      { attachedDeposit: "1" }
+    );
+  }
+  test.true(
+    (await getWnearBalance({ account: bob, wnear })).eq(preBobBalance)
+  );
+
+  // bob offers 1 wNEAR on the NEAR listing
+  const offerCall = await makeFtOffer();
+  test.deepEqual(
+    offerCall.logs
+      .filter((log) => log.startsWith("EVENT_JSON:"))
+      .map(getEvent)
+      .find((event) => event.event === "nft_unlisted_offer").data,
+    {
+      nft_contract_id: store.accountId,
+      nft_token_id: tokenId,
+      offerer_id: bob.accountId,
+      currency: `ft::${wnear.accountId}`,
+      amount: nearToYocto("1"),
+      expires_at: expiresAt,
+    }
+  );
+  test.true(
+    (await getWnearBalance({ account: bob, wnear })).eq(
+      preBobBalance.sub(nearToBn("1"))
+    )
+  );
+  const offers: any = await market.view("get_unlisted_offers", {
+    nft_contract_id: store.accountId,
+    token_id: tokenId,
+  });
+  test.is(offers.length, 1);
+  test.is(offers[0].currency, `ft::${wnear.accountId}`);
+
+  // the escrowed offer cannot be rescued by the market owner
+  const rescueCall = await root.callRaw(
+    market,
+    "rescue_ft",
+    {
+      ft_contract_id: wnear.accountId,
+      amount: nearToYocto("1"),
+      receiver_id: root.accountId,
+    },
+    { attachedDeposit: "1", gas: Gas.parse("100 Tgas") }
+  );
+  test.is(
+    getPanic(rescueCall),
+    `Smart contract panicked: Cannot rescue ${nearToYocto("1")} of ${
+      wnear.accountId
+    }, only ${nearToYocto("0.5")} are not locked in offers or accruals`
+  );
+
+  // alice accepts, which replaces the listing and settles in wNEAR
+  const preAliceBalance = await getWnearBalance({ account: alice, wnear });
+  const token: any = await store.view("nft_token", { token_id: tokenId });
+  await alice.call(
+    market,
+    "accept_unlisted_offer",
+    {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+      offerer_id: bob.accountId,
+      approval_id: token.approved_account_ids[market.accountId],
+    },
+    { gas: Gas.parse("300 Tgas") }
+  );
+  test.is(
+    ((await store.view("nft_token", { token_id: tokenId })) as any).owner_id,
+    bob.accountId
+  );
+  test.true(
+    (await getWnearBalance({ account: alice, wnear })).eq(
+      preAliceBalance.add(nearToBn("0.975"))
+    )
+  );
+  test.is(
+    await market.view("get_listing", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    null
+  );
+  test.deepEqual(
+    await market.view("get_unlisted_offers", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    []
+  );
+});
+
+test("interop-market::rescue-ft", async (test) => {
+  const { root, alice, bob, newMarket: market, store } = test.context.accounts;
+  const wnear = await deployWnear(root);
+  await wrapNear({ account: market, wnear, amount: "0.5" });
+  await wrapNear({ account: bob, wnear, amount: "2" });
+
+  // bob bids 1 wNEAR on an auction, which is locked in the market
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
+  const tokenId = getTokenIds(mintCall)[0];
+  const endsAt = ((Date.now() + 3600_000) * 1_000_000).toString();
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.01") as string }
+  );
+  await alice.call(
+    store,
+    "nft_approve",
+    {
+      token_id: tokenId,
+      account_id: market.accountId,
+      msg: JSON.stringify({
+        price: nearToYocto("1"),
+        ft_contract: wnear.accountId,
+        auction_ends_at: endsAt,
+      }),
+    },
+    {
+      attachedDeposit: nearToYocto("0.008") as string,
+      gas: Gas.parse("50 Tgas"),
+    }
+  );
+  await bob.call(
+    wnear,
+    "ft_transfer_call",
+    {
+      receiver_id: market.accountId,
+      amount: nearToYocto("1"),
+      msg: JSON.stringify({
+        nft_contract_id: store.accountId,
+        token_id: tokenId,
+      }),
+    },
+    { attachedDeposit: "1", gas: Gas.parse("100 Tgas") }
+  );
+  test.true(
+    (await getWnearBalance({ account: market, wnear })).eq(nearToBn("1.5"))
+  );
+
+  const rescue = (account: NearAccount, amount: string) =>
+    account.callRaw(
+      market,
+      "rescue_ft",
+      {
+        ft_contract_id: wnear.accountId,
+        amount: nearToYocto(amount),
+        receiver_id: bob.accountId,
+      },
+      { attachedDeposit: "1", gas: Gas.parse("100 Tgas") }
+    );
+
+  // only the owner can rescue, and not the amount locked in the bid
+  test.is(
+    getPanic(await rescue(alice, "0.5")),
+    "Smart contract panicked: Method is restricted to market owner"
+  );
+  test.is(
+    getPanic(await rescue(root, "1")),
+    `Smart contract panicked: Cannot rescue ${nearToYocto("1")} of ${
+      wnear.accountId
+    }, only ${nearToYocto("0.5")} are not locked in offers or accruals`
+  );
+
+  // the stranded amount can be rescued
+  const preBobBalance = await getWnearBalance({ account: bob, wnear });
+  const rescueCall = await rescue(root, "0.5");
+  test.false(rescueCall.failed);
+  test.deepEqual(
+    rescueCall.logs
+      .filter((log) => log.startsWith("EVENT_JSON:"))
+      .map(getEvent)
+      .find((event) => event.event === "ft_rescue"),
+    {
+      standard: "mb_market",
+      version: "0.3.0",
+      event: "ft_rescue",
+      data: {
+        ft_contract_id: wnear.accountId,
+        amount: nearToYocto("0.5"),
+        receiver_id: bob.accountId,
+      },
+    }
+  );
+  test.true(
+    (await getWnearBalance({ account: bob, wnear })).eq(
+      preBobBalance.add(nearToBn("0.5"))
+    )
+  );
+  test.true(
+    (await getWnearBalance({ account: market, wnear })).eq(nearToBn("1"))
+  );
+});
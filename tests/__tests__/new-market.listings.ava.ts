@@ -71,6 +71,13 @@ test("interop-market::create-listing", async (test) => {
       current_offer: null,
     }
   );
+  test.is(
+    await market.view("get_offer", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    null
+  );
 
   // check that no withdrawal happens
   const preWithdrawBalance = await getBalance(alice);
@@ -170,3 +177,494 @@ test("interop-market::listing-deposit", async (test) => {
     "Smart contract panicked: Storage for listing not covered"
   );
 });
+
+test("interop-market::listing-lock", async (test) => {
+  const { root, alice, newMarket: market, store } = test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 2 });
+  const [tokenId1, tokenId2] = getTokenIds(mintCall);
+  const list = async (tokenId: string) =>
+    alice.call(
+      store,
+      "nft_approve",
+      {
+        token_id: tokenId,
+        account_id: market.accountId,
+        msg: JSON.stringify({ price: nearToYocto("1") }),
+      },
+      {
+        attachedDeposit: nearToYocto("0.008") as string,
+        gas: Gas.parse("50 Tgas"),
+      }
+    );
+  const unlist = async (tokenId: string) =>
+    alice.callRaw(
+      market,
+      "unlist",
+      { nft_contract_id: store.accountId, token_ids: [tokenId] },
+      { attachedDeposit: "1" }
+    );
+
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.02") as string }
+  );
+
+  // without lock, tokens can be unlisted right away
+  test.is(await market.view("get_listing_lock_seconds"), "0");
+  await list(tokenId1);
+  test.is(getPanic(await unlist(tokenId1)), undefined);
+
+  // with lock, unlisting has to wait
+  await root.call(
+    market,
+    "set_listing_lock_seconds",
+    { secs: "3600" },
+    { attachedDeposit: "1" }
+  );
+  await list(tokenId2);
+  test.regex(
+    getPanic(await unlist(tokenId2)) as string,
+    /Listing cannot be withdrawn before timestamp/
+  );
+
+  // removing the lock takes effect for existing listings
+  await root.call(
+    market,
+    "set_listing_lock_seconds",
+    { secs: "0" },
+    { attachedDeposit: "1" }
+  );
+  test.is(getPanic(await unlist(tokenId2)), undefined);
+  test.is(
+    await market.view("get_listings_count", { account: alice.accountId }),
+    "0"
+  );
+});
+
+test("interop-market::cleanup-stale-listing", async (test) => {
+  const {
+    alice,
+    bob,
+    carol,
+    newMarket: market,
+    store,
+  } = test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
+  const tokenId = getTokenIds(mintCall)[0];
+
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.01") as string }
+  );
+  await alice.call(
+    store,
+    "nft_approve",
+    {
+      token_id: tokenId,
+      account_id: market.accountId,
+      msg: JSON.stringify({ price: nearToYocto("1") }),
+    },
+    {
+      attachedDeposit: nearToYocto("0.008") as string,
+      gas: Gas.parse("50 Tgas"),
+    }
+  );
+
+  // valid listings cannot be removed
+  const cleanupValidCall = await carol.callRaw(
+    market,
+    "cleanup_stale_listing",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    { gas: Gas.parse("50 Tgas") }
+  );
+  test.is(
+    getPanic(cleanupValidCall),
+    "Smart contract panicked: Listing is still valid"
+  );
+
+  // transferring the token outside of the market invalidates the listing
+  await alice.call(
+    store,
+    "nft_transfer",
+    { receiver_id: bob.accountId, token_id: tokenId },
+    { attachedDeposit: "1" }
+  );
+  const cleanupCall = await carol.callRaw(
+    market,
+    "cleanup_stale_listing",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    { gas: Gas.parse("50 Tgas") }
+  );
+  test.deepEqual(getEvent(cleanupCall.logs[0]), {
+    standard: "mb_market",
+    version: "0.3.0",
+    event: "nft_unlist",
+    data: {
+      nft_contract_id: store.accountId,
+      nft_token_id: tokenId,
+      nft_approval_id: 0,
+      reason: "stale",
+      forced: false,
+    },
+  });
+  test.is(
+    await market.view("get_listing", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    null
+  );
+  test.is(
+    await market.view("get_listings_count", { account: alice.accountId }),
+    "0"
+  );
+});
+
+test("interop-market::force-unlist", async (test) => {
+  const { root, alice, newMarket: market, store } = test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
+  const tokenId = getTokenIds(mintCall)[0];
+
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.01") as string }
+  );
+  await alice.call(
+    store,
+    "nft_approve",
+    {
+      token_id: tokenId,
+      account_id: market.accountId,
+      msg: JSON.stringify({ price: nearToYocto("1") }),
+    },
+    {
+      attachedDeposit: nearToYocto("0.008") as string,
+      gas: Gas.parse("50 Tgas"),
+    }
+  );
+
+  // alice is locked out of unlisting
+  await root.call(
+    market,
+    "set_listing_lock_seconds",
+    { secs: "3600" },
+    { attachedDeposit: "1" }
+  );
+  const unlistCall = await alice.callRaw(
+    market,
+    "unlist",
+    { nft_contract_id: store.accountId, token_ids: [tokenId] },
+    { attachedDeposit: "1" }
+  );
+  test.regex(
+    getPanic(unlistCall) as string,
+    /Listing cannot be withdrawn before timestamp/
+  );
+
+  // only the market owner may force unlisting
+  const aliceForceCall = await alice.callRaw(
+    market,
+    "force_unlist",
+    { nft_contract_id: store.accountId, token_ids: [tokenId] },
+    { attachedDeposit: "1" }
+  );
+  test.is(
+    getPanic(aliceForceCall),
+    "Smart contract panicked: Method is restricted to market owner"
+  );
+
+  // the owner can unlist regardless of `listing_lock_seconds`
+  const forceCall = await root.callRaw(
+    market,
+    "force_unlist",
+    { nft_contract_id: store.accountId, token_ids: [tokenId] },
+    { attachedDeposit: "1" }
+  );
+  test.deepEqual(getEvent(forceCall.logs[0]), {
+    standard: "mb_market",
+    version: "0.3.0",
+    event: "nft_unlist",
+    data: {
+      nft_contract_id: store.accountId,
+      nft_token_id: tokenId,
+      nft_approval_id: 0,
+      reason: "forced",
+      forced: true,
+    },
+  });
+  test.is(
+    await market.view("get_listing", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    null
+  );
+  test.is(
+    await market.view("get_listings_count", { account: alice.accountId }),
+    "0"
+  );
+});
+
+test("interop-market::token-keys", async (test) => {
+  const { alice, newMarket: market } = test.context.accounts;
+
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.02") as string }
+  );
+  // the market trusts the caller of `nft_on_approve` to be the NFT contract
+  const listUnusual = (token_id: string) =>
+    alice.callRaw(market, "nft_on_approve", {
+      token_id,
+      owner_id: alice.accountId,
+      approval_id: 0,
+      msg: JSON.stringify({ price: nearToYocto("1") }),
+    });
+
+  // separators of other key formats are fine
+  for (const tokenId of ["1:2:3", "a<$b$>c"]) {
+    const listCall = await listUnusual(tokenId);
+    test.is(getPanic(listCall), undefined);
+    test.like(
+      await market.view("get_listing", {
+        nft_contract_id: alice.accountId,
+        token_id: tokenId,
+      }),
+      { nft_token_id: tokenId, nft_contract_id: alice.accountId }
+    );
+  }
+
+  // the token key separator itself is rejected
+  test.is(
+    getPanic(await listUnusual("1<$>2")),
+    "Smart contract panicked: Token IDs cannot contain the token key separator `<$>`"
+  );
+});
+
+test("interop-market::get-listings", async (test) => {
+  const { alice, bob, newMarket: market, store } = test.context.accounts;
+
+  const aliceTokenIds = getTokenIds(
+    await batchMint({ owner: alice, store, num_to_mint: 2 })
+  );
+  const bobTokenIds = getTokenIds(
+    await batchMint({
+      owner: alice,
+      store,
+      owner_id: bob.accountId,
+      num_to_mint: 1,
+    })
+  );
+  for (const [owner, tokenIds] of [
+    [alice, aliceTokenIds],
+    [bob, bobTokenIds],
+  ] as const) {
+    await owner.call(
+      market,
+      "deposit_storage",
+      {},
+      { attachedDeposit: nearToYocto("0.02") as string }
+    );
+    for (const token_id of tokenIds) {
+      await owner.call(
+        store,
+        "nft_approve",
+        {
+          token_id,
+          account_id: market.accountId,
+          msg: JSON.stringify({ price: nearToYocto("1") }),
+        },
+        {
+          attachedDeposit: nearToYocto("0.008") as string,
+          gas: Gas.parse("50 Tgas"),
+        }
+      );
+    }
+  }
+
+  const tokenIdsOf = (listings: any) =>
+    listings.map((listing: any) => listing.nft_token_id);
+  test.deepEqual(tokenIdsOf(await market.view("get_listings", {})), [
+    ...aliceTokenIds,
+    ...bobTokenIds,
+  ]);
+  test.deepEqual(
+    tokenIdsOf(
+      await market.view("get_listings", { from_index: "1", limit: 1 })
+    ),
+    [aliceTokenIds[1]]
+  );
+  test.deepEqual(
+    tokenIdsOf(
+      await market.view("get_listings_by_owner", {
+        owner_id: alice.accountId,
+        from_index: "1",
+      })
+    ),
+    [aliceTokenIds[1]]
+  );
+  test.deepEqual(
+    tokenIdsOf(
+      await market.view("get_listings_by_owner", { owner_id: bob.accountId })
+    ),
+    bobTokenIds
+  );
+});
+
+test("interop-market::external-ref", async (test) => {
+  const { alice, bob, newMarket: market, store } = test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 2 });
+  const tokenIds = getTokenIds(mintCall);
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.02") as string }
+  );
+  const list = (token_id: string, external_ref: string) =>
+    alice.callRaw(
+      store,
+      "nft_approve",
+      {
+        token_id,
+        account_id: market.accountId,
+        msg: JSON.stringify({ price: nearToYocto("1"), external_ref }),
+      },
+      {
+        attachedDeposit: nearToYocto("0.008") as string,
+        gas: Gas.parse("50 Tgas"),
+      }
+    );
+
+  // references are limited to 64 bytes
+  test.is(
+    getPanic(await list(tokenIds[1], "x".repeat(65))),
+    "Smart contract panicked: Cannot process external references with more than 64 bytes"
+  );
+
+  // reference is echoed in the listing event and the listing view
+  const listCall = await list(tokenIds[0], "proposal-42");
+  test.deepEqual(getEvent(listCall.logs[1]), {
+    standard: "mb_market",
+    version: "0.2.1",
+    event: "nft_list",
+    data: {
+      kind: "simple",
+      nft_contract_id: store.accountId,
+      nft_token_id: tokenIds[0],
+      nft_approval_id: 1,
+      nft_owner_id: alice.accountId,
+      currency: "near",
+      price: nearToYocto("1"),
+      external_ref: "proposal-42",
+    },
+  });
+  test.like(
+    await market.view("get_listing", {
+      nft_contract_id: store.accountId,
+      token_id: tokenIds[0],
+    }),
+    { nft_token_id: tokenIds[0], external_ref: "proposal-42" }
+  );
+
+  // ...and in the sale event
+  const buyCall = await bob.callRaw(
+    market,
+    "buy",
+    { nft_contract_id: store.accountId, token_id: tokenIds[0] },
+    { attachedDeposit: nearToYocto("1") as string, gas: Gas.parse("225 Tgas") }
+  );
+  const saleEvent = buyCall.logs
+    .filter((log) => log.startsWith("EVENT_JSON:"))
+    .map(getEvent)
+    .find((event) => event.event === "nft_sale");
+  test.is(saleEvent.data.external_ref, "proposal-42");
+});
+
+test("interop-market::stale-fee", async (test) => {
+  const { root, alice, newMarket: market, store } = test.context.accounts;
+
+  test.is(await market.view("get_stale_fee_per_day"), null);
+  const setFeeCall = await alice.callRaw(
+    market,
+    "set_stale_fee_per_day",
+    { fee: nearToYocto("0.001") },
+    { attachedDeposit: "1" }
+  );
+  test.is(
+    getPanic(setFeeCall),
+    "Smart contract panicked: Method is restricted to market owner"
+  );
+  await root.call(
+    market,
+    "set_stale_fee_per_day",
+    { fee: nearToYocto("0.001") },
+    { attachedDeposit: "1" }
+  );
+  test.is(await market.view("get_stale_fee_per_day"), nearToYocto("0.001"));
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
+  const tokenId = getTokenIds(mintCall)[0];
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.01") as string }
+  );
+  await alice.call(
+    store,
+    "nft_approve",
+    {
+      token_id: tokenId,
+      account_id: market.accountId,
+      msg: JSON.stringify({ price: nearToYocto("1") }),
+    },
+    {
+      attachedDeposit: nearToYocto("0.008") as string,
+      gas: Gas.parse("50 Tgas"),
+    }
+  );
+
+  // no fee accrues before a full day has passed
+  test.is(
+    await market.view("get_accrued_stale_fee", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    "0"
+  );
+  const preBalance = await getBalance(alice);
+  await alice.call(
+    market,
+    "unlist",
+    { nft_contract_id: store.accountId, token_ids: [tokenId] },
+    { attachedDeposit: "1" }
+  );
+  test.true(
+    diffCheck(
+      await getBalance(alice),
+      preBalance,
+      nearToBn("0.01"),
+      nearToBn("0.005") // gas
+    ),
+    "Storage deposit was not fully refunded"
+  );
+  await test.throwsAsync(
+    market.view("get_accrued_stale_fee", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    })
+  );
+});
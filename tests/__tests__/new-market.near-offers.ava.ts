@@ -6,18 +6,18 @@ import {
   diffCheck,
   nearToBn,
 } from "./utils/balances.js";
+import { getEvent } from "./utils/events.js";
 import { createPayouts } from "./utils/payouts.js";
-import { getPanic } from "./utils/panics.js";
-import setup from "./setup.js";
+import { assertContractPanic, getPanic } from "./utils/panics.js";
+import setup, { MB_VERSION } from "./setup.js";
 import { batchMint, getTokenIds } from "./utils/index.js";
 
 const test = setup(avaTest);
 
-const mintAndList = async ({
-  alice,
-  market,
-  store,
-}: Record<string, NearAccount>) => {
+const mintAndList = async (
+  { alice, market, store }: Record<string, NearAccount>,
+  price = "1"
+) => {
   const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
   const tokenId = getTokenIds(mintCall)[0];
 
@@ -34,7 +34,7 @@ const mintAndList = async ({
     {
       token_id: tokenId,
       account_id: market.accountId,
-      msg: JSON.stringify({ price: nearToYocto("1") }),
+      msg: JSON.stringify({ price: nearToYocto(price) }),
     },
     {
       attachedDeposit: nearToYocto("0.008") as string,
@@ -181,7 +181,7 @@ test("interop-market::near-affiliate", async (test) => {
     newMarket: market,
     store,
   } = test.context.accounts;
-  const tokenId = await mintAndList({ alice, market, store });
+  const tokenId = await mintAndList({ alice, market, store }, "10");
   await root.call(
     market,
     "add_affiliate",
@@ -246,7 +246,7 @@ test("interop-market::near-referrer", async (test) => {
     newMarket: market,
     store,
   } = test.context.accounts;
-  const tokenId = await mintAndList({ alice, market, store });
+  const tokenId = await mintAndList({ alice, market, store }, "10");
   await root.call(
     market,
     "add_affiliate",
@@ -307,7 +307,7 @@ test("interop-market::near-referrer", async (test) => {
 test("interop-market::near-payout", async (test) => {
   const { alice, bob, carol, newMarket: market, store } = test.context.accounts;
 
-  const tokenId = await mintAndList({ alice, market, store });
+  const tokenId = await mintAndList({ alice, market, store }, "10");
   await alice.call(
     store,
     "set_split_owners",
@@ -359,6 +359,327 @@ test("interop-market::near-payout", async (test) => {
   );
 });
 
+test("interop-market::near-payout-conservation", async (test) => {
+  const { alice, bob, newMarket: market, store } = test.context.accounts;
+
+  // a price that does not divide evenly by any cut
+  const price = "1.000000000000000000000007";
+  const tokenId = await mintAndList({ alice, market, store }, price);
+  const buyCall = await bob.callRaw(
+    market,
+    "buy",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    {
+      attachedDeposit: nearToYocto(price) as string,
+      gas: Gas.parse("225 Tgas"),
+    }
+  );
+  test.false(buyCall.failed);
+
+  // every yoctoNEAR of the offer is either paid out or the market cut
+  const saleEvent = buyCall.logs
+    .filter((log) => log.startsWith("EVENT_JSON:"))
+    .map(getEvent)
+    .find((event) => event.event === "nft_sale");
+  const payoutSum = Object.values(saleEvent.data.payout as string[]).reduce(
+    (sum, amount) => sum.add(new BN(amount)),
+    new BN(saleEvent.data.mintbase_amount)
+  );
+  test.is(payoutSum.toString(), nearToYocto(price));
+});
+
+test("interop-market::near-payout-affiliate-overlap", async (test) => {
+  const {
+    root,
+    alice,
+    bob,
+    carol,
+    newMarket: market,
+    store,
+  } = test.context.accounts;
+
+  const tokenId = await mintAndList({ alice, market, store }, "10");
+  await alice.call(
+    store,
+    "set_split_owners",
+    {
+      token_ids: [tokenId],
+      split_between: createPayouts([
+        [alice, 6000],
+        [bob, 4000],
+      ]),
+    },
+    { attachedDeposit: nearToYocto("0.0016") as string }
+  );
+  await root.call(
+    market,
+    "add_affiliate",
+    { account_id: bob.accountId, cut: 200 },
+    { attachedDeposit: "1" }
+  );
+
+  const preBobBalance = await getBalance(bob);
+
+  // bob is both split owner and affiliate
+  const buyCall = await carol.callRaw(
+    market,
+    "buy",
+    {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+      affiliate_id: bob.accountId,
+    },
+    { attachedDeposit: nearToYocto("10") as string, gas: Gas.parse("225 Tgas") }
+  );
+
+  // 3.92 from the split, 0.1 from the affiliate cut, in a single transfer
+  const postBobBalance = await getBalance(bob);
+  test.true(postBobBalance.eq(preBobBalance.add(nearToBn("4.02"))));
+  test.is(
+    buyCall.receipts_outcomes.filter(
+      (outcome) => outcome.executor_id === bob.accountId
+    ).length,
+    1
+  );
+});
+
+test("interop-market::near-payout-owner-cut-waived", async (test) => {
+  const {
+    root,
+    alice,
+    carol,
+    newMarket: market,
+    store,
+  } = test.context.accounts;
+
+  // alice becomes market owner and waives the cut on her own sales
+  await root.call(
+    market,
+    "propose_owner",
+    { new_owner: alice.accountId },
+    { attachedDeposit: "1" }
+  );
+  await alice.call(market, "accept_ownership", {}, { attachedDeposit: "1" });
+  await alice.call(
+    market,
+    "set_waive_owner_cut",
+    { waive: true },
+    { attachedDeposit: "1" }
+  );
+  test.true(await market.view("get_waive_owner_cut", {}));
+
+  const tokenId = await mintAndList({ alice, market, store }, "10");
+  const preAliceBalance = await getBalance(alice);
+
+  await carol.call(
+    market,
+    "buy",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    { attachedDeposit: nearToYocto("10") as string, gas: Gas.parse("225 Tgas") }
+  );
+
+  // full price for the sale, 0.01 storage refund
+  const postAliceBalance = await getBalance(alice);
+  test.true(postAliceBalance.eq(preAliceBalance.add(nearToBn("10.01"))));
+});
+
+test("interop-market::near-payout-max-share", async (test) => {
+  const {
+    root,
+    alice,
+    bob,
+    carol,
+    newMarket: market,
+    store,
+  } = test.context.accounts;
+
+  test.is(await market.view("get_max_total_payout_bps"), 10000);
+  await assertContractPanic(
+    test,
+    async () => {
+      await root.call(
+        market,
+        "set_max_total_payout_bps",
+        { bps: 10001 },
+        { attachedDeposit: "1" }
+      );
+    },
+    "Maximum payout share cannot exceed 10000 basis points",
+    "Setting payout share above 100%"
+  );
+  await root.call(
+    market,
+    "set_max_total_payout_bps",
+    { bps: 3000 },
+    { attachedDeposit: "1" }
+  );
+  test.is(await market.view("get_max_total_payout_bps"), 3000);
+
+  // 40% of the sale go to bob, which exceeds the maximum share
+  const tokenId = await mintAndList({ alice, market, store }, "10");
+  await alice.call(
+    store,
+    "set_split_owners",
+    {
+      token_ids: [tokenId],
+      split_between: createPayouts([
+        [alice, 6000],
+        [bob, 4000],
+      ]),
+    },
+    { attachedDeposit: nearToYocto("0.0016") as string }
+  );
+
+  const preBobBalance = await getBalance(bob);
+  const preCarolBalance = await getBalance(carol);
+
+  await carol.call(
+    market,
+    "buy",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    { attachedDeposit: nearToYocto("10") as string, gas: Gas.parse("225 Tgas") }
+  );
+
+  // sale rejected: carol refunded, bob not paid, NFT contract not banned
+  const postBobBalance = await getBalance(bob);
+  const postCarolBalance = await getBalance(carol);
+  test.true(postBobBalance.eq(preBobBalance));
+  test.true(
+    diffCheck(postCarolBalance, preCarolBalance, new BN(0), nearToBn("0.05"))
+  );
+  test.false(await market.view("is_banned", { account_id: store.accountId }));
+});
+
+test("interop-market::near-payout-split-owners", async (test) => {
+  const {
+    root,
+    alice,
+    bob,
+    carol,
+    newMarket: market,
+    store,
+  } = test.context.accounts;
+
+  await root.call(
+    market,
+    "set_max_total_payout_bps",
+    { bps: 5000 },
+    { attachedDeposit: "1" }
+  );
+
+  // 40% of the sale go to bob, which is within the maximum share
+  const tokenId = await mintAndList({ alice, market, store }, "10");
+  await alice.call(
+    store,
+    "set_split_owners",
+    {
+      token_ids: [tokenId],
+      split_between: createPayouts([
+        [alice, 6000],
+        [bob, 4000],
+      ]),
+    },
+    { attachedDeposit: nearToYocto("0.0016") as string }
+  );
+
+  const preBobBalance = await getBalance(bob);
+
+  await carol.call(
+    market,
+    "buy",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    { attachedDeposit: nearToYocto("10") as string, gas: Gas.parse("225 Tgas") }
+  );
+
+  // sale went through: bob receives 40% of the sale after the 2.5% cut
+  const postBobBalance = await getBalance(bob);
+  test.true(
+    diffCheck(postBobBalance, preBobBalance, nearToBn("3.9"), new BN(1))
+  );
+  test.like(await store.view("nft_token", { token_id: tokenId }), {
+    owner_id: carol.accountId,
+  });
+  test.false(await market.view("is_banned", { account_id: store.accountId }));
+});
+
+test("interop-market::near-transfer-fee", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, dave, newMarket: market, store } = test.context.accounts;
+
+  // sales are exempt from the store's transfer fee
+  await alice.call(
+    store,
+    "set_transfer_fee",
+    { transfer_fee: [dave.accountId, nearToYocto("0.1")] },
+    { attachedDeposit: "1" }
+  );
+  const tokenId = await mintAndList({ alice, market, store });
+  const preDaveBalance = await getBalance(dave);
+
+  const buyCall = await bob.callRaw(
+    market,
+    "buy",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    { attachedDeposit: nearToYocto("1") as string, gas: Gas.parse("225 Tgas") }
+  );
+  test.false(buyCall.failed);
+  const saleEvent = buyCall.logs
+    .filter((log) => log.startsWith("EVENT_JSON:"))
+    .map(getEvent)
+    .find((event) => event.event === "nft_sale");
+  test.truthy(saleEvent, "Sale did not go through");
+  test.is(
+    ((await store.view("nft_token", { token_id: tokenId })) as any).owner_id,
+    bob.accountId
+  );
+  test.true((await getBalance(dave)).eq(preDaveBalance));
+});
+
+test("interop-market::near-offer-above-ask", async (test) => {
+  const { alice, carol, newMarket: market, store } = test.context.accounts;
+  const tokenId = await mintAndList({ alice, market, store });
+
+  const preMarketBalance = await getBalance(market);
+  const preAliceBalance = await getBalance(alice);
+  const preCarolBalance = await getBalance(carol);
+
+  await carol.call(
+    market,
+    "buy",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    { attachedDeposit: nearToYocto("2") as string, gas: Gas.parse("225 Tgas") }
+  );
+
+  const postMarketBalance = await getBalance(market);
+  const postAliceBalance = await getBalance(alice);
+  const postCarolBalance = await getBalance(carol);
+
+  // fees only apply to the listing price
+  test.true(
+    diffCheck(
+      postMarketBalance,
+      preMarketBalance,
+      nearToBn("0.025"),
+      nearToBn("0.01") // -> storage + yocto + something else (not exact)
+    )
+  );
+  // 0.975 for the sale, 0.01 storage refund
+  test.true(postAliceBalance.eq(preAliceBalance.add(nearToBn("0.985"))));
+  // surplus has been refunded
+  test.true(
+    diffCheck(
+      postCarolBalance,
+      preCarolBalance,
+      nearToBn("1").neg(),
+      nearToBn("0.05")
+    )
+  );
+});
+
 // // -------------------------- checking edge cases --------------------------- //
 const checkFailedBuy = async (
   test: ExecutionContext,
@@ -377,12 +698,24 @@ const checkFailedBuy = async (
   const preAliceBalance = await getBalance(alice);
   const preBobBalance = await getBalance(bob);
   const preOwner = await getOwner({ token_id: tokenId });
-  await bob.call(
+  const buyCall = await bob.callRaw(
     market,
     "buy",
     { nft_contract_id: store.accountId, token_id: tokenId },
     { attachedDeposit: nearToYocto("2") as string, gas: Gas.parse("225 Tgas") }
   );
+  // the aborted sale is announced with the refunded offer
+  const failedEvent = buyCall.logs
+    .filter((log) => log.startsWith("EVENT_JSON:"))
+    .map(getEvent)
+    .find((event) => event.event === "nft_failed_listing");
+  test.is(failedEvent.version, "0.3.0");
+  test.like(failedEvent.data, {
+    nft_contract_id: store.accountId,
+    nft_token_id: tokenId,
+    offerer_id: bob.accountId,
+    amount: nearToYocto("1"),
+  });
   // owner did not change
   const postOwner = await getOwner({ token_id: tokenId });
   test.is(preOwner, postOwner);
@@ -465,3 +798,728 @@ test("interop-market::near-updated-approval", async (test) => {
 
   await checkFailedBuy(test, { alice, bob, market, store }, tokenId);
 });
+
+test("interop-market::near-concurrent-listing-changes", async (test) => {
+  const {
+    root,
+    alice,
+    carol,
+    newMarket: market,
+    store,
+  } = test.context.accounts;
+
+  // removing the offer while the sale executes must not break the payout
+  const tokenId = await mintAndList({ alice, market, store });
+  const [buyCall, removeCall] = await Promise.all([
+    carol.callRaw(
+      market,
+      "buy",
+      { nft_contract_id: store.accountId, token_id: tokenId },
+      {
+        attachedDeposit: nearToYocto("1") as string,
+        gas: Gas.parse("225 Tgas"),
+      }
+    ),
+    root.callRaw(
+      market,
+      "remove_offer",
+      { nft_contract_id: store.accountId, token_id: tokenId },
+      { attachedDeposit: "1" }
+    ),
+  ]);
+  test.false(buyCall.failed);
+  test.true(removeCall.failed);
+  test.is(
+    ((await store.view("nft_token", { token_id: tokenId })) as any).owner_id,
+    carol.accountId
+  );
+  test.is(
+    await market.view("get_listing", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    null
+  );
+
+  // either the unlisting or the sale goes through, never both
+  const otherTokenId = await mintAndList({ alice, market, store });
+  const [otherBuyCall, unlistCall] = await Promise.all([
+    carol.callRaw(
+      market,
+      "buy",
+      { nft_contract_id: store.accountId, token_id: otherTokenId },
+      {
+        attachedDeposit: nearToYocto("1") as string,
+        gas: Gas.parse("225 Tgas"),
+      }
+    ),
+    alice.callRaw(
+      market,
+      "unlist",
+      { nft_contract_id: store.accountId, token_ids: [otherTokenId] },
+      { attachedDeposit: "1" }
+    ),
+  ]);
+  // buying an unlisted token refunds without failing
+  const buyRefunded = otherBuyCall.logs.some((log) =>
+    log.includes('"event":"nft_refund_offer"')
+  );
+  test.false(otherBuyCall.failed);
+  test.not(buyRefunded, unlistCall.failed);
+  test.is(
+    ((await store.view("nft_token", { token_id: otherTokenId })) as any)
+      .owner_id,
+    unlistCall.failed ? carol.accountId : alice.accountId
+  );
+});
+
+test("interop-market::remove-offer-and-refund", async (test) => {
+  const { root, alice, newMarket: market, store } = test.context.accounts;
+
+  const tokenId = await mintAndList({ alice, market, store });
+  const removeOffer = (caller: NearAccount) =>
+    caller.callRaw(
+      market,
+      "remove_offer_and_refund",
+      { nft_contract_id: store.accountId, token_id: tokenId },
+      { attachedDeposit: "1" }
+    );
+
+  test.is(
+    getPanic(await removeOffer(alice)),
+    "Smart contract panicked: Method is restricted to market owner"
+  );
+  test.is(
+    getPanic(await removeOffer(root)),
+    "Smart contract panicked: Listing does not have an offer"
+  );
+});
+
+test("interop-market::near-buy-vanished-listing", async (test) => {
+  const { alice, bob, carol, newMarket: market, store } = test.context.accounts;
+
+  const tokenId = await mintAndList({ alice, market, store });
+  const buy = (buyer: NearAccount) =>
+    buyer.callRaw(
+      market,
+      "buy",
+      { nft_contract_id: store.accountId, token_id: tokenId },
+      {
+        attachedDeposit: nearToYocto("1") as string,
+        gas: Gas.parse("225 Tgas"),
+      }
+    );
+
+  // two buyers race, neither transaction fails
+  const [bobCall, carolCall] = await Promise.all([buy(bob), buy(carol)]);
+  test.false(bobCall.failed);
+  test.false(carolCall.failed);
+
+  const owner = ((await store.view("nft_token", { token_id: tokenId })) as any)
+    .owner_id;
+  const [winner, loserCall] =
+    owner === bob.accountId ? [bob, carolCall] : [carol, bobCall];
+  const loser = winner === bob ? carol : bob;
+  test.is(owner, winner.accountId);
+
+  // the slower buyer receives a refund and a clear signal
+  test.deepEqual(getEvent(loserCall.logs[0]), {
+    standard: "mb_market",
+    version: "0.3.0",
+    event: "nft_refund_offer",
+    data: {
+      nft_contract_id: store.accountId,
+      nft_token_id: tokenId,
+      offerer_id: loser.accountId,
+      currency: "near",
+      amount: nearToYocto("1"),
+      reason: "listing_not_found",
+    },
+  });
+
+  // buying after the sale completed behaves the same
+  const preBalance = await getBalance(loser);
+  const lateCall = await buy(loser);
+  test.false(lateCall.failed);
+  test.is(getEvent(lateCall.logs[0]).event, "nft_refund_offer");
+  test.true(
+    diffCheck(
+      await getBalance(loser),
+      preBalance,
+      nearToBn("0"),
+      nearToBn("0.01") // gas
+    )
+  );
+});
+
+test("interop-market::near-batch-buy", async (test) => {
+  const { alice, bob, newMarket: market, store } = test.context.accounts;
+
+  const tokenIds = [
+    await mintAndList({ alice, market, store }),
+    await mintAndList({ alice, market, store }),
+  ];
+  const batchBuy = (tokenIds: string[], deposit: string) =>
+    bob.callRaw(
+      market,
+      "batch_buy",
+      {
+        purchases: tokenIds.map((tokenId) => [
+          store.accountId,
+          tokenId,
+          null,
+        ]),
+      },
+      {
+        attachedDeposit: nearToYocto(deposit) as string,
+        gas: Gas.parse("300 Tgas"),
+      }
+    );
+
+  // the whole batch fails without any offer being inserted
+  test.is(
+    getPanic(await batchBuy(tokenIds, "1.9")),
+    `Smart contract panicked: Deposit needs to cover the sum of listing prices (${nearToYocto(
+      "2"
+    )})`
+  );
+  test.is(
+    getPanic(await batchBuy([tokenIds[0], tokenIds[0]], "2")),
+    `Smart contract panicked: Cannot buy token ${tokenIds[0]} twice`
+  );
+  test.is(
+    await market.view("get_offer", {
+      nft_contract_id: store.accountId,
+      token_id: tokenIds[0],
+    }),
+    null
+  );
+
+  // both tokens are bought, and the surplus is refunded
+  const preBobBalance = await getBalance(bob);
+  const buyCall = await batchBuy(tokenIds, "2.5");
+  test.false(buyCall.failed);
+  for (const token_id of tokenIds) {
+    test.is(
+      ((await store.view("nft_token", { token_id })) as any).owner_id,
+      bob.accountId
+    );
+  }
+  test.true(
+    diffCheck(
+      await getBalance(bob),
+      preBobBalance,
+      nearToBn("2").neg(),
+      nearToBn("0.05") // gas
+    )
+  );
+});
+
+test("interop-market::near-self-purchase", async (test) => {
+  const { root, alice, newMarket: market, store } = test.context.accounts;
+
+  const tokenId = await mintAndList({ alice, market, store });
+  test.false(await market.view("get_allow_self_purchase"));
+
+  // the lister is refunded instead of buying their own token
+  const buyCall = await alice.callRaw(
+    market,
+    "buy",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    {
+      attachedDeposit: nearToYocto("1") as string,
+      gas: Gas.parse("225 Tgas"),
+    }
+  );
+  test.false(buyCall.failed);
+  test.deepEqual(getEvent(buyCall.logs[0]), {
+    standard: "mb_market",
+    version: "0.3.0",
+    event: "nft_refund_offer",
+    data: {
+      nft_contract_id: store.accountId,
+      nft_token_id: tokenId,
+      offerer_id: alice.accountId,
+      currency: "near",
+      amount: nearToYocto("1"),
+      reason: "self_purchase",
+    },
+  });
+  const listing: any = await market.view("get_listing", {
+    nft_contract_id: store.accountId,
+    token_id: tokenId,
+  });
+  test.is(listing.current_offer, null);
+
+  // the market owner can allow self-purchases
+  const canBuy = () =>
+    market.view("can_buy", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+      buyer_id: alice.accountId,
+      deposit: nearToYocto("1"),
+    });
+  test.deepEqual(await canBuy(), {
+    ok: false,
+    reason: "Cannot buy your own listing",
+  });
+  await root.call(
+    market,
+    "set_allow_self_purchase",
+    { allow: true },
+    { attachedDeposit: "1" }
+  );
+  test.true(await market.view("get_allow_self_purchase"));
+  test.deepEqual(await canBuy(), { ok: true, reason: null });
+});
+
+test("interop-market::near-purchase-hook", async (test) => {
+  const { alice, bob, dave, newMarket: market, store } = test.context.accounts;
+
+  const tokenId = await mintAndList({ alice, market, store });
+  const buy = (msg: string) =>
+    bob.callRaw(
+      market,
+      "buy",
+      {
+        nft_contract_id: store.accountId,
+        token_id: tokenId,
+        on_purchase: [dave.accountId, msg],
+      },
+      {
+        attachedDeposit: nearToYocto("1") as string,
+        gas: Gas.parse("235 Tgas"),
+      }
+    );
+
+  test.is(
+    getPanic(await buy("x".repeat(257))),
+    "Smart contract panicked: Cannot process purchase messages with more than 256 bytes"
+  );
+
+  // dave has no contract, but the failing hook does not revert the sale
+  const buyCall = await buy("level-up");
+  test.false(buyCall.failed);
+  test.true(
+    buyCall.logs
+      .filter((log) => log.startsWith("EVENT_JSON:"))
+      .some((log) => getEvent(log).event === "nft_sale")
+  );
+  test.is(
+    ((await store.view("nft_token", { token_id: tokenId })) as any).owner_id,
+    bob.accountId
+  );
+});
+
+test("interop-market::can-buy", async (test) => {
+  const {
+    root,
+    alice,
+    bob,
+    carol,
+    newMarket: market,
+    store,
+  } = test.context.accounts;
+
+  const tokenId = await mintAndList({ alice, market, store });
+  const canBuy = (
+    buyer: NearAccount,
+    deposit: string,
+    currency: string | null = null
+  ) =>
+    market.view("can_buy", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+      buyer_id: buyer.accountId,
+      deposit,
+      currency,
+    });
+
+  test.deepEqual(await canBuy(bob, nearToYocto("1") as string), {
+    ok: true,
+    reason: null,
+  });
+  test.deepEqual(await canBuy(bob, nearToYocto("0.9") as string), {
+    ok: false,
+    reason: "Deposit needs to be higher than listing price",
+  });
+  test.deepEqual(
+    await canBuy(bob, nearToYocto("1") as string, "wrap.near"),
+    { ok: false, reason: "This NFT is listed for near" }
+  );
+  test.deepEqual(
+    await market.view("can_buy", {
+      nft_contract_id: store.accountId,
+      token_id: "1337",
+      buyer_id: bob.accountId,
+      deposit: nearToYocto("1"),
+    }),
+    { ok: false, reason: "Listing not found" }
+  );
+
+  await root.call(
+    market,
+    "ban",
+    { account_id: carol.accountId },
+    { attachedDeposit: "1" }
+  );
+  test.deepEqual(await canBuy(carol, nearToYocto("1") as string), {
+    ok: false,
+    reason: `${carol.accountId} is banned from the market`,
+  });
+});
+
+test("interop-market::auction", async (test) => {
+  const {
+    alice,
+    bob,
+    carol,
+    dave,
+    newMarket: market,
+    store,
+  } = test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 2 });
+  const [tokenId, unsoldTokenId] = getTokenIds(mintCall);
+  const endsAtMs = Date.now() + 15_000;
+  const endsAt = (endsAtMs * 1_000_000).toString();
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.02") as string }
+  );
+  for (const token_id of [tokenId, unsoldTokenId]) {
+    await alice.call(
+      store,
+      "nft_approve",
+      {
+        token_id,
+        account_id: market.accountId,
+        msg: JSON.stringify({
+          price: nearToYocto("1"),
+          auction_ends_at: endsAt,
+        }),
+      },
+      {
+        attachedDeposit: nearToYocto("0.008") as string,
+        gas: Gas.parse("50 Tgas"),
+      }
+    );
+  }
+  const listing: any = await market.view("get_listing", {
+    nft_contract_id: store.accountId,
+    token_id: tokenId,
+  });
+  test.deepEqual(listing.auction, {
+    ends_at: endsAt,
+    min_bid: nearToYocto("1"),
+  });
+
+  const bid = (bidder: NearAccount, amount: string) =>
+    bidder.callRaw(
+      market,
+      "buy",
+      { nft_contract_id: store.accountId, token_id: tokenId },
+      {
+        attachedDeposit: nearToYocto(amount) as string,
+        gas: Gas.parse("225 Tgas"),
+      }
+    );
+  const settle = (token_id: string) =>
+    dave.callRaw(
+      market,
+      "settle_auction",
+      { nft_contract_id: store.accountId, token_id },
+      { gas: Gas.parse("300 Tgas") }
+    );
+
+  // bids must reach the minimum and exceed the current bid
+  test.is(
+    getPanic(await bid(bob, "0.5")),
+    `Smart contract panicked: Bid needs to be at least ${nearToYocto("1")}`
+  );
+  const bobCall = await bid(bob, "1");
+  test.false(bobCall.failed);
+  test.is(getEvent(bobCall.logs[0]).event, "nft_make_offer");
+  test.is(getEvent(bobCall.logs[0]).data.price, nearToYocto("1"));
+  test.is(
+    getPanic(await bid(carol, "1")),
+    `Smart contract panicked: Bid needs to be higher than the current bid of ${nearToYocto(
+      "1"
+    )}`
+  );
+
+  // outbid bidders are refunded
+  const bobBalance = await getBalance(bob);
+  const carolCall = await bid(carol, "2");
+  test.false(carolCall.failed);
+  test.is(getEvent(carolCall.logs[1]).data.reason, "outbid");
+  test.true(
+    diffCheck(await getBalance(bob), bobBalance, nearToBn("1"), nearToBn("0"))
+  );
+  test.is(
+    getPanic(await settle(tokenId)),
+    `Smart contract panicked: Auction cannot be settled before timestamp ${Math.floor(
+      endsAtMs / 1000
+    )}`
+  );
+
+  // after the end, bids are rejected and anyone can settle
+  await new Promise((resolve) => setTimeout(resolve, 15_000));
+  test.is(
+    getPanic(await bid(bob, "3")),
+    "Smart contract panicked: Auction has ended"
+  );
+  const settleCall = await settle(tokenId);
+  test.false(settleCall.failed);
+  test.is(
+    ((await store.view("nft_token", { token_id: tokenId })) as any).owner_id,
+    carol.accountId
+  );
+  test.is(
+    await market.view("get_listing", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    null
+  );
+
+  // auctions without bids cannot be settled, but unlisted
+  test.is(
+    getPanic(await settle(unsoldTokenId)),
+    "Smart contract panicked: Auction has ended without bids, the listing can be removed via `unlist`"
+  );
+  await alice.call(
+    market,
+    "unlist",
+    { nft_contract_id: store.accountId, token_ids: [unsoldTokenId] },
+    { attachedDeposit: "1" }
+  );
+});
+
+// ----------------------- offers on unlisted tokens ------------------------ //
+test("interop-market::unlisted-offers", async (test) => {
+  const { alice, bob, carol, newMarket: market, store } = test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
+  const tokenId = getTokenIds(mintCall)[0];
+  const expiresAt = ((Date.now() + 3600_000) * 1_000_000).toString();
+
+  // carol offers 1 NEAR (plus 0.01 NEAR storage deposit)
+  await carol.call(
+    market,
+    "make_unlisted_offer",
+    {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+      expires_at: expiresAt,
+    },
+    { attachedDeposit: nearToYocto("1.01") as string }
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await carol.call(
+        market,
+        "make_unlisted_offer",
+        {
+          nft_contract_id: store.accountId,
+          token_id: tokenId,
+          expires_at: expiresAt,
+        },
+        { attachedDeposit: nearToYocto("1.01") as string }
+      );
+    },
+    "You already have an offer on this token",
+    "Making a second offer on the same token"
+  );
+
+  // bob offers and withdraws again
+  await bob.call(
+    market,
+    "make_unlisted_offer",
+    {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+      expires_at: expiresAt,
+    },
+    { attachedDeposit: nearToYocto("0.51") as string }
+  );
+  const preWithdrawBobBalance = await getBalance(bob);
+  await bob.call(
+    market,
+    "withdraw_unlisted_offer",
+    { nft_contract_id: store.accountId, token_id: tokenId },
+    { attachedDeposit: "1" }
+  );
+  test.true(
+    diffCheck(
+      await getBalance(bob),
+      preWithdrawBobBalance,
+      nearToBn("0.51"),
+      nearToBn("0.01")
+    )
+  );
+
+  const offers: any = await market.view("get_unlisted_offers", {
+    nft_contract_id: store.accountId,
+    token_id: tokenId,
+  });
+  test.is(offers.length, 1);
+  test.is(offers[0].offerer_id, carol.accountId);
+  test.is(offers[0].amount, nearToYocto("1"));
+
+  // alice approves the market without listing and accepts
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.01") as string }
+  );
+  await alice.call(
+    store,
+    "nft_approve",
+    { token_id: tokenId, account_id: market.accountId },
+    { attachedDeposit: nearToYocto("0.008") as string }
+  );
+  const token: any = await store.view("nft_token", { token_id: tokenId });
+  const approvalId = token.approved_account_ids[market.accountId];
+
+  // bob cannot accept for alice
+  await bob.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: nearToYocto("0.01") as string }
+  );
+  await bob.call(
+    market,
+    "accept_unlisted_offer",
+    {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+      offerer_id: carol.accountId,
+      approval_id: approvalId,
+    },
+    { gas: Gas.parse("300 Tgas") }
+  );
+  test.is(
+    (
+      (await market.view("get_unlisted_offers", {
+        nft_contract_id: store.accountId,
+        token_id: tokenId,
+      })) as any[]
+    ).length,
+    1
+  );
+
+  const preAliceBalance = await getBalance(alice);
+  await alice.call(
+    market,
+    "accept_unlisted_offer",
+    {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+      offerer_id: carol.accountId,
+      approval_id: approvalId,
+    },
+    { gas: Gas.parse("300 Tgas") }
+  );
+
+  test.is(
+    ((await store.view("nft_token", { token_id: tokenId })) as any).owner_id,
+    carol.accountId
+  );
+  test.deepEqual(
+    await market.view("get_unlisted_offers", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    []
+  );
+  test.is(
+    await market.view("get_listing", {
+      nft_contract_id: store.accountId,
+      token_id: tokenId,
+    }),
+    null
+  );
+  // 0.95 for the sale, 0.01 storage refund
+  test.true(
+    diffCheck(
+      await getBalance(alice),
+      preAliceBalance,
+      nearToBn("0.96"),
+      nearToBn("0.01")
+    )
+  );
+});
+
+test("interop-market::unlisted-offers-outbid", async (test) => {
+  const { root, alice, carol, newMarket: market, store } =
+    test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
+  const tokenId = getTokenIds(mintCall)[0];
+  const expiresAt = ((Date.now() + 3600_000) * 1_000_000).toString();
+  const offerArgs = {
+    nft_contract_id: store.accountId,
+    token_id: tokenId,
+    expires_at: expiresAt,
+  };
+  // 1 yoctoNEAR offers plus 0.01 NEAR storage deposit
+  const dustDeposit = new BN(nearToYocto("0.01") as string).addn(1).toString();
+
+  // fill up the offers on the token with dust
+  const offerers = await Promise.all(
+    [...Array(10).keys()].map((i) =>
+      root.createSubAccount(`offerer${i}`, {
+        initialBalance: nearToYocto("1") as string,
+      })
+    )
+  );
+  for (const offerer of offerers) {
+    await offerer.call(market, "make_unlisted_offer", offerArgs, {
+      attachedDeposit: dustDeposit,
+    });
+  }
+
+  // matching the lowest offer is not enough
+  await assertContractPanic(
+    test,
+    async () => {
+      await carol.call(market, "make_unlisted_offer", offerArgs, {
+        attachedDeposit: dustDeposit,
+      });
+    },
+    "Cannot make more than 10 offers on a single token without outbidding the lowest offer in the same currency",
+    "Making an offer that does not outbid the lowest offer"
+  );
+
+  // outbidding the lowest offer refunds it
+  const offerCall = await carol.callRaw(
+    market,
+    "make_unlisted_offer",
+    offerArgs,
+    { attachedDeposit: nearToYocto("0.51") as string }
+  );
+  test.deepEqual(getEvent(offerCall.logs[0]), {
+    standard: "mb_market",
+    version: "0.3.0",
+    event: "nft_remove_unlisted_offer",
+    data: {
+      nft_contract_id: store.accountId,
+      nft_token_id: tokenId,
+      offerer_id: offerers[0].accountId,
+      reason: "outbid",
+    },
+  });
+  const offers: any = await market.view("get_unlisted_offers", {
+    nft_contract_id: store.accountId,
+    token_id: tokenId,
+  });
+  test.is(offers.length, 10);
+  test.false(offers.some((o: any) => o.offerer_id === offerers[0].accountId));
+  test.true(offers.some((o: any) => o.offerer_id === carol.accountId));
+});
@@ -12,6 +12,7 @@ import {
   assertNoApproval,
   changeSettingsData,
   getTokenIds,
+  Tgas,
 } from "./utils/index.js";
 import { setup, CHANGE_SETTING_VERSION, MB_VERSION } from "./setup.js";
 
@@ -438,6 +439,69 @@ test("approvals::core", async (test) => {
     ],
     "revoking all"
   );
+  test.is(
+    await store.view("nft_approvals_count", { token_id: tokenIds[0] }),
+    2
+  );
+  test.is(
+    await store.view("nft_approvals_count", { token_id: tokenIds[1] }),
+    0
+  );
+});
+
+test("approvals::approve-and-return", async (test) => {
+  const { alice, bob, carol, store } = test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
+  const tokenIds = getTokenIds(mintCall);
+
+  test.is(
+    await alice.call(
+      store,
+      "nft_approve_and_return",
+      { token_id: tokenIds[0], account_id: bob.accountId },
+      { attachedDeposit: mNEAR(0.8) }
+    ),
+    0
+  );
+  test.is(
+    await alice.call(
+      store,
+      "nft_approve_and_return",
+      { token_id: tokenIds[0], account_id: carol.accountId },
+      { attachedDeposit: mNEAR(0.8) }
+    ),
+    1
+  );
+  await assertApprovals(
+    { test, store },
+    [
+      {
+        token_id: tokenIds[0],
+        approved_account_id: bob.accountId,
+        approval_id: 0,
+      },
+      {
+        token_id: tokenIds[0],
+        approved_account_id: carol.accountId,
+        approval_id: 1,
+      },
+    ],
+    "approving with returned approval ID"
+  );
+
+  test.is(
+    await store.view("estimate_approval_storage", { num_approvals: 2 }),
+    mNEAR(1.6).toString()
+  );
+
+  test.deepEqual(
+    await store.view("nft_approval_ids", {
+      token_ids: [tokenIds[0], MB_VERSION == "v1" ? "1000" : "1000:0"],
+      account_id: carol.accountId,
+    }),
+    [1, null]
+  );
 });
 
 test("approvals::minting", async (test) => {
@@ -453,6 +517,8 @@ test("approvals::minting", async (test) => {
     MB_VERSION === "v1" ? "check_is_minter" : "check_is_creator";
   const LIST_MINTERS_METHOD =
     MB_VERSION === "v1" ? "list_minters" : "list_creators";
+  const MINTERS_COUNT_METHOD =
+    MB_VERSION === "v1" ? "minters_count" : "creators_count";
 
   // ---------------------------- authorized mint ----------------------------
   // TODO::store::low: this increases storage, shouldn't it then require
@@ -517,6 +583,15 @@ test("approvals::minting", async (test) => {
     [alice.accountId, bob.accountId],
     "Bad minters list after granting minting rigths to Bob"
   );
+  test.deepEqual(
+    await store.view(`${LIST_MINTERS_METHOD}_paginated`, {
+      from_index: 1,
+      limit: 1,
+    }),
+    [bob.accountId],
+    "Bad minters page after granting minting rigths to Bob"
+  );
+  test.is(await store.view(MINTERS_COUNT_METHOD), 2);
 
   // actual minting
   // TODO::store::low: shouldn't third party minting require deposits to
@@ -909,3 +984,30 @@ test.skip("approvals::capping", async (test) => {
     {}
   );
 });
+
+test("approvals::transfer-call-revert", async (test) => {
+  const { alice, bob, carol, store } = test.context.accounts;
+
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 1 });
+  const tokenIds = getTokenIds(mintCall);
+  await alice.call(
+    store,
+    "nft_approve",
+    { token_id: tokenIds[0], account_id: bob.accountId },
+    { attachedDeposit: mNEAR(0.8) }
+  );
+
+  // carol has no contract deployed, so `nft_on_transfer` fails and the
+  // transfer is reverted
+  await alice.call(
+    store,
+    "nft_transfer_call",
+    { receiver_id: carol.accountId, token_id: tokenIds[0], msg: "" },
+    { attachedDeposit: "1", gas: Tgas(200) }
+  );
+
+  test.like(await store.view("nft_token", { token_id: tokenIds[0] }), {
+    owner_id: alice.accountId,
+    approved_account_ids: { [bob.accountId]: 0 },
+  });
+});
@@ -406,3 +406,211 @@ test("batch-mint", async (test) => {
   // @ts-ignore
   test.is(typeof mintCall.status.SuccessValue, "string");
 });
+
+test("batch-mint-to", async (test) => {
+  if (MB_VERSION == "v2") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+
+  await alice
+    .call(
+      store,
+      "nft_batch_mint_to",
+      {
+        owner_token_pairs: [
+          [alice.accountId, 1],
+          [bob.accountId, 2],
+        ],
+        metadata: {},
+      },
+      { attachedDeposit: mintingDeposit({ n_tokens: 3 }) }
+    )
+    .then(async (mintCall) => {
+      assertEventLogs(
+        test,
+        (mintCall as TransactionResult).logs,
+        [
+          {
+            standard: "nep171",
+            version: "1.0.0",
+            event: "nft_mint",
+            data: [
+              {
+                owner_id: alice.accountId,
+                token_ids: ["0"],
+                memo: JSON.stringify({
+                  royalty: null,
+                  split_owners: null,
+                  meta_id: null,
+                  meta_extra: null,
+                  minter: alice.accountId,
+                }),
+              },
+            ],
+          },
+          {
+            standard: "nep171",
+            version: "1.0.0",
+            event: "nft_mint",
+            data: [
+              {
+                owner_id: bob.accountId,
+                token_ids: ["1", "2"],
+                memo: JSON.stringify({
+                  royalty: null,
+                  split_owners: null,
+                  meta_id: null,
+                  meta_extra: null,
+                  minter: alice.accountId,
+                }),
+              },
+            ],
+          },
+        ],
+        "batch minting to multiple owners"
+      );
+    })
+    .catch(failPromiseRejection(test, "batch minting to multiple owners"));
+
+  await assertContractTokenOwners(
+    { test, store },
+    [
+      { token_id: "0", owner_id: alice.accountId },
+      { token_id: "1", owner_id: bob.accountId },
+      { token_id: "2", owner_id: bob.accountId },
+    ],
+    "batch minting to multiple owners"
+  );
+});
+
+test("nft-mint", async (test) => {
+  if (MB_VERSION == "v2") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+
+  await assertContractPanics(test, [
+    // token IDs cannot be chosen freely
+    [
+      async () => {
+        await alice.call(
+          store,
+          "nft_mint",
+          {
+            token_id: "1",
+            receiver_id: bob.accountId,
+            token_metadata: {},
+          },
+          { attachedDeposit: mintingDeposit({ n_tokens: 1 }) }
+        );
+      },
+      "Token IDs are assigned sequentially, the next token ID is 0",
+      "Minting with unexpected token ID",
+    ],
+    // only minters may mint
+    [
+      async () => {
+        await bob.call(
+          store,
+          "nft_mint",
+          {
+            token_id: "0",
+            receiver_id: bob.accountId,
+            token_metadata: {},
+          },
+          { attachedDeposit: mintingDeposit({ n_tokens: 1 }) }
+        );
+      },
+      `${bob.accountId} is not allowed to mint on this store`,
+      "Bob tried minting without minter permission",
+    ],
+  ]);
+
+  const mintCall = await alice.callRaw(
+    store,
+    "nft_mint",
+    { token_id: "0", receiver_id: bob.accountId, token_metadata: {} },
+    { attachedDeposit: mintingDeposit({ n_tokens: 1 }) }
+  );
+  assertEventLogs(
+    test,
+    mintCall.logs,
+    [
+      {
+        standard: "nep171",
+        version: "1.0.0",
+        event: "nft_mint",
+        data: [
+          {
+            owner_id: bob.accountId,
+            token_ids: ["0"],
+            memo: JSON.stringify({
+              royalty: null,
+              split_owners: null,
+              meta_id: null,
+              meta_extra: null,
+              minter: alice.accountId,
+            }),
+          },
+        ],
+      },
+    ],
+    "minting via nft_mint"
+  );
+  await assertContractTokenOwners(
+    { test, store },
+    [{ token_id: "0", owner_id: bob.accountId }],
+    "minting via nft_mint"
+  );
+});
+
+test("send-all", async (test) => {
+  const { alice, bob, store } = test.context.accounts;
+
+  const tokenIds = getTokenIds(
+    await batchMint({ owner: alice, store, num_to_mint: 3 })
+  );
+
+  const sendCall = await alice
+    .callRaw(
+      store,
+      "nft_send_all",
+      { token_ids: tokenIds, receiver_id: bob.accountId },
+      { attachedDeposit: "1" }
+    )
+    .catch(failPromiseRejection(test, "sending all tokens"));
+
+  // a single transfer log for all tokens
+  assertEventLogs(
+    test,
+    (sendCall as TransactionResult).logs,
+    [
+      {
+        standard: "nep171",
+        version: "1.0.0",
+        event: "nft_transfer",
+        data: [
+          {
+            authorized_id: null,
+            old_owner_id: alice.accountId,
+            new_owner_id: bob.accountId,
+            token_ids: tokenIds,
+            memo: null,
+          },
+        ],
+      },
+    ],
+    "sending all tokens"
+  );
+
+  await assertContractTokenOwners(
+    { test, store },
+    tokenIds.map((token_id) => ({ token_id, owner_id: bob.accountId })),
+    "After sending all tokens"
+  ).catch(failPromiseRejection(test, "checking token ownership"));
+});
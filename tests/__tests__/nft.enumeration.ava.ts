@@ -1,6 +1,11 @@
 import avaTest from "ava";
-import { assertTokensAre, batchMint, getTokenIds } from "./utils/index.js";
-import { setup } from "./setup.js";
+import {
+  assertTokensAre,
+  batchMint,
+  getTokenIds,
+  mNEAR,
+} from "./utils/index.js";
+import { MB_VERSION, setup } from "./setup.js";
 import { TransactionResult } from "near-workspaces";
 
 const test = setup(avaTest);
@@ -138,4 +143,37 @@ test("enumeration", async (test) => {
     [{ token_id: bobTokenIds[1], owner_id: bob.accountId }],
     "`nft_tokens_for_owner({})` output is wrong after burning"
   );
+
+  // burned tokens can be told apart from tokens that were never minted
+  test.is(
+    await store.view("nft_token_status", { token_id: bobTokenIds[0] }),
+    "Burned"
+  );
+  test.is(
+    await store.view("nft_token_status", { token_id: bobTokenIds[1] }),
+    "Exists"
+  );
+  test.is(
+    await store.view("nft_token_status", {
+      token_id: MB_VERSION == "v1" ? "1000" : "1000:0",
+    }),
+    "Never"
+  );
+
+  if (MB_VERSION == "v1") return;
+
+  // call `nft_tokens_for_owner_with_approvals` after approving Alice
+  await bob.call(
+    store,
+    "nft_approve",
+    { token_id: bobTokenIds[1], account_id: alice.accountId },
+    { attachedDeposit: mNEAR(0.8) }
+  );
+  const tokensWithApprovals: any[] = await store.view(
+    "nft_tokens_for_owner_with_approvals",
+    { account_id: bob.accountId, limit: 100 }
+  );
+  test.is(tokensWithApprovals.length, 1);
+  test.is(tokensWithApprovals[0].token.token_id, bobTokenIds[1]);
+  test.deepEqual(tokensWithApprovals[0].approvals, { [alice.accountId]: 0 });
 });
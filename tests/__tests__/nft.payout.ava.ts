@@ -1,5 +1,10 @@
 import avaTest from "ava";
-import { NEAR, mintingDeposit } from "./utils/index.js";
+import {
+  NEAR,
+  mintingDeposit,
+  assertContractPanic,
+  Tgas,
+} from "./utils/index.js";
 import { MB_VERSION, setup } from "./setup.js";
 import { NearAccount } from "near-workspaces";
 
@@ -201,6 +206,68 @@ test("payout::low_balance", async (test) => {
   );
 });
 
+test("payout::single_split", async (test) => {
+  const { alice, store } = test.context.accounts;
+
+  const tokenId = await mint({
+    alice,
+    store,
+    split_owners: { "treasury.near": 10000 },
+  });
+
+  test.deepEqual(
+    await store.view("nft_payout", {
+      token_id: tokenId,
+      balance: "10000",
+    }),
+    { payout: { "treasury.near": "10000" } }
+  );
+});
+
+test("payout::splits_transfer_call_revert", async (test) => {
+  const { alice, bob, store } = test.context.accounts;
+
+  const tokenId = await mint({
+    alice,
+    store,
+    split_owners: { "a.near": 6000, "b.near": 4000 },
+  });
+
+  // bob has no contract deployed, so `nft_on_transfer` fails and the
+  // transfer is reverted, which needs to restore the split owners
+  await alice.call(
+    store,
+    "nft_transfer_call",
+    { receiver_id: bob.accountId, token_id: tokenId, msg: "" },
+    { attachedDeposit: "1", gas: Tgas(200) }
+  );
+
+  test.deepEqual(
+    await store.view("nft_payout", {
+      token_id: tokenId,
+      balance: "10000",
+    }),
+    { payout: { "a.near": "6000", "b.near": "4000" } }
+  );
+});
+
+test("payout::invalid_splits", async (test) => {
+  const { alice, store } = test.context.accounts;
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await mint({
+        alice,
+        store,
+        split_owners: { "a.near": 6000, "b.near": 3000 },
+      });
+    },
+    "Splits numerators must sum up to 10_000",
+    "Setting splits with invalid sum"
+  );
+});
+
 test("payout::max_len", async (test) => {
   const { alice, store } = test.context.accounts;
 
@@ -7,7 +7,12 @@ import {
   changeSettingsData,
   assertContractPanic,
   NEAR,
+  mNEAR,
   Tgas,
+  getBalance,
+  assertBalanceChanges,
+  batchMint,
+  getTokenIds,
 } from "./utils/index.js";
 import {
   setup,
@@ -248,6 +253,45 @@ test("v2::minting_cap", async (test) => {
     "This mint would exceed the smart contracts minting cap",
     "Minting beyond cap"
   );
+
+  // minting up to the cap emits an event
+  test.false(await store.view("is_minted_out"));
+  const mintOutCall = await mintOnMetadata({
+    bob: alice,
+    store,
+    args: {
+      metadata_id: "1",
+      owner_id: alice.accountId,
+      num_to_mint: 2,
+    },
+    deposit: 0.05,
+  });
+  const mintOutEvent = (mintOutCall as TransactionResult).logs
+    .filter((log) => log.startsWith("EVENT_JSON:"))
+    .map((log) => JSON.parse(log.slice("EVENT_JSON:".length)))
+    .find((event) => event.event === "minting_cap_reached");
+  test.deepEqual(mintOutEvent, {
+    standard: "mb_store",
+    version: "2.0.0",
+    event: "minting_cap_reached",
+    data: { cap: "2" },
+  });
+  test.true(await store.view("is_minted_out"));
+
+  // cannot lower the cap below minted tokens
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        store,
+        "reduce_minting_cap",
+        { new_cap: 1 },
+        { attachedDeposit: "1" }
+      );
+    },
+    "Cannot set minting cap lower than already minted tokens",
+    "Reducing minting cap below minted tokens"
+  );
   // TODO: (low priority) cannot set cap beyond already minted tokens
   // TODO: (low priority) requires yoctoNEAR deposit
 });
@@ -444,6 +488,15 @@ test("v2::mint_on_metadata", async (test) => {
     "Attached deposit does not cover the total price of 10000000000000000000000 yoctoNEAR",
     "Minting with insufficient deposit"
   );
+
+  test.is(
+    await store.view("nft_token_metadata_id", { token_id: "0:12" }),
+    "0"
+  );
+  test.is(
+    await store.view("nft_token_metadata_id", { token_id: "0:13" }),
+    null
+  );
 });
 
 test("v2::minters_allowlist", async (test) => {
@@ -563,6 +616,12 @@ test("v2::minters_allowlist_unique_minters", async (test) => {
     ],
     "creating metadata with unique minters"
   );
+  test.false(
+    await store.view("has_minted", {
+      metadata_id: "0",
+      account_id: bob.accountId,
+    })
+  );
 
   // bob can mint
   await mintOnMetadata({
@@ -575,6 +634,18 @@ test("v2::minters_allowlist_unique_minters", async (test) => {
     },
     deposit: 0.05,
   });
+  test.true(
+    await store.view("has_minted", {
+      metadata_id: "0",
+      account_id: bob.accountId,
+    })
+  );
+  test.false(
+    await store.view("has_minted", {
+      metadata_id: "0",
+      account_id: alice.accountId,
+    })
+  );
 
   await assertContractPanic(
     test,
@@ -690,6 +761,119 @@ test("v2::royalties", async (test) => {
       },
     }
   );
+
+  test.deepEqual(await store.view("nft_token_royalty", { token_id: "0:0" }), {
+    split_between: {
+      "a.near": { numerator: 6000 },
+      "b.near": { numerator: 4000 },
+    },
+    percentage: { numerator: 2000 },
+  });
+});
+
+test("v2::royalty_templates", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  const royalty = {
+    split_between: {
+      "a.near": { numerator: 6000 },
+      "b.near": { numerator: 4000 },
+    },
+    percentage: { numerator: 2000 },
+  };
+
+  // only creators can register templates
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "register_royalty_template",
+        {
+          name: "default",
+          royalty_args: {
+            split_between: { "a.near": 6000, "b.near": 4000 },
+            percentage: 2000,
+          },
+        },
+        { attachedDeposit: NEAR(0.01) }
+      );
+    },
+    `${bob.accountId} is not allowed to create metadata`,
+    "Non-creator registering royalty template"
+  );
+
+  await alice.call(
+    store,
+    "register_royalty_template",
+    {
+      name: "default",
+      royalty_args: {
+        split_between: { "a.near": 6000, "b.near": 4000 },
+        percentage: 2000,
+      },
+    },
+    { attachedDeposit: NEAR(0.01) }
+  );
+  test.deepEqual(await store.view("get_royalty_templates"), {
+    default: royalty,
+  });
+
+  // templates cannot be overwritten
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        store,
+        "register_royalty_template",
+        {
+          name: "default",
+          royalty_args: {
+            split_between: { "a.near": 10000 },
+            percentage: 1000,
+          },
+        },
+        { attachedDeposit: NEAR(0.01) }
+      );
+    },
+    "Royalty template default already exists",
+    "Overwriting royalty template"
+  );
+
+  // metadata can only use existing templates
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        store,
+        "create_metadata",
+        { metadata: {}, royalty_template: "unknown", price: NEAR(0.01) },
+        { attachedDeposit: NEAR(0.1) }
+      );
+    },
+    "Royalty template unknown does not exist",
+    "Using nonexistent royalty template"
+  );
+
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, royalty_template: "default", price: NEAR(0.01) },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+  test.deepEqual(
+    await store.view("nft_token_royalty", { token_id: "0:0" }),
+    royalty
+  );
 });
 
 test("v2::per_metadata_max_supply", async (test) => {
@@ -1363,3 +1547,1812 @@ test("v2::no_token_id_reuse", async (test) => {
     "minting on metadata metadata"
   );
 });
+
+test("v2::repair_reference", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: { reference: "broken" }, price: NEAR(0.01) },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 2, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+  await alice.call(
+    store,
+    "lock_metadata",
+    { metadata_id: "0" },
+    { attachedDeposit: "1" }
+  );
+
+  const hash = Buffer.alloc(32, 1).toString("base64");
+  const repair = (account: NearAccount, args: Record<string, any>) =>
+    account.call(
+      store,
+      "repair_reference",
+      {
+        metadata_id: "0",
+        new_reference: "fixed",
+        new_reference_hash: hash,
+        ...args,
+      },
+      { attachedDeposit: "1" }
+    );
+
+  // only the creator may repair, and storage growth is bounded
+  await assertContractPanic(
+    test,
+    async () => {
+      await repair(bob, {});
+    },
+    "This method can only be called by the metadata creator",
+    "Repairing reference as non-creator"
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await repair(alice, { new_reference: "a".repeat(257) });
+    },
+    "Reference cannot exceed 256 bytes",
+    "Repairing with an overlong reference"
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await repair(alice, { new_reference_hash: "AQID" });
+    },
+    "Reference hash must be a sha256 hash",
+    "Repairing with an invalid reference hash"
+  );
+
+  // a single event covers all tokens of the metadata
+  const repairCall = await alice.callRaw(
+    store,
+    "repair_reference",
+    { metadata_id: "0", new_reference: "fixed", new_reference_hash: hash },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    repairCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: "2.0.0",
+        event: "repair_reference",
+        data: { metadata_id: "0", reference: "fixed", reference_hash: hash },
+      },
+    ],
+    "repairing reference"
+  );
+  test.like(await store.view("nft_token", { token_id: "0:1" }), {
+    metadata: { reference: "fixed", reference_hash: hash },
+  });
+
+  // repairs can be disabled by the store owner
+  await alice.call(
+    store,
+    "disable_reference_repairs",
+    {},
+    { attachedDeposit: "1" }
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await repair(alice, {});
+    },
+    "Reference repairs are disabled on this contract",
+    "Repairing reference after disabling repairs"
+  );
+});
+
+test("v2::transfer_cooldown", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, carol, store } = test.context.accounts;
+  await createMetadata({
+    alice,
+    store,
+    args: {
+      metadata: {},
+      price: NEAR(0.01),
+      transfer_cooldown_seconds: 3600,
+    },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: {
+      metadata_id: "0",
+      num_to_mint: 2,
+      owner_id: bob.accountId,
+    },
+    deposit: 0.05,
+  });
+
+  const remaining: number = await store.view(
+    "get_transfer_cooldown_remaining",
+    { token_id: "0:0" }
+  );
+  test.true(remaining > 0 && remaining <= 3600);
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "nft_transfer",
+        { receiver_id: carol.accountId, token_id: "0:0" },
+        { attachedDeposit: "1" }
+      );
+    },
+    "Token 0:0 cannot be transferred during its cooldown",
+    "Transferring during cooldown"
+  );
+
+  // burning is exempt from the cooldown
+  await bob.call(
+    store,
+    "nft_batch_burn",
+    { token_ids: ["0:1"] },
+    { attachedDeposit: "1" }
+  );
+  test.is(await store.view("nft_token", { token_id: "0:1" }), null);
+
+  // ...and so are transfers to the burn sink
+  await alice.call(
+    store,
+    "set_burn_sink_account",
+    { account_id: "burn.near" },
+    { attachedDeposit: "1" }
+  );
+  await bob.call(
+    store,
+    "nft_transfer",
+    { receiver_id: "burn.near", token_id: "0:0" },
+    { attachedDeposit: "1" }
+  );
+  test.is(await store.view("nft_token_status", { token_id: "0:0" }), "Burned");
+});
+
+test("v2::nft_gift", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, carol, store } = test.context.accounts;
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01) },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+
+  // only the owner can gift a token
+  await test.throwsAsync(
+    alice.call(
+      store,
+      "nft_gift",
+      { receiver_id: alice.accountId, token_id: "0:0" },
+      { attachedDeposit: "1" }
+    )
+  );
+
+  const giftCall = await bob.callRaw(
+    store,
+    "nft_gift",
+    { receiver_id: carol.accountId, token_id: "0:0", memo: "Congrats!" },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    giftCall.logs,
+    [
+      {
+        standard: "nep171",
+        version: "1.0.0",
+        event: "nft_transfer",
+        data: [
+          {
+            authorized_id: null,
+            old_owner_id: bob.accountId,
+            new_owner_id: carol.accountId,
+            token_ids: ["0:0"],
+            memo: JSON.stringify({ gift: true, memo: "Congrats!" }),
+          },
+        ],
+      },
+    ],
+    "gifting a token"
+  );
+  test.like(await store.view("nft_token", { token_id: "0:0" }), {
+    owner_id: carol.accountId,
+  });
+});
+
+test("v2::batch_transfer_call", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 21 });
+  const tokenIds = getTokenIds(mintCall);
+
+  // batches are capped to keep the callback within its gas
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        store,
+        "nft_batch_transfer_call",
+        { token_ids: tokenIds, receiver_id: bob.accountId, msg: "" },
+        { attachedDeposit: "1", gas: Tgas(300) }
+      );
+    },
+    "Cannot transfer more than 20 tokens at once",
+    "Transferring too many tokens at once"
+  );
+
+  // bob has no contract to accept the tokens, so all are returned
+  const transferCall = await alice.callRaw(
+    store,
+    "nft_batch_transfer_call",
+    { token_ids: tokenIds.slice(0, 20), receiver_id: bob.accountId, msg: "" },
+    { attachedDeposit: "1", gas: Tgas(300) }
+  );
+  test.deepEqual(transferCall.parseResult(), []);
+  for (const token_id of tokenIds.slice(0, 20)) {
+    test.like(await store.view("nft_token", { token_id }), {
+      owner_id: alice.accountId,
+    });
+  }
+});
+
+test("v2::nft_tokens_by_extra", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  for (const extra of ["background:red", "background:blue"]) {
+    await createMetadata({
+      alice,
+      store,
+      args: { metadata: { extra }, price: NEAR(0.01) },
+    });
+  }
+  for (const metadata_id of ["0", "1"]) {
+    await mintOnMetadata({
+      bob,
+      store,
+      args: { metadata_id, num_to_mint: 2, owner_id: bob.accountId },
+      deposit: 0.05,
+    });
+  }
+
+  const tokenIds = async (args: Record<string, any>) =>
+    ((await store.view("nft_tokens_by_extra", args)) as any[]).map(
+      (token) => token.token_id
+    );
+
+  test.deepEqual(await tokenIds({ substring: "blue" }), ["1:0", "1:1"]);
+  test.deepEqual(await tokenIds({ substring: "background" }), [
+    "0:0",
+    "0:1",
+    "1:0",
+    "1:1",
+  ]);
+  test.deepEqual(
+    await tokenIds({ substring: "background", from_index: "1", limit: 2 }),
+    ["0:1", "1:0"]
+  );
+  test.deepEqual(await tokenIds({ substring: "green" }), []);
+});
+
+test("v2::transfer_fee", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, carol, store } = test.context.accounts;
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01) },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+
+  // only the store owner can set the fee
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "set_transfer_fee",
+        { transfer_fee: [bob.accountId, NEAR(0.01).toString()] },
+        { attachedDeposit: "1" }
+      );
+    },
+    "This method can only be called by the store owner",
+    "Setting transfer fee as non-owner"
+  );
+  const setFeeCall = await alice.callRaw(
+    store,
+    "set_transfer_fee",
+    { transfer_fee: [alice.accountId, NEAR(0.01).toString()] },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    setFeeCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: CHANGE_SETTING_VERSION,
+        event: "change_setting",
+        data: changeSettingsData({
+          new_transfer_fee: [alice.accountId, NEAR(0.01).toString()],
+        }),
+      },
+    ],
+    "setting transfer fee"
+  );
+  test.deepEqual(await store.view("get_transfer_fee"), [
+    alice.accountId,
+    NEAR(0.01).toString(),
+  ]);
+
+  // transferring without covering the fee fails
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "nft_transfer",
+        { receiver_id: carol.accountId, token_id: "0:0" },
+        { attachedDeposit: "1" }
+      );
+    },
+    `Requires attached deposit of at least ${NEAR(0.01)
+      .add(new BN(1))
+      .toString()} yoctoNEAR to cover the transfer fee`,
+    "Transferring without covering the fee"
+  );
+
+  const transferCall = await bob.callRaw(
+    store,
+    "nft_transfer",
+    { receiver_id: carol.accountId, token_id: "0:0" },
+    { attachedDeposit: NEAR(0.01).add(new BN(1)) }
+  );
+  assertEventLogs(
+    test,
+    transferCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: "2.0.0",
+        event: "transfer_fee",
+        data: {
+          token_ids: ["0:0"],
+          payer_id: bob.accountId,
+          fee_recipient: alice.accountId,
+          amount: NEAR(0.01).toString(),
+        },
+      },
+      {
+        standard: "nep171",
+        version: "1.0.0",
+        event: "nft_transfer",
+        data: [
+          {
+            authorized_id: null,
+            old_owner_id: bob.accountId,
+            new_owner_id: carol.accountId,
+            token_ids: ["0:0"],
+            memo: null,
+          },
+        ],
+      },
+    ],
+    "transferring with fee"
+  );
+
+  // batch transfers and gifts are charged as well
+  await assertContractPanic(
+    test,
+    async () => {
+      await carol.call(
+        store,
+        "nft_batch_transfer",
+        { token_ids: [["0:0", bob.accountId]] },
+        { attachedDeposit: "1" }
+      );
+    },
+    `Requires attached deposit of at least ${NEAR(0.01)
+      .add(new BN(1))
+      .toString()} yoctoNEAR to cover the transfer fee`,
+    "Batch transferring without covering the fee"
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await carol.call(
+        store,
+        "nft_gift",
+        { receiver_id: bob.accountId, token_id: "0:0" },
+        { attachedDeposit: "1" }
+      );
+    },
+    `Requires attached deposit of at least ${NEAR(0.01)
+      .add(new BN(1))
+      .toString()} yoctoNEAR to cover the transfer fee`,
+    "Gifting without covering the fee"
+  );
+
+  // deposit exceeding the fee is refunded
+  const carolBalance = await getBalance(carol);
+  await carol.call(
+    store,
+    "nft_batch_transfer",
+    { token_ids: [["0:0", bob.accountId]] },
+    { attachedDeposit: NEAR(1) }
+  );
+  test.true(
+    carolBalance.sub(await getBalance(carol)).lt(NEAR(0.02)),
+    "Surplus deposit was not refunded"
+  );
+
+  // removing the fee restores the one yocto requirement
+  const unsetFeeCall = await alice.callRaw(
+    store,
+    "set_transfer_fee",
+    { transfer_fee: null },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    unsetFeeCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: CHANGE_SETTING_VERSION,
+        event: "change_setting",
+        data: changeSettingsData({ new_transfer_fee: null }),
+      },
+    ],
+    "removing transfer fee"
+  );
+  test.is(await store.view("get_transfer_fee"), null);
+  await bob.call(
+    store,
+    "nft_transfer",
+    { receiver_id: carol.accountId, token_id: "0:0" },
+    { attachedDeposit: "1" }
+  );
+});
+
+test("v2::global_mint_rate_limit", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01) },
+  });
+
+  const setLimitCall = await alice.callRaw(
+    store,
+    "set_global_mint_rate_limit",
+    { global_mint_rate_limit: [2, 3600] },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    setLimitCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: CHANGE_SETTING_VERSION,
+        event: "change_setting",
+        data: changeSettingsData({ new_global_mint_rate_limit: [2, 3600] }),
+      },
+    ],
+    "setting global mint rate limit"
+  );
+  test.deepEqual(await store.view("get_global_mint_rate_limit"), [2, 3600]);
+
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 2, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+  test.is(await store.view("get_tokens_minted_in_range"), 2);
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await mintOnMetadata({
+        bob,
+        store,
+        args: { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+        deposit: 0.05,
+      });
+    },
+    "Global mint rate limit of 2 tokens per 3600 seconds reached, retry in",
+    "Minting beyond the global rate limit"
+  );
+
+  // lifting the limit allows minting again
+  const unsetLimitCall = await alice.callRaw(
+    store,
+    "set_global_mint_rate_limit",
+    { global_mint_rate_limit: null },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    unsetLimitCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: CHANGE_SETTING_VERSION,
+        event: "change_setting",
+        data: changeSettingsData({ new_global_mint_rate_limit: null }),
+      },
+    ],
+    "lifting global mint rate limit"
+  );
+  test.is(await store.view("get_tokens_minted_in_range"), 0);
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+});
+
+test("v2::per_token_overrides", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  await createMetadata({
+    alice,
+    store,
+    args: {
+      metadata: { reference: "shared-ref", media: "shared-media" },
+      price: NEAR(0.01),
+    },
+  });
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await mintOnMetadata({
+        bob,
+        store,
+        args: {
+          metadata_id: "0",
+          num_to_mint: 2,
+          owner_id: bob.accountId,
+          per_token_overrides: [{ reference: "ref-0" }],
+        },
+        deposit: 0.05,
+      });
+    },
+    "Expected 2 per-token overrides, got 1",
+    "Minting with wrong number of overrides"
+  );
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await mintOnMetadata({
+        bob,
+        store,
+        args: {
+          metadata_id: "0",
+          num_to_mint: 1,
+          owner_id: bob.accountId,
+          per_token_overrides: [{ media: "x".repeat(257) }],
+        },
+        deposit: 0.05,
+      });
+    },
+    "Per-token overrides may not exceed 256 characters",
+    "Minting with oversized override"
+  );
+
+  await mintOnMetadata({
+    bob,
+    store,
+    args: {
+      metadata_id: "0",
+      num_to_mint: 2,
+      owner_id: bob.accountId,
+      per_token_overrides: [
+        { reference: "ref-0", media: "media-0" },
+        { reference: null, media: null },
+      ],
+    },
+    deposit: 0.05,
+  });
+
+  const token0: any = await store.view("nft_token", { token_id: "0:0" });
+  test.is(token0.metadata.reference, "ref-0");
+  test.is(token0.metadata.media, "media-0");
+  const token1: any = await store.view("nft_token", { token_id: "0:1" });
+  test.is(token1.metadata.reference, "shared-ref");
+  test.is(token1.metadata.media, "shared-media");
+});
+
+test("v2::primary_split", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, carol, dave, store } = test.context.accounts;
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await createMetadata({
+        alice,
+        store,
+        args: {
+          metadata: {},
+          price: NEAR(1),
+          primary_split: { [carol.accountId]: 6000, [dave.accountId]: 3000 },
+        },
+      });
+    },
+    "Splits numerators must sum up to 10_000",
+    "Creating metadata with invalid primary split"
+  );
+
+  await createMetadata({
+    alice,
+    store,
+    args: {
+      metadata: {},
+      price: NEAR(1),
+      primary_split: { [carol.accountId]: 6000, [dave.accountId]: 4000 },
+    },
+  });
+  test.deepEqual(await store.view("get_primary_split", { metadata_id: "0" }), {
+    [carol.accountId]: 6000,
+    [dave.accountId]: 4000,
+  });
+
+  const carolBalance = await getBalance(carol);
+  const daveBalance = await getBalance(dave);
+  await mintOnMetadata({
+    bob,
+    store,
+    args: {
+      metadata_id: "0",
+      num_to_mint: 1,
+      owner_id: bob.accountId,
+    },
+    deposit: 1,
+  });
+  await assertBalanceChanges(
+    test,
+    [
+      { account: carol, ref: carolBalance, diff: NEAR(0.6) },
+      { account: dave, ref: daveBalance, diff: NEAR(0.4) },
+    ],
+    "Minting with primary split"
+  );
+});
+
+test("v2::royalty_currency_hint", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01) },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+  test.is(
+    await store.view("get_preferred_sale_currency", { token_id: "0:0" }),
+    null
+  );
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "set_royalty_currency_hint",
+        { metadata_id: "0", ft_contract_id: "usdc.near" },
+        { attachedDeposit: "1" }
+      );
+    },
+    "This method can only be called by the metadata creator",
+    "Non-creator setting royalty currency hint"
+  );
+
+  // storing a hint requires a storage deposit
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        store,
+        "set_royalty_currency_hint",
+        { metadata_id: "0", ft_contract_id: "usdc.near" },
+        { attachedDeposit: "1" }
+      );
+    },
+    `Requires storage deposit of at least ${mNEAR(0.8)}`,
+    "Setting royalty currency hint without storage deposit"
+  );
+  await alice.call(
+    store,
+    "set_royalty_currency_hint",
+    { metadata_id: "0", ft_contract_id: "usdc.near" },
+    { attachedDeposit: mNEAR(0.8) }
+  );
+  test.is(
+    await store.view("get_preferred_sale_currency", { token_id: "0:0" }),
+    "usdc.near"
+  );
+
+  // replacing a hint does not require another deposit
+  await alice.call(
+    store,
+    "set_royalty_currency_hint",
+    { metadata_id: "0", ft_contract_id: "usdt.near" },
+    { attachedDeposit: "1" }
+  );
+  test.is(
+    await store.view("get_preferred_sale_currency", { token_id: "0:0" }),
+    "usdt.near"
+  );
+
+  await alice.call(
+    store,
+    "set_royalty_currency_hint",
+    { metadata_id: "0", ft_contract_id: null },
+    { attachedDeposit: "1" }
+  );
+  test.is(
+    await store.view("get_preferred_sale_currency", { token_id: "0:0" }),
+    null
+  );
+});
+
+test("v2::default_mint_window", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  // window starting in the year 2100
+  const window = ["4102444800000000000", "4133980800000000000"];
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(
+        store,
+        "set_default_mint_window",
+        { default_mint_window: [window[1], window[0]] },
+        { attachedDeposit: "1" }
+      );
+    },
+    "Mint window must start before it expires",
+    "Setting inverted default mint window"
+  );
+
+  const setWindowCall = await alice.callRaw(
+    store,
+    "set_default_mint_window",
+    { default_mint_window: window },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    setWindowCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: CHANGE_SETTING_VERSION,
+        event: "change_setting",
+        data: changeSettingsData({ new_default_mint_window: window }),
+      },
+    ],
+    "setting default mint window"
+  );
+  test.deepEqual(await store.view("get_default_mint_window"), window);
+
+  // metadata without own window inherits the default
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01) },
+  });
+  await assertContractPanic(
+    test,
+    async () => {
+      await mintOnMetadata({
+        bob,
+        store,
+        args: { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+        deposit: 0.05,
+      });
+    },
+    "This metadata has not yet started and cannot be minted on",
+    "Minting before default mint window"
+  );
+
+  // own start after the default expiry results in an invalid window
+  await assertContractPanic(
+    test,
+    async () => {
+      await createMetadata({
+        alice,
+        store,
+        args: {
+          metadata: {},
+          price: NEAR(0.01),
+          starts_at: "4165516800000000000",
+        },
+      });
+    },
+    "Mint window must start before it expires",
+    "Creating metadata that starts after the default expiry"
+  );
+
+  // metadata with own window overrides the default
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01), starts_at: "0" },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "1", num_to_mint: 1, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+});
+
+test("v2::metadata_states", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+
+  // sold out after a single mint
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01), max_supply: 1 },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+  // starting in the year 2100
+  await createMetadata({
+    alice,
+    store,
+    args: {
+      metadata: {},
+      price: NEAR(0.02),
+      starts_at: "4102444800000000000",
+    },
+  });
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.03) },
+  });
+
+  test.deepEqual(
+    await store.view("get_metadata_states", {
+      metadata_ids: ["0", "1", "2", "3"],
+    }),
+    [
+      {
+        is_locked: true,
+        minted: 1,
+        max_supply: 1,
+        price: NEAR(0.01).toString(),
+        mintable_now: false,
+      },
+      {
+        is_locked: true,
+        minted: 0,
+        max_supply: null,
+        price: NEAR(0.02).toString(),
+        mintable_now: false,
+      },
+      {
+        is_locked: true,
+        minted: 0,
+        max_supply: null,
+        price: NEAR(0.03).toString(),
+        mintable_now: true,
+      },
+      null,
+    ]
+  );
+
+  // at most 100 metadata per query
+  await test.throwsAsync(async () => {
+    await store.view("get_metadata_states", {
+      metadata_ids: [...Array(101).keys()].map((id) => `${id}`),
+    });
+  });
+});
+
+test("v2::metadata_payment", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+
+  await createMetadata({
+    alice,
+    store,
+    args: {
+      metadata: {},
+      price: NEAR(0.01),
+      ft_contract_id: bob.accountId,
+    },
+  });
+
+  // only the creator may change the payment
+  await assertContractPanic(
+    test,
+    async () =>
+      bob.call(
+        store,
+        "set_metadata_payment",
+        { metadata_id: "0", ft_contract_id: null, price: NEAR(0.02) },
+        { attachedDeposit: "1" }
+      ),
+    "This method can only be called by the metadata creator",
+    "Changing payment by non-creator"
+  );
+
+  // switch to NEAR before the first mint
+  const setPaymentCall = await alice.callRaw(
+    store,
+    "set_metadata_payment",
+    { metadata_id: "0", ft_contract_id: null, price: NEAR(0.02) },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    (setPaymentCall as TransactionResult).logs,
+    [
+      {
+        standard: "mb_store",
+        version: "2.0.0",
+        event: "minting_metadata_update",
+        data: {
+          metadata_id: "0",
+          minters_allowlist: null,
+          price: NEAR(0.02).toString(),
+          is_dynamic: null,
+          currency: "near",
+        },
+      },
+    ],
+    "changing payment"
+  );
+  const [state] = (await store.view("get_metadata_states", {
+    metadata_ids: ["0"],
+  })) as any[];
+  test.is(state.price, NEAR(0.02).toString());
+
+  // minting with NEAR works now, after which the payment is frozen
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+  await assertContractPanic(
+    test,
+    async () =>
+      alice.call(
+        store,
+        "set_metadata_payment",
+        { metadata_id: "0", ft_contract_id: bob.accountId, price: null },
+        { attachedDeposit: "1" }
+      ),
+    "Payment cannot be changed after tokens have been minted",
+    "Changing payment after minting"
+  );
+});
+
+test("v2::per_minter_cap", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+
+  await assertContractPanic(
+    test,
+    async () =>
+      alice.call(
+        store,
+        "create_metadata",
+        { metadata: {}, price: NEAR(0.01), per_minter_cap: 0 },
+        { attachedDeposit: NEAR(0.1) }
+      ),
+    "`per_minter_cap` needs to be greater than zero",
+    "Creating metadata with a zero cap"
+  );
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01), per_minter_cap: 2 },
+  });
+  test.is(await store.view("get_per_minter_cap", { metadata_id: "0" }), 2);
+
+  // bob can mint up to the cap, but not beyond it
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 2, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+  test.is(
+    await store.view("get_minted_by_minter", {
+      metadata_id: "0",
+      account_id: bob.accountId,
+    }),
+    2
+  );
+  await assertContractPanic(
+    test,
+    async () =>
+      bob.call(
+        store,
+        "mint_on_metadata",
+        { metadata_id: "0", num_to_mint: 1, owner_id: bob.accountId },
+        { attachedDeposit: NEAR(0.05) }
+      ),
+    `${bob.accountId} may not mint more than 2 tokens on this metadata`,
+    "Minting beyond the per-minter cap"
+  );
+
+  // other accounts are not affected
+  await mintOnMetadata({
+    bob: alice,
+    store,
+    args: { metadata_id: "0", num_to_mint: 2, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+  test.is(
+    await store.view("get_minted_by_minter", {
+      metadata_id: "0",
+      account_id: alice.accountId,
+    }),
+    2
+  );
+});
+
+test("v2::burn_sink", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  const sink = "burn.near";
+
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01) },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 2, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+
+  const setSinkCall = await alice.callRaw(
+    store,
+    "set_burn_sink_account",
+    { account_id: sink },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    setSinkCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: CHANGE_SETTING_VERSION,
+        event: "change_setting",
+        data: changeSettingsData({ new_burn_sink_account: sink }),
+      },
+    ],
+    "setting burn sink"
+  );
+  test.is(await store.view("get_burn_sink_account"), sink);
+
+  // other transfer methods refuse the sink instead of stranding tokens
+  const sinkPanic = `${sink} is the burn sink, use nft_transfer to burn tokens`;
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "nft_transfer_call",
+        { receiver_id: sink, token_id: "0:0", msg: "" },
+        { attachedDeposit: "1", gas: Tgas(200) }
+      );
+    },
+    sinkPanic,
+    "nft_transfer_call to burn sink"
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "nft_batch_transfer",
+        { token_ids: [["0:0", sink]] },
+        { attachedDeposit: "1" }
+      );
+    },
+    sinkPanic,
+    "nft_batch_transfer to burn sink"
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "nft_send_all",
+        { token_ids: ["0:0"], receiver_id: sink },
+        { attachedDeposit: "1" }
+      );
+    },
+    sinkPanic,
+    "nft_send_all to burn sink"
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "nft_batch_transfer_call",
+        { token_ids: ["0:0"], receiver_id: sink, msg: "" },
+        { attachedDeposit: "1", gas: Tgas(200) }
+      );
+    },
+    sinkPanic,
+    "nft_batch_transfer_call to burn sink"
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await bob.call(
+        store,
+        "nft_gift",
+        { receiver_id: sink, token_id: "0:0" },
+        { attachedDeposit: "1" }
+      );
+    },
+    sinkPanic,
+    "nft_gift to burn sink"
+  );
+
+  // transferring to the sink burns the token
+  const transferCall = await bob.callRaw(
+    store,
+    "nft_transfer",
+    { receiver_id: sink, token_id: "0:0", memo: "bye" },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    transferCall.logs,
+    [
+      {
+        standard: "nep171",
+        version: "1.0.0",
+        event: "nft_burn",
+        data: [
+          {
+            owner_id: bob.accountId,
+            authorized_id: null,
+            token_ids: ["0:0"],
+            memo: "bye",
+          },
+        ],
+      },
+    ],
+    "transferring to burn sink"
+  );
+  test.is(await store.view("nft_token_status", { token_id: "0:0" }), "Burned");
+  test.is(
+    await store.view("nft_supply_for_owner", { account_id: bob.accountId }),
+    "1"
+  );
+
+  // without a sink, the account receives tokens as usual
+  const unsetSinkCall = await alice.callRaw(
+    store,
+    "set_burn_sink_account",
+    { account_id: null },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    unsetSinkCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: CHANGE_SETTING_VERSION,
+        event: "change_setting",
+        data: changeSettingsData({ new_burn_sink_account: null }),
+      },
+    ],
+    "unsetting burn sink"
+  );
+  await bob.call(
+    store,
+    "nft_transfer",
+    { receiver_id: sink, token_id: "0:1" },
+    { attachedDeposit: "1" }
+  );
+  test.is(
+    ((await store.view("nft_token", { token_id: "0:1" })) as any).owner_id,
+    sink
+  );
+});
+
+test("v2::approval_expiry", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, carol, store } = test.context.accounts;
+
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01) },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 2, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+
+  // expiry must be in the future
+  await assertContractPanic(
+    test,
+    async () =>
+      bob.call(
+        store,
+        "nft_approve",
+        {
+          token_id: "0:0",
+          account_id: carol.accountId,
+          expires_at: ((Date.now() - 1000) * 1e6).toString(),
+        },
+        { attachedDeposit: NEAR(0.01) }
+      ),
+    "Approval expiry must be in the future",
+    "Approving with expiry in the past"
+  );
+  // storing the expiry requires the deposit of another approval
+  await assertContractPanic(
+    test,
+    async () =>
+      bob.call(
+        store,
+        "nft_approve",
+        {
+          token_id: "0:0",
+          account_id: carol.accountId,
+          expires_at: ((Date.now() + 3000) * 1e6).toString(),
+        },
+        { attachedDeposit: mNEAR(0.8) }
+      ),
+    `Requires storage deposit of at least ${mNEAR(1.6)}`,
+    "Approving with expiry without deposit for the expiry"
+  );
+
+  // approval that expires shortly
+  await bob.call(
+    store,
+    "nft_approve",
+    {
+      token_id: "0:0",
+      account_id: carol.accountId,
+      expires_at: ((Date.now() + 3000) * 1e6).toString(),
+    },
+    { attachedDeposit: NEAR(0.01) }
+  );
+  // approval without expiry
+  await bob.call(
+    store,
+    "nft_approve",
+    { token_id: "0:1", account_id: carol.accountId },
+    { attachedDeposit: NEAR(0.01) }
+  );
+  test.true(
+    await store.view("nft_is_approved", {
+      token_id: "0:0",
+      approved_account_id: carol.accountId,
+      approval_id: 0,
+    })
+  );
+
+  await new Promise((resolve) => setTimeout(resolve, 5000));
+
+  test.false(
+    await store.view("nft_is_approved", {
+      token_id: "0:0",
+      approved_account_id: carol.accountId,
+      approval_id: 0,
+    })
+  );
+  await assertContractPanic(
+    test,
+    async () =>
+      carol.call(
+        store,
+        "nft_transfer",
+        { receiver_id: carol.accountId, token_id: "0:0", approval_id: 0 },
+        { attachedDeposit: "1" }
+      ),
+    `Approval of ${carol.accountId} for token 0:0 has expired`,
+    "Transferring with expired approval"
+  );
+
+  // approvals without expiry keep working
+  await carol.call(
+    store,
+    "nft_transfer",
+    { receiver_id: carol.accountId, token_id: "0:1", approval_id: 1 },
+    { attachedDeposit: "1" }
+  );
+  test.is(
+    ((await store.view("nft_token", { token_id: "0:1" })) as any).owner_id,
+    carol.accountId
+  );
+});
+
+test("v2::prune_expired_approvals", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, carol, dave, store } = test.context.accounts;
+
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01) },
+  });
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 2, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+
+  const approve = async (
+    token_id: string,
+    account: NearAccount,
+    expires_at?: number
+  ) =>
+    bob.call(
+      store,
+      "nft_approve",
+      {
+        token_id,
+        account_id: account.accountId,
+        expires_at: expires_at ? (expires_at * 1e6).toString() : null,
+      },
+      { attachedDeposit: NEAR(0.01) }
+    );
+  // expires shortly
+  await approve("0:0", carol, Date.now() + 3000);
+  // expires far in the future
+  await approve("0:0", dave, Date.now() + 3600e3);
+  // never expires
+  await approve("0:1", carol);
+
+  // only the owner may prune
+  await assertContractPanic(
+    test,
+    async () =>
+      carol.call(
+        store,
+        "prune_expired_approvals",
+        { token_ids: ["0:0"] },
+        { attachedDeposit: "1" }
+      ),
+    `${carol.accountId} is required to own token 0`,
+    "Non-owner pruning approvals"
+  );
+
+  // nothing is pruned before the expiry has been reached
+  const earlyCall = await bob.callRaw(
+    store,
+    "prune_expired_approvals",
+    { token_ids: ["0:0", "0:1"] },
+    { attachedDeposit: "1" }
+  );
+  test.deepEqual(earlyCall.logs, []);
+
+  await new Promise((resolve) => setTimeout(resolve, 5000));
+
+  const pruneCall = await bob.callRaw(
+    store,
+    "prune_expired_approvals",
+    { token_ids: ["0:0", "0:1"] },
+    { attachedDeposit: "1" }
+  );
+  assertEventLogs(
+    test,
+    pruneCall.logs,
+    [
+      {
+        standard: "mb_store",
+        version: "0.1.0",
+        event: "nft_revoke",
+        data: { token_id: "0:0", account_id: carol.accountId },
+      },
+    ],
+    "pruning expired approvals"
+  );
+  const approvalId = (token_id: string, account: NearAccount) =>
+    store.view("nft_approval_id", { token_id, account_id: account.accountId });
+  test.is(await approvalId("0:0", carol), null);
+  test.is(await approvalId("0:0", dave), 1);
+  test.is(await approvalId("0:1", carol), 2);
+});
+
+test("v2::deterministic_metadata_id", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, store } = test.context.accounts;
+  const metadata = { title: "deterministic" };
+
+  const metadataId: string = await store.view("derive_metadata_id", {
+    metadata,
+  });
+  const createCall = await alice.callRaw(
+    store,
+    "create_metadata_deterministic",
+    { metadata, price: NEAR(0.01) },
+    { attachedDeposit: NEAR(0.1) }
+  );
+  test.is(createCall.parseResult(), metadataId);
+  test.is(
+    ((await store.view("get_metadata", { metadata_id: metadataId })) as any)
+      .title,
+    "deterministic"
+  );
+
+  // same metadata yields the same ID
+  await assertContractPanic(
+    test,
+    async () =>
+      alice.call(
+        store,
+        "create_metadata_deterministic",
+        { metadata, price: NEAR(0.01) },
+        { attachedDeposit: NEAR(0.1) }
+      ),
+    `Metadata ID ${metadataId} already exists`,
+    "Creating deterministic metadata twice"
+  );
+
+  // counter-based IDs are unaffected
+  const counterCall = await alice.callRaw(
+    store,
+    "create_metadata",
+    { metadata: {}, price: NEAR(0.01) },
+    { attachedDeposit: NEAR(0.1) }
+  );
+  test.is(counterCall.parseResult(), "0");
+});
+
+test("v2::next_metadata_id", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, store } = test.context.accounts;
+
+  test.is(await store.view("get_next_metadata_id"), "0");
+  // explicit IDs fill the gaps that would be auto-assigned
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, metadata_id: "0", price: NEAR(0.01) },
+  });
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, metadata_id: "1", price: NEAR(0.01) },
+  });
+  test.is(await store.view("get_next_metadata_id"), "2");
+
+  const call = await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01) },
+  });
+  test.is(call.parseResult(), "2");
+  test.is(await store.view("get_next_metadata_id"), "3");
+});
+
+test("v2::storage_management", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { carol, store } = test.context.accounts;
+  const storageBalance = async (): Promise<any> =>
+    store.view("storage_balance_of", { account_id: carol.accountId });
+
+  const bounds: any = await store.view("storage_balance_bounds");
+  test.is(bounds.max, null);
+  test.is(await storageBalance(), null);
+
+  // registration requires the minimum deposit
+  await assertContractPanic(
+    test,
+    async () =>
+      carol.call(
+        store,
+        "storage_deposit",
+        {},
+        { attachedDeposit: new BN(bounds.min).subn(1).toString() }
+      ),
+    `The attached deposit is less than the minimum storage balance of ${bounds.min} yoctoNEAR`,
+    "Registering with insufficient deposit"
+  );
+
+  // registration only keeps the minimum
+  await carol.call(
+    store,
+    "storage_deposit",
+    { registration_only: true },
+    { attachedDeposit: NEAR(0.5) }
+  );
+  const registered = await storageBalance();
+  test.is(registered.total, registered.available);
+  test.true(new BN(registered.total).lt(new BN(bounds.min)));
+  test.true(new BN(registered.total).gtn(0));
+
+  // registered accounts can top up
+  await carol.call(
+    store,
+    "storage_deposit",
+    { account_id: carol.accountId },
+    { attachedDeposit: NEAR(0.1) }
+  );
+  const toppedUp = new BN(registered.total).add(NEAR(0.1));
+  test.is((await storageBalance()).total, toppedUp.toString());
+
+  // withdrawals are limited to the available balance
+  await assertContractPanic(
+    test,
+    async () =>
+      carol.call(
+        store,
+        "storage_withdraw",
+        { amount: toppedUp.addn(1).toString() },
+        { attachedDeposit: "1" }
+      ),
+    `Cannot withdraw more than the available ${toppedUp} yoctoNEAR`,
+    "Withdrawing more than available"
+  );
+  await carol.call(
+    store,
+    "storage_withdraw",
+    { amount: NEAR(0.1) },
+    { attachedDeposit: "1" }
+  );
+  test.is((await storageBalance()).total, registered.total);
+  await carol.call(store, "storage_withdraw", {}, { attachedDeposit: "1" });
+  test.is((await storageBalance()).total, "0");
+});
+
+test("v2::soulbound", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, carol, store } = test.context.accounts;
+
+  await createMetadata({
+    alice,
+    store,
+    args: { metadata: {}, price: NEAR(0.01), soulbound: true },
+  });
+  test.true(await store.view("is_metadata_soulbound", { metadata_id: "0" }));
+  await mintOnMetadata({
+    bob,
+    store,
+    args: { metadata_id: "0", num_to_mint: 2, owner_id: bob.accountId },
+    deposit: 0.05,
+  });
+
+  // soulbound tokens can be neither transferred nor approved
+  await assertContractPanic(
+    test,
+    async () =>
+      bob.call(
+        store,
+        "nft_transfer",
+        { receiver_id: carol.accountId, token_id: "0:0" },
+        { attachedDeposit: "1" }
+      ),
+    "Token 0:0 is soulbound and cannot be transferred",
+    "Transferring a soulbound token"
+  );
+  await assertContractPanic(
+    test,
+    async () =>
+      bob.call(
+        store,
+        "nft_batch_transfer",
+        { token_ids: [["0:0", carol.accountId]] },
+        { attachedDeposit: "1" }
+      ),
+    "Token 0:0 is soulbound and cannot be transferred",
+    "Batch transferring a soulbound token"
+  );
+  await assertContractPanic(
+    test,
+    async () =>
+      bob.call(
+        store,
+        "nft_approve",
+        { token_id: "0:0", account_id: carol.accountId },
+        { attachedDeposit: mNEAR(0.81) }
+      ),
+    "Token 0:0 is soulbound and cannot be approved",
+    "Approving a soulbound token"
+  );
+
+  // burning is still allowed
+  await bob.call(
+    store,
+    "nft_batch_burn",
+    { token_ids: ["0:0"] },
+    { attachedDeposit: "1" }
+  );
+  test.is(await store.view("nft_token", { token_id: "0:0" }), null);
+});
+
+test("v2::batch_deposit_storage_for_metadata", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { alice, bob, store } = test.context.accounts;
+  for (const metadata_id of ["0", "1"]) {
+    await createMetadata({
+      alice,
+      store,
+      args: { metadata_id, metadata: {}, price: NEAR(0.01) },
+    });
+  }
+
+  await assertContractPanic(
+    test,
+    async () =>
+      alice.call(
+        store,
+        "batch_deposit_storage_for_metadata",
+        { metadata_ids: ["0", "1"], amounts: [NEAR(0.02)] },
+        { attachedDeposit: NEAR(0.1) }
+      ),
+    "Number of metadata IDs and amounts must be equal",
+    "Batch depositing with mismatched amounts"
+  );
+  await assertContractPanic(
+    test,
+    async () =>
+      alice.call(
+        store,
+        "batch_deposit_storage_for_metadata",
+        { metadata_ids: ["0", "1"], amounts: [NEAR(0.02), NEAR(0.03)] },
+        { attachedDeposit: NEAR(0.04) }
+      ),
+    `Attached deposit of ${NEAR(0.04)} yoctoNEAR does not cover the total of ${NEAR(0.05)} yoctoNEAR`,
+    "Batch depositing with insufficient deposit"
+  );
+
+  await alice.call(
+    store,
+    "batch_deposit_storage_for_metadata",
+    { metadata_ids: ["0", "1"], amounts: [NEAR(0.02), NEAR(0.03)] },
+    { attachedDeposit: NEAR(0.1) }
+  );
+
+  // bob has no storage deposit, but both metadata are sponsored
+  for (const metadata_id of ["0", "1"]) {
+    await bob.call(
+      store,
+      "mint_on_metadata",
+      { metadata_id, num_to_mint: 1, owner_id: bob.accountId },
+      { attachedDeposit: NEAR(0.01) }
+    );
+  }
+});
@@ -1,4 +1,4 @@
-import { TransactionResult } from "near-workspaces";
+import { BN, TransactionResult } from "near-workspaces";
 import avaTest from "ava";
 import {
   assertContractPanics,
@@ -309,3 +309,86 @@ test("market::auction", async (test) => {
     ),
   ]);
 });
+
+test("market::auction-min-increment", async (test) => {
+  if (MB_VERSION !== "v1") {
+    test.pass();
+    return;
+  }
+
+  const {
+    root,
+    factory,
+    store,
+    oldMarket: market,
+    alice,
+    bob,
+    carol,
+  } = test.context.accounts;
+  await prepareTokenListing(test, { root, alice, store, market, factory });
+
+  await market.call(
+    market,
+    "set_min_offer_increment_bps",
+    { min_offer_increment_bps: 1000 },
+    { attachedDeposit: "1" }
+  );
+  test.is(await market.view("get_min_offer_increment_bps"), 1000);
+
+  await alice.call(
+    store,
+    "nft_approve",
+    {
+      token_id: "0",
+      account_id: market.accountId,
+      msg: JSON.stringify({ price: NEAR(1), autotransfer: false }),
+    },
+    { attachedDeposit: mNEAR(0.81), gas: Tgas(200) }
+  );
+  const tokenKey = `0:${store.accountId}`;
+
+  await bob.call(
+    market,
+    "make_offer",
+    { token_key: [tokenKey], price: [NEAR(1)], timeout: [{ Hours: 24 }] },
+    { attachedDeposit: NEAR(1), gas: Tgas(200) }
+  );
+
+  // just below the 10% increment
+  const belowIncrement = NEAR(1.1).sub(new BN(1));
+  await assertContractPanics(test, [
+    [
+      async () => {
+        await carol.call(
+          market,
+          "make_offer",
+          {
+            token_key: [tokenKey],
+            price: [belowIncrement],
+            timeout: [{ Hours: 24 }],
+          },
+          { attachedDeposit: belowIncrement, gas: Tgas(200) }
+        );
+      },
+      `The offer must be at least ${NEAR(
+        1.1
+      )} to exceed the current offer price of ${NEAR(
+        1
+      )} by the minimum increment`,
+      "Outbidding below minimum increment",
+    ],
+  ]);
+
+  // exactly at the 10% increment
+  await carol.call(
+    market,
+    "make_offer",
+    { token_key: [tokenKey], price: [NEAR(1.1)], timeout: [{ Hours: 24 }] },
+    { attachedDeposit: NEAR(1.1), gas: Tgas(200) }
+  );
+  test.like(
+    await market.view("get_current_offer", { token_key: tokenKey }),
+    { id: 2, price: NEAR(1.1).toString() },
+    "Highest offer not replaced"
+  );
+});
@@ -0,0 +1,159 @@
+import avaTest from "ava";
+import { BN } from "near-workspaces";
+import { assertContractPanic, NEAR } from "./utils/index.js";
+import { setup, createAndDeploy, MB_VERSION } from "./setup.js";
+
+const test = setup(avaTest);
+
+// Borsh serialization of a NEAR listing without a current offer
+const borshListing = ({
+  tokenId,
+  approvalId,
+  ownerId,
+  contractId,
+  price,
+  createdAt,
+}: {
+  tokenId: string;
+  approvalId: number;
+  ownerId: string;
+  contractId: string;
+  price: string;
+  createdAt: string;
+}): Buffer => {
+  const string = (s: string) => {
+    const len = Buffer.alloc(4);
+    len.writeUInt32LE(s.length);
+    return Buffer.concat([len, Buffer.from(s)]);
+  };
+  const u64 = (n: number | string) => new BN(n).toArrayLike(Buffer, "le", 8);
+  const u128 = (n: string) => new BN(n).toArrayLike(Buffer, "le", 16);
+
+  return Buffer.concat([
+    string(tokenId),
+    u64(approvalId),
+    string(ownerId),
+    string(contractId),
+    u128(price),
+    Buffer.from([0]), // `Currency::Near`
+    u64(createdAt),
+    Buffer.from([0]), // no current offer
+  ]);
+};
+
+test("testing::debug_seed_token", async (test) => {
+  if (MB_VERSION == "v1") {
+    test.pass();
+    return;
+  }
+
+  const { root, alice, bob } = test.context.accounts;
+  const store = await createAndDeploy(root, "teststore", {
+    initialBalanceNear: "10",
+    codePath: "../wasm/mb-nft-v2-testing.wasm",
+    initMethod: "new",
+    initArgs: {
+      owner_id: alice.accountId,
+      metadata: { spec: "nft-1.0.0", name: "store", symbol: "ALICE" },
+    },
+  });
+  const token = {
+    id: 0,
+    owner_id: { Account: bob.accountId },
+    approvals: {},
+    metadata_id: 0,
+    royalty_id: null,
+    split_owners: null,
+    minter: alice.accountId,
+    loan: null,
+    composable_stats: { local_depth: 0, cross_contract_children: 0 },
+    origin_key: null,
+  };
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await store.call(store, "debug_seed_token", { token });
+    },
+    "Metadata 0 does not exist",
+    "Seeding token without metadata"
+  );
+
+  await alice.call(
+    store,
+    "create_metadata",
+    { metadata: {}, price: NEAR(0.01) },
+    { attachedDeposit: NEAR(0.1) }
+  );
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(store, "debug_seed_token", { token });
+    },
+    "Method debug_seed_token is private",
+    "Seeding token from another account"
+  );
+
+  await store.call(store, "debug_seed_token", { token });
+  test.is(
+    ((await store.view("nft_token", { token_id: "0:0" })) as any).owner_id,
+    bob.accountId
+  );
+  test.is(
+    await store.view("nft_supply_for_owner", { account_id: bob.accountId }),
+    "1"
+  );
+});
+
+test("testing::debug_seed_listing", async (test) => {
+  const { root, alice, store } = test.context.accounts;
+  const market = await createAndDeploy(root, "testmarket", {
+    initialBalanceNear: "10",
+    codePath: "../wasm/interop-market-testing.wasm",
+    initMethod: "init",
+    initArgs: {
+      owner: root,
+      mintbase_cut: 5000,
+      fallback_cut: 250,
+      listing_lock_seconds: "0",
+    },
+  });
+  const listing = borshListing({
+    tokenId: "0",
+    approvalId: 0,
+    ownerId: alice.accountId,
+    contractId: store.accountId,
+    price: NEAR(1).toString(),
+    createdAt: "0",
+  });
+
+  await assertContractPanic(
+    test,
+    async () => {
+      await alice.call(market, "debug_seed_listing", listing, {
+        attachedDeposit: NEAR(0.01),
+      });
+    },
+    "Method debug_seed_listing is private",
+    "Seeding listing from another account"
+  );
+
+  await market.call(market, "debug_seed_listing", listing, {
+    attachedDeposit: NEAR(0.01),
+  });
+  const listingJson = (await market.view("get_listing", {
+    nft_contract_id: store.accountId,
+    token_id: "0",
+  })) as any;
+  test.is(listingJson.nft_owner_id, alice.accountId);
+  test.is(listingJson.price, NEAR(1).toString());
+  test.is(listingJson.currency, "near");
+  test.is(
+    await market.view("get_listings_count", { account: alice.accountId }),
+    "1"
+  );
+  test.is(
+    await market.view("get_storage_deposit", { account: alice.accountId }),
+    NEAR(0.01).toString()
+  );
+});
@@ -4,10 +4,12 @@ import {
   batchMint,
   downloadContracts,
   failPromiseRejection,
+  getTokenIds,
   mNEAR,
   NEAR,
   Tgas,
 } from "./utils/index.js";
+import { existsSync } from "fs";
 import { readFile } from "fs/promises";
 
 import { setup, createAndDeploy, MB_VERSION } from "./setup.js";
@@ -66,6 +68,9 @@ test("upgrade::mainnet", async (test) => {
 
   // upgrade contracts
   await updateContract(store, `mb-nft-${MB_VERSION}`);
+  await store.call(store, "migrate_metadata", {
+    metadata: { spec: "nft-1.0.0", name: "store", symbol: "ALICE" },
+  });
   await updateContract(factory, `factory-${MB_VERSION}`);
   await updateContract(market, "legacy-market");
 
@@ -102,6 +107,212 @@ test("upgrade::mainnet", async (test) => {
   );
 });
 
+test("upgrade::v1-state-migration", async (test) => {
+  if (MB_VERSION === "v2") {
+    test.pass();
+    return;
+  }
+  const { root, alice, bob } = test.context.accounts;
+  const metadata = { spec: "nft-1.0.0", name: "store", symbol: "ALICE" };
+
+  // deploy a store with the currently deployed state layout
+  await downloadContracts();
+  const store = await createAndDeploy(root, "s", {
+    initialBalanceNear: "10",
+    codePath: "./downloads/mainnet-store.wasm",
+    initMethod: "new",
+    initArgs: { owner_id: alice.accountId, metadata },
+  });
+  await batchMint({ owner: alice, store, num_to_mint: 2 });
+  const referenceToken = await store.view("nft_token", { token_id: "0" });
+
+  // upgrade and migrate
+  await updateContract(store, "mb-nft-v1");
+  await store.call(store, "migrate_metadata", { metadata });
+
+  test.deepEqual(
+    await store.view("nft_token", { token_id: "0" }),
+    referenceToken
+  );
+  test.is(await store.view("nft_total_supply", {}), "2");
+  // without a fee recipient, the parent account receives the minting fee
+  test.is(await store.view("get_fee_recipient"), root.accountId);
+
+  // old and new functionality works on the migrated state
+  await alice.call(
+    store,
+    "nft_transfer",
+    { receiver_id: bob.accountId, token_id: "1" },
+    { attachedDeposit: "1" }
+  );
+  await batchMint({ owner: alice, store, num_to_mint: 1 });
+  test.is(await store.view("nft_total_supply", {}), "3");
+  test.is(await store.view("get_pending_owner"), null);
+  await alice.call(
+    store,
+    "propose_owner",
+    { new_owner: bob.accountId, keep_old_minters: true },
+    { attachedDeposit: "1" }
+  );
+  test.is(await store.view("get_pending_owner"), bob.accountId);
+
+  // migrating a store that already has the current layout keeps its state
+  await store.call(store, "migrate_metadata", { metadata });
+  test.is(await store.view("nft_total_supply", {}), "3");
+  test.is(await store.view("get_pending_owner"), bob.accountId);
+  await bob.call(store, "accept_ownership", {}, { attachedDeposit: "1" });
+  test.is(await store.view("get_owner_id"), bob.accountId);
+  test.like(await store.view("nft_token", { token_id: "1" }), {
+    owner_id: bob.accountId,
+  });
+});
+
+test("upgrade::v2-state-migration", async (test) => {
+  if (MB_VERSION === "v1") {
+    test.pass();
+    return;
+  }
+  // built from `PRE_MIGRATION_REV` by `scripts/build.sh`
+  const preMigrationPath = "../wasm/mb-nft-v2-pre-migration.wasm";
+  if (!existsSync(preMigrationPath)) {
+    test.log("No pre-migration binary, set PRE_MIGRATION_REV when building");
+    test.pass();
+    return;
+  }
+  const { root, alice, bob } = test.context.accounts;
+  const metadata = { spec: "nft-1.0.0", name: "store", symbol: "ALICE" };
+
+  // deploy a store with the state layout prior to the migration
+  const store = await createAndDeploy(root, "s", {
+    initialBalanceNear: "10",
+    codePath: preMigrationPath,
+    initMethod: "new",
+    initArgs: { owner_id: alice.accountId, metadata },
+  });
+  await alice.call(
+    store,
+    "create_metadata",
+    { metadata: {}, price: NEAR(0.01) },
+    { attachedDeposit: NEAR(0.1) }
+  );
+  await bob.call(store, "deposit_storage", {}, { attachedDeposit: NEAR(0.05) });
+  await bob.call(
+    store,
+    "mint_on_metadata",
+    { metadata_id: "0", num_to_mint: 2, owner_id: bob.accountId },
+    { attachedDeposit: NEAR(0.05) }
+  );
+  const referenceToken = await store.view("nft_token", { token_id: "0:0" });
+
+  // upgrade and migrate
+  await updateContract(store, "mb-nft-v2");
+  await store.call(store, "migrate", { metadata });
+
+  test.deepEqual(
+    await store.view("nft_token", { token_id: "0:0" }),
+    referenceToken
+  );
+  test.is(await store.view("nft_total_supply", {}), "2");
+  test.is(await store.view("get_transfer_fee"), null);
+
+  // old and new functionality works on the migrated state
+  await bob.call(
+    store,
+    "nft_transfer",
+    { receiver_id: alice.accountId, token_id: "0:1" },
+    { attachedDeposit: "1" }
+  );
+  await alice.call(
+    store,
+    "create_metadata",
+    { metadata: {}, price: NEAR(0.01), soulbound: true },
+    { attachedDeposit: NEAR(0.1) }
+  );
+  test.true(await store.view("is_metadata_soulbound", { metadata_id: "1" }));
+
+  // migrating a store that already has the current layout keeps its state
+  await store.call(store, "migrate", { metadata });
+  test.true(await store.view("is_metadata_soulbound", { metadata_id: "1" }));
+  test.is(await store.view("nft_total_supply", {}), "2");
+});
+
+test("upgrade::interop-market-state-migration", async (test) => {
+  const { root, alice, bob, store } = test.context.accounts;
+
+  // deploy a market with the currently deployed state layout
+  await downloadContracts();
+  const market = await createAndDeploy(root, "m", {
+    initialBalanceNear: "10",
+    codePath: "./downloads/mainnet-interop-market.wasm",
+    initMethod: "init",
+    initArgs: {
+      owner: root,
+      mintbase_cut: 5000,
+      fallback_cut: 250,
+      listing_lock_seconds: "0",
+    },
+  });
+  const mintCall = await batchMint({ owner: alice, store, num_to_mint: 2 });
+  const [tokenA, tokenB] = getTokenIds(mintCall);
+  await alice.call(
+    market,
+    "deposit_storage",
+    {},
+    { attachedDeposit: mNEAR(20) }
+  );
+  for (const token_id of [tokenA, tokenB]) {
+    await alice.call(
+      store,
+      "nft_approve",
+      {
+        token_id,
+        account_id: market.accountId,
+        msg: JSON.stringify({ price: NEAR(1).toString() }),
+      },
+      { attachedDeposit: mNEAR(8), gas: Tgas(50) }
+    );
+  }
+  const referenceListing = await market.view("get_listing", {
+    nft_contract_id: store.accountId,
+    token_id: tokenA,
+  });
+
+  // upgrade and migrate
+  await updateContract(market, "interop-market");
+  await market.call(market, "migrate", {});
+
+  test.like(
+    await market.view("get_listing", {
+      nft_contract_id: store.accountId,
+      token_id: tokenA,
+    }),
+    referenceListing as Record<string, any>
+  );
+  test.is(await market.view("get_owner"), root.accountId);
+  test.is(await market.view("get_pending_owner"), null);
+
+  // listings from before the migration can be sold
+  await bob.call(
+    market,
+    "buy",
+    { nft_contract_id: store.accountId, token_id: tokenA },
+    { attachedDeposit: NEAR(1), gas: Tgas(200) }
+  );
+  test.like(await store.view("nft_token", { token_id: tokenA }), {
+    owner_id: bob.accountId,
+  });
+
+  // migrating a market that already has the current layout keeps its state
+  await market.call(market, "migrate", {});
+  test.like(
+    await market.view("get_listing", {
+      nft_contract_id: store.accountId,
+      token_id: tokenB,
+    }),
+    { nft_owner_id: alice.accountId }
+  );
+});
+
 interface StateSnapshot {
   aliceDeployed: boolean;
   bobDeployed: boolean;
@@ -52,4 +52,9 @@ export async function downloadContracts() {
   await downloadContract("store", "mainnet", "mintbase.mintbase1.near");
   await downloadContract("factory", "mainnet", "mintbase1.near");
   await downloadContract("legacy-market", "mainnet", "market.mintbase1.near");
+  await downloadContract(
+    "interop-market",
+    "mainnet",
+    "simple.market.mintbase1.near"
+  );
 }
@@ -115,8 +115,8 @@ export function hours(x: number): number {
   return Math.round(x * 3600 * 1e9);
 }
 
-export function changeSettingsData(subset: Record<string, string>) {
-  const data: Record<string, string | null> = {
+export function changeSettingsData(subset: Record<string, unknown>) {
+  const data: Record<string, unknown> = {
     granted_minter: null,
     revoked_minter: null,
     new_icon_base64: null,